@@ -1,6 +1,8 @@
 use bencher::{benchmark_group, benchmark_main};
 
+use std::fs;
 use std::io::{Cursor, Write};
+use std::path::PathBuf;
 
 use bencher::Bencher;
 use ziperu::{ZipArchive, ZipWriter};
@@ -25,6 +27,13 @@ fn generate_random_archive(count_files: usize, file_size: usize) -> Vec<u8> {
     writer.finish().unwrap().into_inner()
 }
 
+/// Write data to a temporary file, so that reading it goes through the file system
+fn temp_file(name: &str, data: &[u8]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("ziperu-bench-{name}-{}", std::process::id()));
+    fs::write(&path, data).unwrap();
+    path
+}
+
 fn read_metadata(bench: &mut Bencher) {
     let bytes = generate_random_archive(FILE_COUNT, FILE_SIZE);
 
@@ -34,5 +43,30 @@ fn read_metadata(bench: &mut Bencher) {
     });
 }
 
-benchmark_group!(benches, read_metadata);
+fn parse_archive_with_comment(bench: &mut Bencher) {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer.start_file("file.dat", Default::default()).unwrap();
+    writer.set_raw_comment(vec![b'c'; u16::MAX as usize]);
+    let path = temp_file("comment", &writer.finish().unwrap().into_inner());
+
+    bench.iter(|| {
+        let archive = ZipArchive::new(fs::File::open(&path).unwrap()).unwrap();
+        archive.len()
+    });
+    fs::remove_file(path).unwrap();
+}
+
+fn parse_non_zip(bench: &mut Bencher) {
+    let path = temp_file("non-zip", &vec![0; 1024 * 1024]);
+
+    bench.iter(|| ZipArchive::new(fs::File::open(&path).unwrap()).is_err());
+    fs::remove_file(path).unwrap();
+}
+
+benchmark_group!(
+    benches,
+    read_metadata,
+    parse_archive_with_comment,
+    parse_non_zip
+);
 benchmark_main!(benches);
@@ -15,10 +15,11 @@ fn real_main() -> i32 {
     let file = fs::File::open(fname).unwrap();
     let reader = BufReader::new(file);
 
-    let mut archive = ziperu::ZipArchive::new(reader).unwrap();
+    let archive = ziperu::ZipArchive::new(reader).unwrap();
 
-    for i in 0..archive.len() {
-        let file = archive.by_index(i).unwrap();
+    // The metadata comes from the central directory, no entry has to be opened
+    for file in archive.entries() {
+        let i = file.index();
         let outpath = match file.enclosed_name() {
             Some(path) => path,
             None => {
@@ -34,7 +35,7 @@ fn real_main() -> i32 {
             }
         }
 
-        if (*file.name()).ends_with('/') {
+        if file.is_dir() {
             println!(
                 "Entry {} is a directory with name \"{}\"",
                 i,
@@ -42,10 +43,12 @@ fn real_main() -> i32 {
             );
         } else {
             println!(
-                "Entry {} is a file with name \"{}\" ({} bytes)",
+                "Entry {} is a file with name \"{}\" ({} bytes, {} compressed with {})",
                 i,
                 outpath.display(),
-                file.size()
+                file.size(),
+                file.compressed_size(),
+                file.compression()
             );
         }
     }
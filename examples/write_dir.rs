@@ -1,12 +1,8 @@
-use std::io::prelude::*;
-use std::io::{Seek, Write};
-use std::iter::Iterator;
 use ziperu::result::ZipError;
 use ziperu::write::FileOptions;
 
 use std::fs::File;
 use std::path::Path;
-use walkdir::{DirEntry, WalkDir};
 
 fn main() {
     std::process::exit(real_main());
@@ -63,46 +59,6 @@ fn real_main() -> i32 {
     0
 }
 
-fn zip_dir<T>(
-    it: &mut dyn Iterator<Item = DirEntry>,
-    prefix: &str,
-    writer: T,
-    method: ziperu::CompressionMethod,
-) -> ziperu::result::ZipResult<()>
-where
-    T: Write + Seek,
-{
-    let mut zip = ziperu::ZipWriter::new(writer);
-    let options = FileOptions::default()
-        .compression_method(method)
-        .unix_permissions(0o755);
-
-    let mut buffer = Vec::new();
-    for entry in it {
-        let path = entry.path();
-        let name = path.strip_prefix(Path::new(prefix)).unwrap();
-
-        // Write file or directory explicitly
-        // Some unzip tools unzip files with directory paths correctly, some do not!
-        if path.is_file() {
-            println!("adding file {path:?} as {name:?} ...");
-            zip.start_file_from_path(name, options)?;
-            let mut f = File::open(path)?;
-
-            f.read_to_end(&mut buffer)?;
-            zip.write_all(&buffer)?;
-            buffer.clear();
-        } else if !name.as_os_str().is_empty() {
-            // Only if not root! Avoids path spec / warning
-            // and mapname conversion failed error on unzip
-            println!("adding dir {path:?} as {name:?} ...");
-            zip.add_directory_from_path(&name, options)?;
-        }
-    }
-    zip.finish()?;
-    Result::Ok(())
-}
-
 fn doit(
     src_dir: &str,
     dst_file: &str,
@@ -115,10 +71,10 @@ fn doit(
     let path = Path::new(dst_file);
     let file = File::create(path).unwrap();
 
-    let walkdir = WalkDir::new(src_dir);
-    let it = walkdir.into_iter();
-
-    zip_dir(&mut it.filter_map(|e| e.ok()), src_dir, file, method)?;
+    let mut zip = ziperu::ZipWriter::new(file);
+    let options = FileOptions::default().compression_method(method);
+    zip.add_dir_all(src_dir, "", options)?;
+    zip.finish()?;
 
     Ok(())
 }
@@ -1,12 +1,22 @@
 #![no_main]
 use libfuzzer_sys::fuzz_target;
+use std::io::Read;
 
 fn decompress_all(data: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
     let reader = std::io::Cursor::new(data);
     let mut zip = ziperu::ZipArchive::new(reader)?;
 
     for i in 0..zip.len() {
-        let mut file = zip.by_index(i)?;
+        let _ = zip.encryption(i);
+        if let Ok(mut file) = zip.by_index_raw(i) {
+            let _ = std::io::copy(&mut (&mut file).take(1 << 20), &mut std::io::sink());
+        }
+        let Ok(mut file) = zip.by_index_decrypt(i, b"password")? else {
+            continue;
+        };
+        let _ = file.enclosed_name();
+        let _ = file.unix_mode();
+        let _ = file.extra_fields().count();
         if file.size() < 1 << 20 {
             let _ = std::io::copy(&mut file, &mut std::io::sink());
         }
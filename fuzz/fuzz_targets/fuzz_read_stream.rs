@@ -0,0 +1,32 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use std::io::Read;
+use ziperu::read::ZipFile;
+use ziperu::result::ZipResult;
+use ziperu::unstable::stream::{ZipStreamFileMetadata, ZipStreamReader, ZipStreamVisitor};
+
+struct Visitor;
+
+impl ZipStreamVisitor for Visitor {
+    fn visit_file(&mut self, file: &mut ZipFile<'_>) -> ZipResult<()> {
+        let _ = file.enclosed_name();
+        let _ = file.extra_fields().count();
+        let _ = std::io::copy(&mut file.take(1 << 20), &mut std::io::sink());
+        Ok(())
+    }
+
+    fn visit_additional_metadata(&mut self, metadata: &ZipStreamFileMetadata) -> ZipResult<()> {
+        let _ = metadata.enclosed_name();
+        let _ = metadata.unix_mode();
+        Ok(())
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let _ = ZipStreamReader::new(data).visit(&mut Visitor);
+
+    let mut reader = data;
+    while let Ok(Some(mut file)) = ziperu::read::read_zipfile_from_stream(&mut reader) {
+        let _ = std::io::copy(&mut (&mut file).take(1 << 20), &mut std::io::sink());
+    }
+});
@@ -0,0 +1,110 @@
+#![no_main]
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use std::io::{Cursor, Read, Write};
+use ziperu::unstable::write::FileOptionsExt;
+use ziperu::write::FileOptions;
+use ziperu::{CompressionMethod, ZipArchive, ZipWriter};
+
+#[derive(Arbitrary, Debug)]
+struct Options {
+    method: u16,
+    level: Option<i32>,
+    permissions: Option<u32>,
+    large_file: bool,
+    password: Option<Vec<u8>>,
+}
+
+#[derive(Arbitrary, Debug)]
+enum Operation {
+    StartFile {
+        name: String,
+        options: Options,
+    },
+    StartFileWithExtraData {
+        name: String,
+        options: Options,
+        extra: Vec<u8>,
+    },
+    Write(Vec<u8>),
+    AddDirectory {
+        name: String,
+        options: Options,
+    },
+    AddSymlink {
+        name: String,
+        target: String,
+        options: Options,
+    },
+    SetComment(String),
+    Reopen,
+}
+
+fn file_options<'k>(options: &'k Options) -> FileOptions<'k> {
+    let mut file_options = FileOptions::default()
+        .compression_method(CompressionMethod::from_u16(options.method))
+        .compression_level(options.level)
+        .large_file(options.large_file);
+    if let Some(mode) = options.permissions {
+        file_options = file_options.unix_permissions(mode);
+    }
+    if let Some(password) = &options.password {
+        file_options = file_options.with_deprecated_encryption(password);
+    }
+    file_options
+}
+
+fn do_operations(operations: &[Operation]) -> ziperu::result::ZipResult<()> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    for operation in operations {
+        match operation {
+            Operation::StartFile { name, options } => {
+                let _ = writer.start_file(name.as_str(), file_options(options));
+            }
+            Operation::StartFileWithExtraData {
+                name,
+                options,
+                extra,
+            } => {
+                if writer
+                    .start_file_with_extra_data(name.as_str(), file_options(options))
+                    .is_ok()
+                {
+                    let _ = writer.write_all(extra);
+                    let _ = writer.end_extra_data();
+                }
+            }
+            Operation::Write(data) => {
+                let _ = writer.write_all(data);
+            }
+            Operation::AddDirectory { name, options } => {
+                let _ = writer.add_directory(name.as_str(), file_options(options));
+            }
+            Operation::AddSymlink {
+                name,
+                target,
+                options,
+            } => {
+                let _ = writer.add_symlink(name.as_str(), target.as_str(), file_options(options));
+            }
+            Operation::SetComment(comment) => writer.set_comment(comment.as_str()),
+            Operation::Reopen => {
+                let inner = writer.finish()?;
+                writer = ZipWriter::new_append(inner)?;
+            }
+        }
+    }
+
+    // Whatever the writer produced has to be readable again
+    let mut archive = ZipArchive::new(writer.finish()?)?;
+    for i in 0..archive.len() {
+        if let Ok(mut file) = archive.by_index(i) {
+            let _ = file.read_to_end(&mut Vec::new());
+        }
+    }
+    Ok(())
+}
+
+fuzz_target!(|operations: Vec<Operation>| {
+    let _ = do_operations(&operations);
+});
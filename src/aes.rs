@@ -5,10 +5,12 @@
 //! If the file is marked as encrypted with AE-2 the CRC field is ignored, even if it isn't set to 0.
 
 use crate::aes_ctr;
-use crate::types::AesMode;
+use crate::result::{ZipError, ZipResult};
+use crate::types::{AesMode, SecretBytes, secret};
 use constant_time_eq::constant_time_eq;
 use hmac::{Hmac, Mac};
 use sha1::Sha1;
+use std::collections::HashMap;
 use std::io::{self, Read};
 
 /// The length of the password verifcation value in bytes
@@ -18,6 +20,65 @@ const AUTH_CODE_LENGTH: usize = 10;
 /// The number of iterations used with PBKDF2
 const ITERATION_COUNT: u32 = 1000;
 
+/// Derived keys, indexed by password and salt
+///
+/// With the `zeroize` feature, the passwords and keys are wiped when the cache is dropped.
+#[derive(Default)]
+pub(crate) struct KeyCache {
+    keys: HashMap<Vec<u8>, HashMap<Vec<u8>, SecretBytes>>,
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for KeyCache {
+    fn drop(&mut self) {
+        use zeroize::Zeroize;
+        for (mut password, _) in self.keys.drain() {
+            password.zeroize();
+        }
+    }
+}
+
+impl KeyCache {
+    pub(crate) fn contains(&self, password: &[u8], salt: &[u8]) -> bool {
+        self.keys
+            .get(password)
+            .is_some_and(|keys| keys.contains_key(salt))
+    }
+
+    pub(crate) fn insert(&mut self, password: &[u8], salt: Vec<u8>, key: SecretBytes) {
+        self.keys_for(password).insert(salt, key);
+    }
+
+    fn keys_for(&mut self, password: &[u8]) -> &mut HashMap<Vec<u8>, SecretBytes> {
+        // Look up first, so that the password is only copied once
+        if !self.keys.contains_key(password) {
+            self.keys.insert(password.to_vec(), HashMap::new());
+        }
+        self.keys.get_mut(password).unwrap()
+    }
+
+    fn get_or_derive(&mut self, password: &[u8], salt: &[u8], aes_mode: AesMode) -> Option<&[u8]> {
+        let keys = self.keys_for(password);
+        if !keys.contains_key(salt) {
+            let derived_key = derive_key(password, salt, aes_mode)?;
+            keys.insert(salt.to_vec(), derived_key);
+        }
+        keys.get(salt).map(|key| &key[..])
+    }
+}
+
+/// Derive the encryption key, authentication key and password verification value from a
+/// password and salt.
+///
+/// Returns `None` if the password has an invalid length.
+pub(crate) fn derive_key(password: &[u8], salt: &[u8], aes_mode: AesMode) -> Option<SecretBytes> {
+    // the length depends on the aes key length
+    let mut derived_key = secret(vec![0; 2 * aes_mode.key_length() + PWD_VERIFY_LENGTH]);
+    // use PBKDF2 with HMAC-Sha1 to derive the key
+    pbkdf2::pbkdf2::<Hmac<Sha1>>(password, salt, ITERATION_COUNT, &mut derived_key).ok()?;
+    Some(derived_key)
+}
+
 /// Create a AesCipher depending on the used `AesMode` and the given `key`.
 ///
 /// # Panics
@@ -45,15 +106,18 @@ pub struct AesReader<R> {
 }
 
 impl<R: Read> AesReader<R> {
-    pub fn new(reader: R, aes_mode: AesMode, compressed_size: u64) -> AesReader<R> {
+    pub fn new(reader: R, aes_mode: AesMode, compressed_size: u64) -> ZipResult<AesReader<R>> {
         let data_length = compressed_size
-            - (PWD_VERIFY_LENGTH + AUTH_CODE_LENGTH + aes_mode.salt_length()) as u64;
+            .checked_sub((PWD_VERIFY_LENGTH + AUTH_CODE_LENGTH + aes_mode.salt_length()) as u64)
+            .ok_or(ZipError::InvalidArchive(
+                "AES encrypted file is shorter than its header",
+            ))?;
 
-        Self {
+        Ok(Self {
             reader,
             aes_mode,
             data_length,
-        }
+        })
     }
 
     /// Read the AES header bytes and validate the password.
@@ -67,7 +131,13 @@ impl<R: Read> AesReader<R> {
     ///
     /// If the password verification failed `Ok(None)` will be returned to match the validate
     /// method of ZipCryptoReader.
-    pub fn validate(mut self, password: &[u8]) -> io::Result<Option<AesReaderValid<R>>> {
+    ///
+    /// Derived keys are looked up in and added to `key_cache`, if given.
+    pub fn validate(
+        mut self,
+        password: &[u8],
+        key_cache: Option<&mut KeyCache>,
+    ) -> io::Result<Option<AesReaderValid<R>>> {
         let salt_length = self.aes_mode.salt_length();
         let key_length = self.aes_mode.key_length();
 
@@ -79,19 +149,21 @@ impl<R: Read> AesReader<R> {
         self.reader.read_exact(&mut pwd_verification_value)?;
 
         // derive a key from the password and salt
-        // the length depends on the aes key length
-        let derived_key_len = 2 * key_length + PWD_VERIFY_LENGTH;
-        let mut derived_key: Vec<u8> = vec![0; derived_key_len];
-
-        // use PBKDF2 with HMAC-Sha1 to derive the key
-        if pbkdf2::pbkdf2::<Hmac<Sha1>>(password, &salt, ITERATION_COUNT, &mut derived_key).is_err()
-        {
+        let uncached_key;
+        let derived_key = match key_cache {
+            Some(cache) => cache.get_or_derive(password, &salt, self.aes_mode),
+            None => {
+                uncached_key = derive_key(password, &salt, self.aes_mode);
+                uncached_key.as_ref().map(|key| &key[..])
+            }
+        };
+        let Some(derived_key) = derived_key else {
             // password has an invalid length
             return Ok(None);
         };
         let decrypt_key = &derived_key[0..key_length];
         let hmac_key = &derived_key[key_length..key_length * 2];
-        let pwd_verify = &derived_key[derived_key_len - 2..];
+        let pwd_verify = &derived_key[derived_key.len() - 2..];
 
         // the last 2 bytes should equal the password verification value
         if pwd_verification_value != pwd_verify {
@@ -154,25 +226,7 @@ impl<R: Read> Read for AesReaderValid<R> {
 
         // if there is no data left to read, check the integrity of the data
         if self.data_remaining == 0 {
-            assert!(
-                !self.finalized,
-                "Tried to use an already finalized HMAC. This is a bug!"
-            );
-            self.finalized = true;
-
-            // Zip uses HMAC-Sha1-80, which only uses the first half of the hash
-            // see https://www.winzip.com/win/en/aes_info.html#auth-faq
-            let mut read_auth_code = [0; AUTH_CODE_LENGTH];
-            self.reader.read_exact(&mut read_auth_code)?;
-            let computed_auth_code = &self.hmac.finalize_reset().into_bytes()[0..AUTH_CODE_LENGTH];
-
-            // use constant time comparison to mitigate timing attacks
-            if !constant_time_eq(computed_auth_code, &read_auth_code) {
-                return Err(io::Error::new(
-                    io::ErrorKind::InvalidData,
-                    "Invalid authentication code, this could be due to an invalid password or errors in the data",
-                ));
-            }
+            self.check_auth_code()?;
         }
 
         Ok(read)
@@ -180,6 +234,47 @@ impl<R: Read> Read for AesReaderValid<R> {
 }
 
 impl<R: Read> AesReaderValid<R> {
+    /// Check the authentication code of the whole entry without decrypting it.
+    ///
+    /// This consumes the encrypted data, so the entry has to be opened again to read it.
+    pub fn authenticate(mut self) -> io::Result<()> {
+        let mut buf = [0; 8192];
+        while self.data_remaining > 0 {
+            let bytes_to_read = self.data_remaining.min(buf.len() as u64) as usize;
+            let read = self.reader.read(&mut buf[0..bytes_to_read])?;
+            if read == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            self.data_remaining -= read as u64;
+            self.hmac.update(&buf[0..read]);
+        }
+        self.check_auth_code()
+    }
+
+    fn check_auth_code(&mut self) -> io::Result<()> {
+        if self.finalized {
+            return Err(io::Error::other(
+                "Tried to use an already finalized HMAC. This is a bug!",
+            ));
+        }
+        self.finalized = true;
+
+        // Zip uses HMAC-Sha1-80, which only uses the first half of the hash
+        // see https://www.winzip.com/win/en/aes_info.html#auth-faq
+        let mut read_auth_code = [0; AUTH_CODE_LENGTH];
+        self.reader.read_exact(&mut read_auth_code)?;
+        let computed_auth_code = &self.hmac.finalize_reset().into_bytes()[0..AUTH_CODE_LENGTH];
+
+        // use constant time comparison to mitigate timing attacks
+        if !constant_time_eq(computed_auth_code, &read_auth_code) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid authentication code, this could be due to an invalid password or errors in the data",
+            ));
+        }
+        Ok(())
+    }
+
     /// Consumes this decoder, returning the underlying reader.
     pub fn into_inner(self) -> R {
         self.reader
@@ -26,7 +26,7 @@ pub trait AesKind {
     /// Key type.
     type Key: AsRef<[u8]>;
     /// Cipher used to decrypt.
-    type Cipher;
+    type Cipher: Send;
 }
 
 impl AesKind for Aes128 {
@@ -78,6 +78,18 @@ where
     }
 }
 
+/// The cipher wipes its own key schedule, but the buffered key stream is wiped here.
+#[cfg(feature = "zeroize")]
+impl<C> Drop for AesCtrZipKeyStream<C>
+where
+    C: AesKind,
+{
+    fn drop(&mut self) {
+        use zeroize::Zeroize;
+        self.buffer.zeroize();
+    }
+}
+
 impl<C> AesCtrZipKeyStream<C>
 where
     C: AesKind,
@@ -140,7 +152,7 @@ where
 }
 
 /// This trait allows using generic AES ciphers with different key sizes.
-pub trait AesCipher {
+pub trait AesCipher: Send {
     fn crypt_in_place(&mut self, target: &mut [u8]);
 }
 
@@ -0,0 +1,168 @@
+//! Custom compression methods
+//!
+//! [`CompressionMethod`] only covers the methods built into this crate. Other methods are
+//! supported by implementing [`Codec`] and adding it to a [`CodecRegistry`], which is handed to
+//! [`ZipArchive::set_codecs`], [`ZipStreamReader::with_codecs`] or [`ZipWriter::set_codecs`].
+//!
+//! A registry is only consulted for [`CompressionMethod::Unsupported`] methods, so the built-in
+//! methods can't be replaced. A method that is built in but disabled by a cargo feature can be
+//! provided by a codec, though.
+//!
+//! [`ZipArchive::set_codecs`]: crate::ZipArchive::set_codecs
+//! [`ZipStreamReader::with_codecs`]: crate::unstable::stream::ZipStreamReader::with_codecs
+//! [`ZipWriter::set_codecs`]: crate::ZipWriter::set_codecs
+
+use crate::compression::CompressionMethod;
+use crate::types::DEFAULT_MINIMUM_ZIP_SPECIFICATION_VERSION;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+/// A compression method that is not built into this crate
+pub trait Codec: Send + Sync {
+    /// The method id stored in the file headers
+    fn method(&self) -> u16;
+
+    /// The version needed to extract entries compressed with this method
+    ///
+    /// The default is 1.0. The version written to the headers is raised further if the entry is
+    /// encrypted or uses ZIP64.
+    fn version_needed(&self) -> u16 {
+        DEFAULT_MINIMUM_ZIP_SPECIFICATION_VERSION
+    }
+
+    /// Create a decoder for an entry, reading the compressed data from `reader`
+    ///
+    /// `uncompressed_size` is the size stored in the central directory. The CRC of the decoded
+    /// data is checked by the caller.
+    fn decoder<'a>(
+        &self,
+        reader: Box<dyn Read + 'a>,
+        uncompressed_size: u64,
+    ) -> io::Result<Box<dyn Read + 'a>>;
+
+    /// Create a decoder like [`Codec::decoder`] that can be sent to other threads
+    ///
+    /// This is used for files opened with [`ZipArchive::by_index_owned`]. The default returns an
+    /// error of kind [`io::ErrorKind::Unsupported`]. Codecs whose decoders are `Send` can
+    /// implement it like [`Codec::decoder`].
+    ///
+    /// [`ZipArchive::by_index_owned`]: crate::ZipArchive::by_index_owned
+    fn send_decoder<'a>(
+        &self,
+        reader: Box<dyn Read + Send + 'a>,
+        uncompressed_size: u64,
+    ) -> io::Result<Box<dyn Read + Send + 'a>> {
+        let _ = (reader, uncompressed_size);
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "The codec doesn't support decoders that can be sent to other threads",
+        ))
+    }
+
+    /// Create an encoder for an entry
+    ///
+    /// `level` is the level set with [`FileOptions::compression_level`], to be interpreted by the
+    /// codec.
+    ///
+    /// [`FileOptions::compression_level`]: crate::write::FileOptions::compression_level
+    fn encoder(&self, level: Option<i32>) -> io::Result<Box<dyn Encoder>>;
+}
+
+/// Compresses the data of a single entry, see [`Codec::encoder`]
+///
+/// The encoder doesn't own the archive. Instead, the compressed data is written to the `output`
+/// passed along with each call. Encoders wrapping a writer based compressor can compress into a
+/// buffer and move its content to `output`.
+pub trait Encoder: Send {
+    /// Compress `input`, writing any compressed data that is ready to `output`
+    fn write(&mut self, input: &[u8], output: &mut dyn Write) -> io::Result<()>;
+
+    /// Write the remaining compressed data to `output` once all input has been written
+    fn finish(self: Box<Self>, output: &mut dyn Write) -> io::Result<()>;
+}
+
+/// A set of [`Codec`]s, indexed by method id
+///
+/// The registry is cheap to clone, the codecs are shared.
+///
+/// ```
+/// use std::io::{self, Read, Write};
+/// use ziperu::codec::{Codec, CodecRegistry, Encoder};
+///
+/// /// Stores data as is under a custom method id
+/// struct Identity;
+///
+/// impl Codec for Identity {
+///     fn method(&self) -> u16 {
+///         0x8000
+///     }
+///
+///     fn decoder<'a>(
+///         &self,
+///         reader: Box<dyn Read + 'a>,
+///         _uncompressed_size: u64,
+///     ) -> io::Result<Box<dyn Read + 'a>> {
+///         Ok(reader)
+///     }
+///
+///     fn encoder(&self, _level: Option<i32>) -> io::Result<Box<dyn Encoder>> {
+///         Ok(Box::new(Identity))
+///     }
+/// }
+///
+/// impl Encoder for Identity {
+///     fn write(&mut self, input: &[u8], output: &mut dyn Write) -> io::Result<()> {
+///         output.write_all(input)
+///     }
+///
+///     fn finish(self: Box<Self>, _output: &mut dyn Write) -> io::Result<()> {
+///         Ok(())
+///     }
+/// }
+///
+/// let codecs = CodecRegistry::new().register(Identity);
+/// assert!(codecs.get(0x8000).is_some());
+/// ```
+#[derive(Clone, Default)]
+pub struct CodecRegistry {
+    codecs: HashMap<u16, Arc<dyn Codec>>,
+}
+
+impl CodecRegistry {
+    /// Create an empty registry
+    pub fn new() -> CodecRegistry {
+        CodecRegistry::default()
+    }
+
+    /// Add a codec, replacing any codec registered for the same method id
+    #[must_use]
+    pub fn register<C: Codec + 'static>(mut self, codec: C) -> CodecRegistry {
+        self.codecs.insert(codec.method(), Arc::new(codec));
+        self
+    }
+
+    /// Get the codec registered for a method id
+    pub fn get(&self, method: u16) -> Option<&dyn Codec> {
+        self.codecs.get(&method).map(|codec| &**codec)
+    }
+
+    /// Get the codec for a method that is not built in
+    pub(crate) fn custom(&self, method: CompressionMethod) -> Option<Arc<dyn Codec>> {
+        match method {
+            CompressionMethod::Unsupported(id) => self.codecs.get(&id).cloned(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Debug for CodecRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut methods: Vec<_> = self.codecs.keys().collect();
+        methods.sort();
+        f.debug_struct("CodecRegistry")
+            .field("methods", &methods)
+            .finish()
+    }
+}
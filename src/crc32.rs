@@ -5,6 +5,8 @@ use std::io::prelude::*;
 
 use crc32fast::Hasher;
 
+use crate::result::{ErrorKind, ZipError};
+
 /// Reader that validates the CRC32 when it reaches the EOF.
 pub struct Crc32Reader<R> {
     inner: R,
@@ -41,7 +43,13 @@ impl<R: Read> Read for Crc32Reader<R> {
         let invalid_check = !buf.is_empty() && !self.check_matches() && !self.ae2_encrypted;
 
         let count = match self.inner.read(buf) {
-            Ok(0) if invalid_check => return Err(io::Error::other("Invalid checksum")),
+            Ok(0) if invalid_check => {
+                return Err(ZipError::detailed(ErrorKind::CrcMismatch {
+                    expected: self.check,
+                    actual: self.hasher.clone().finalize(),
+                })
+                .into());
+            }
             Ok(n) => n,
             Err(e) => return Err(e),
         };
@@ -64,12 +72,15 @@ mod test {
         assert_eq!(reader.read(&mut buf).unwrap(), 0);
 
         let mut reader = Crc32Reader::new(data, 1, false);
-        assert!(
-            reader
-                .read(&mut buf)
-                .unwrap_err()
-                .to_string()
-                .contains("Invalid checksum")
+        let err = reader.read(&mut buf).unwrap_err();
+        assert!(err.to_string().contains("Invalid checksum"));
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            ZipError::from(err).details().unwrap().kind(),
+            &ErrorKind::CrcMismatch {
+                expected: 1,
+                actual: 0
+            }
         );
     }
 
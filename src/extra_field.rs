@@ -0,0 +1,548 @@
+//! Types for the extra fields stored in local and central file headers.
+//!
+//! Every extra field is made of a 2 byte header ID, a 2 byte data size and the data itself.
+//! See chapter 4.5 and 4.6 of the [specification](https://pkware.cachefly.net/webdocs/casestudies/APPNOTE.TXT)
+//! for the fields defined by PKWARE and third parties.
+
+use crate::result::{ZipError, ZipResult};
+use crate::types::{AesMode, AesVendorVersion};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Write};
+
+/// Header ID of the ZIP64 extended information extra field
+pub(crate) const ZIP64_EXTRA_FIELD_ID: u16 = 0x0001;
+/// Header ID of the NTFS extra field
+const NTFS_EXTRA_FIELD_ID: u16 = 0x000a;
+/// Header ID of the extended timestamp extra field
+const EXTENDED_TIMESTAMP_EXTRA_FIELD_ID: u16 = 0x5455;
+/// Header ID of the Info-ZIP Unicode path extra field
+const UNICODE_PATH_EXTRA_FIELD_ID: u16 = 0x7075;
+/// Header ID of the Info-ZIP new Unix extra field
+const UNIX_EXTRA_FIELD_ID: u16 = 0x7875;
+/// Header ID of the AES extra field
+pub(crate) const AES_EXTRA_FIELD_ID: u16 = 0x9901;
+/// Header ID of the data stream alignment extra field
+pub(crate) const ALIGNMENT_EXTRA_FIELD_ID: u16 = 0xa11e;
+
+/// The vendor ID of the AES extra field, which is always "AE"
+const AES_VENDOR_ID: u16 = 0x4541;
+
+/// A single extra field of a local or central file header.
+///
+/// Fields can be read from an archive with [`crate::read::ZipFile::extra_fields`] and written with
+/// [`crate::write::FileOptions::extra_fields`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ExtraField {
+    /// ZIP64 extended information (`0x0001`)
+    Zip64(Zip64ExtraField),
+    /// WinZip AES encryption information (`0x9901`)
+    Aes {
+        /// The AE-x version of the encryption
+        vendor_version: AesVendorVersion,
+        /// The AES key strength
+        aes_mode: AesMode,
+        /// The compression method used before encryption
+        compression_method: u16,
+    },
+    /// Extended timestamp with a precision of one second (`0x5455`)
+    ExtendedTimestamp(ExtendedTimestamp),
+    /// Info-ZIP Unix user and group IDs (`0x7875`)
+    Unix {
+        /// The user ID of the owner
+        uid: u32,
+        /// The group ID of the owner
+        gid: u32,
+    },
+    /// NTFS file times (`0x000a`)
+    ///
+    /// All times are in 100 nanosecond intervals since 1601-01-01 (`FILETIME`).
+    Ntfs {
+        /// Last modification time
+        mtime: u64,
+        /// Last access time
+        atime: u64,
+        /// Creation time
+        ctime: u64,
+    },
+    /// Info-ZIP Unicode path (`0x7075`)
+    UnicodePath {
+        /// CRC32 of the file name stored in the header, used to detect stale entries
+        crc32: u32,
+        /// The UTF-8 encoded file name
+        name: String,
+    },
+    /// Data stream alignment (`0xa11e`)
+    Alignment {
+        /// The alignment the file data was padded to
+        alignment: u16,
+        /// The number of padding bytes
+        padding: u16,
+    },
+    /// Any field not known to this crate
+    Unknown {
+        /// The header ID of the field
+        header_id: u16,
+        /// The raw data of the field
+        data: Vec<u8>,
+    },
+}
+
+/// The values of a ZIP64 extended information extra field.
+///
+/// The record only holds the values whose counterpart in the file header is set to `0xFFFFFFFF`,
+/// always in the order of the fields of this struct. Since it is not known which header fields were
+/// saturated when parsing the extra field on its own, parsed values are assigned in order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Zip64ExtraField {
+    /// Size of the file when extracted
+    pub uncompressed_size: Option<u64>,
+    /// Size of the file in the ZIP
+    pub compressed_size: Option<u64>,
+    /// Offset of the local file header
+    pub header_start: Option<u64>,
+    /// Number of the disk on which the file starts
+    pub disk_start: Option<u32>,
+}
+
+/// The values of an extended timestamp extra field.
+///
+/// All times are in seconds since the unix epoch. Central headers usually only contain the
+/// modification time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExtendedTimestamp {
+    /// Last modification time
+    pub mod_time: Option<i32>,
+    /// Last access time
+    pub ac_time: Option<i32>,
+    /// Creation time
+    pub cr_time: Option<i32>,
+}
+
+impl ExtraField {
+    /// Create a Unicode path extra field for a file whose header contains `header_name`
+    pub fn unicode_path(header_name: &[u8], name: impl Into<String>) -> ExtraField {
+        ExtraField::UnicodePath {
+            crc32: crc32fast::hash(header_name),
+            name: name.into(),
+        }
+    }
+
+    /// Get the header ID of this field
+    pub fn header_id(&self) -> u16 {
+        match self {
+            ExtraField::Zip64(_) => ZIP64_EXTRA_FIELD_ID,
+            ExtraField::Aes { .. } => AES_EXTRA_FIELD_ID,
+            ExtraField::ExtendedTimestamp(_) => EXTENDED_TIMESTAMP_EXTRA_FIELD_ID,
+            ExtraField::Unix { .. } => UNIX_EXTRA_FIELD_ID,
+            ExtraField::Ntfs { .. } => NTFS_EXTRA_FIELD_ID,
+            ExtraField::UnicodePath { .. } => UNICODE_PATH_EXTRA_FIELD_ID,
+            ExtraField::Alignment { .. } => ALIGNMENT_EXTRA_FIELD_ID,
+            ExtraField::Unknown { header_id, .. } => *header_id,
+        }
+    }
+
+    /// Parse a single field from its header ID and data
+    pub fn parse(header_id: u16, mut data: &[u8]) -> ZipResult<ExtraField> {
+        let field = match header_id {
+            ZIP64_EXTRA_FIELD_ID => {
+                let mut field = Zip64ExtraField::default();
+                if data.len() >= 8 {
+                    field.uncompressed_size = Some(data.read_u64::<LittleEndian>()?);
+                }
+                if data.len() >= 8 {
+                    field.compressed_size = Some(data.read_u64::<LittleEndian>()?);
+                }
+                if data.len() >= 8 {
+                    field.header_start = Some(data.read_u64::<LittleEndian>()?);
+                }
+                if data.len() >= 4 {
+                    field.disk_start = Some(data.read_u32::<LittleEndian>()?);
+                }
+                ExtraField::Zip64(field)
+            }
+            AES_EXTRA_FIELD_ID => {
+                if data.len() != 7 {
+                    return Err(ZipError::UnsupportedArchive(
+                        "AES extra data field has an unsupported length",
+                    ));
+                }
+                let vendor_version = data.read_u16::<LittleEndian>()?;
+                let vendor_id = data.read_u16::<LittleEndian>()?;
+                let aes_mode = data.read_u8()?;
+                let compression_method = data.read_u16::<LittleEndian>()?;
+
+                if vendor_id != AES_VENDOR_ID {
+                    return Err(ZipError::InvalidArchive("Invalid AES vendor"));
+                }
+                let vendor_version = match vendor_version {
+                    0x0001 => AesVendorVersion::Ae1,
+                    0x0002 => AesVendorVersion::Ae2,
+                    _ => return Err(ZipError::InvalidArchive("Invalid AES vendor version")),
+                };
+                let aes_mode = match aes_mode {
+                    0x01 => AesMode::Aes128,
+                    0x02 => AesMode::Aes192,
+                    0x03 => AesMode::Aes256,
+                    _ => return Err(ZipError::InvalidArchive("Invalid AES encryption strength")),
+                };
+                ExtraField::Aes {
+                    vendor_version,
+                    aes_mode,
+                    compression_method,
+                }
+            }
+            EXTENDED_TIMESTAMP_EXTRA_FIELD_ID => {
+                let flags = data.read_u8()?;
+                let mut field = ExtendedTimestamp::default();
+                // The central header only contains the modification time, even though the flags
+                // are copied from the local header. So only read what is actually there.
+                if flags & 0b001 != 0 && data.len() >= 4 {
+                    field.mod_time = Some(data.read_i32::<LittleEndian>()?);
+                }
+                if flags & 0b010 != 0 && data.len() >= 4 {
+                    field.ac_time = Some(data.read_i32::<LittleEndian>()?);
+                }
+                if flags & 0b100 != 0 && data.len() >= 4 {
+                    field.cr_time = Some(data.read_i32::<LittleEndian>()?);
+                }
+                ExtraField::ExtendedTimestamp(field)
+            }
+            UNIX_EXTRA_FIELD_ID => {
+                let version = data.read_u8()?;
+                if version != 1 {
+                    return Err(ZipError::UnsupportedArchive(
+                        "Unix extra data field has an unsupported version",
+                    ));
+                }
+                let uid = read_variable_id(&mut data)?;
+                let gid = read_variable_id(&mut data)?;
+                ExtraField::Unix { uid, gid }
+            }
+            NTFS_EXTRA_FIELD_ID => {
+                let _reserved = data.read_u32::<LittleEndian>()?;
+                let mut times = None;
+                while !data.is_empty() {
+                    let tag = data.read_u16::<LittleEndian>()?;
+                    let size = data.read_u16::<LittleEndian>()? as usize;
+                    if size > data.len() {
+                        return Err(ZipError::InvalidArchive(
+                            "NTFS attribute size exceeds extra field",
+                        ));
+                    }
+                    let (mut attribute, rest) = data.split_at(size);
+                    data = rest;
+                    if tag == 0x0001 && size == 24 {
+                        times = Some((
+                            attribute.read_u64::<LittleEndian>()?,
+                            attribute.read_u64::<LittleEndian>()?,
+                            attribute.read_u64::<LittleEndian>()?,
+                        ));
+                    }
+                }
+                let (mtime, atime, ctime) = times.ok_or(ZipError::UnsupportedArchive(
+                    "NTFS extra data field without file times",
+                ))?;
+                ExtraField::Ntfs {
+                    mtime,
+                    atime,
+                    ctime,
+                }
+            }
+            UNICODE_PATH_EXTRA_FIELD_ID => {
+                let version = data.read_u8()?;
+                if version != 1 {
+                    return Err(ZipError::UnsupportedArchive(
+                        "Unicode path extra data field has an unsupported version",
+                    ));
+                }
+                let crc32 = data.read_u32::<LittleEndian>()?;
+                let name = String::from_utf8(data.to_vec()).map_err(|_| {
+                    ZipError::InvalidArchive("Unicode path extra data field is not UTF-8")
+                })?;
+                ExtraField::UnicodePath { crc32, name }
+            }
+            ALIGNMENT_EXTRA_FIELD_ID => {
+                let alignment = data.read_u16::<LittleEndian>()?;
+                ExtraField::Alignment {
+                    alignment,
+                    padding: data.len() as u16,
+                }
+            }
+            header_id => ExtraField::Unknown {
+                header_id,
+                data: data.to_vec(),
+            },
+        };
+
+        Ok(field)
+    }
+
+    /// Get the size of the serialized field, including its 4 byte header
+    pub fn len(&self) -> usize {
+        4 + match self {
+            ExtraField::Zip64(field) => {
+                8 * [
+                    field.uncompressed_size,
+                    field.compressed_size,
+                    field.header_start,
+                ]
+                .iter()
+                .filter(|value| value.is_some())
+                .count()
+                    + 4 * field.disk_start.is_some() as usize
+            }
+            ExtraField::Aes { .. } => 7,
+            ExtraField::ExtendedTimestamp(field) => {
+                1 + 4 * [field.mod_time, field.ac_time, field.cr_time]
+                    .iter()
+                    .filter(|value| value.is_some())
+                    .count()
+            }
+            ExtraField::Unix { .. } => 11,
+            ExtraField::Ntfs { .. } => 32,
+            ExtraField::UnicodePath { name, .. } => 5 + name.len(),
+            ExtraField::Alignment { padding, .. } => 2 + *padding as usize,
+            ExtraField::Unknown { data, .. } => data.len(),
+        }
+    }
+
+    /// Returns `true` if the field has no data
+    pub fn is_empty(&self) -> bool {
+        self.len() == 4
+    }
+
+    /// Serialize the field including its header ID and data size
+    pub fn write<W: Write>(&self, writer: &mut W) -> ZipResult<()> {
+        let data_size = u16::try_from(self.len() - 4).map_err(|_| {
+            ZipError::Io(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Extra data exceeds extra field",
+            ))
+        })?;
+        writer.write_u16::<LittleEndian>(self.header_id())?;
+        writer.write_u16::<LittleEndian>(data_size)?;
+
+        match self {
+            ExtraField::Zip64(field) => {
+                for value in [
+                    field.uncompressed_size,
+                    field.compressed_size,
+                    field.header_start,
+                ]
+                .into_iter()
+                .flatten()
+                {
+                    writer.write_u64::<LittleEndian>(value)?;
+                }
+                if let Some(disk_start) = field.disk_start {
+                    writer.write_u32::<LittleEndian>(disk_start)?;
+                }
+            }
+            ExtraField::Aes {
+                vendor_version,
+                aes_mode,
+                compression_method,
+            } => {
+                writer.write_u16::<LittleEndian>(match vendor_version {
+                    AesVendorVersion::Ae1 => 0x0001,
+                    AesVendorVersion::Ae2 => 0x0002,
+                })?;
+                writer.write_u16::<LittleEndian>(AES_VENDOR_ID)?;
+                writer.write_u8(match aes_mode {
+                    AesMode::Aes128 => 0x01,
+                    AesMode::Aes192 => 0x02,
+                    AesMode::Aes256 => 0x03,
+                })?;
+                writer.write_u16::<LittleEndian>(*compression_method)?;
+            }
+            ExtraField::ExtendedTimestamp(field) => {
+                let flags = field.mod_time.is_some() as u8
+                    | (field.ac_time.is_some() as u8) << 1
+                    | (field.cr_time.is_some() as u8) << 2;
+                writer.write_u8(flags)?;
+                for time in [field.mod_time, field.ac_time, field.cr_time]
+                    .into_iter()
+                    .flatten()
+                {
+                    writer.write_i32::<LittleEndian>(time)?;
+                }
+            }
+            ExtraField::Unix { uid, gid } => {
+                writer.write_u8(1)?;
+                writer.write_u8(4)?;
+                writer.write_u32::<LittleEndian>(*uid)?;
+                writer.write_u8(4)?;
+                writer.write_u32::<LittleEndian>(*gid)?;
+            }
+            ExtraField::Ntfs {
+                mtime,
+                atime,
+                ctime,
+            } => {
+                writer.write_u32::<LittleEndian>(0)?;
+                writer.write_u16::<LittleEndian>(0x0001)?;
+                writer.write_u16::<LittleEndian>(24)?;
+                writer.write_u64::<LittleEndian>(*mtime)?;
+                writer.write_u64::<LittleEndian>(*atime)?;
+                writer.write_u64::<LittleEndian>(*ctime)?;
+            }
+            ExtraField::UnicodePath { crc32, name } => {
+                writer.write_u8(1)?;
+                writer.write_u32::<LittleEndian>(*crc32)?;
+                writer.write_all(name.as_bytes())?;
+            }
+            ExtraField::Alignment { alignment, padding } => {
+                writer.write_u16::<LittleEndian>(*alignment)?;
+                writer.write_all(&vec![0; *padding as usize])?;
+            }
+            ExtraField::Unknown { data, .. } => {
+                writer.write_all(data)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Read an Info-ZIP variable length ID, which is prefixed by its size
+fn read_variable_id(data: &mut &[u8]) -> ZipResult<u32> {
+    let size = data.read_u8()? as usize;
+    if size > data.len() {
+        return Err(ZipError::InvalidArchive("Unix ID size exceeds extra field"));
+    }
+    let (id, rest) = data.split_at(size);
+    *data = rest;
+
+    // IDs are stored in little endian with leading zeros, so anything past the 4th byte must be 0
+    if id.iter().skip(4).any(|byte| *byte != 0) {
+        return Err(ZipError::UnsupportedArchive(
+            "Unix IDs larger than 32 bits are not supported",
+        ));
+    }
+    let mut buf = [0; 4];
+    let len = id.len().min(4);
+    buf[..len].copy_from_slice(&id[..len]);
+    Ok(u32::from_le_bytes(buf))
+}
+
+/// Serialize a list of extra fields
+pub(crate) fn serialize(fields: &[ExtraField]) -> ZipResult<Vec<u8>> {
+    let mut data = Vec::with_capacity(fields.iter().map(ExtraField::len).sum());
+    for field in fields {
+        field.write(&mut data)?;
+    }
+    Ok(data)
+}
+
+/// Iterator over the extra fields of a file header.
+///
+/// If the framing of the fields is broken, an error is returned and the iteration stops.
+#[derive(Clone, Debug)]
+pub struct ExtraFields<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> ExtraFields<'a> {
+    /// Iterate over the fields in `data`
+    pub fn new(data: &'a [u8]) -> ExtraFields<'a> {
+        ExtraFields { data }
+    }
+}
+
+impl Iterator for ExtraFields<'_> {
+    type Item = ZipResult<ExtraField>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+
+        let mut header = &self.data[..self.data.len().min(4)];
+        let parsed = (|| {
+            let header_id = header.read_u16::<LittleEndian>()?;
+            let size = header.read_u16::<LittleEndian>()? as usize;
+            let data = self.data[4..].get(..size).ok_or(ZipError::InvalidArchive(
+                "Extra data size exceeds extra field",
+            ))?;
+            Ok((header_id, data))
+        })();
+
+        match parsed {
+            Ok((header_id, data)) => {
+                self.data = &self.data[4 + data.len()..];
+                Some(ExtraField::parse(header_id, data))
+            }
+            Err(e) => {
+                self.data = &[];
+                Some(Err(match e {
+                    ZipError::Io(_) => ZipError::InvalidArchive("Incomplete extra data header"),
+                    e => e,
+                }))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn roundtrip(field: ExtraField) {
+        let data = serialize(std::slice::from_ref(&field)).unwrap();
+        assert_eq!(data.len(), field.len());
+        let parsed = ExtraFields::new(&data)
+            .collect::<ZipResult<Vec<_>>>()
+            .unwrap();
+        assert_eq!(parsed, vec![field]);
+    }
+
+    #[test]
+    fn roundtrip_fields() {
+        roundtrip(ExtraField::Zip64(Zip64ExtraField {
+            uncompressed_size: Some(1 << 33),
+            compressed_size: Some(1 << 32),
+            header_start: None,
+            disk_start: None,
+        }));
+        roundtrip(ExtraField::Aes {
+            vendor_version: AesVendorVersion::Ae2,
+            aes_mode: AesMode::Aes256,
+            compression_method: 8,
+        });
+        roundtrip(ExtraField::ExtendedTimestamp(ExtendedTimestamp {
+            mod_time: Some(1_600_000_000),
+            ac_time: None,
+            cr_time: Some(1_500_000_000),
+        }));
+        roundtrip(ExtraField::Unix {
+            uid: 1000,
+            gid: 100,
+        });
+        roundtrip(ExtraField::Ntfs {
+            mtime: 1,
+            atime: 2,
+            ctime: 3,
+        });
+        roundtrip(ExtraField::unicode_path(b"caf?", "café"));
+        roundtrip(ExtraField::Alignment {
+            alignment: 4,
+            padding: 3,
+        });
+        roundtrip(ExtraField::Unknown {
+            header_id: 0xbeef,
+            data: b"data".to_vec(),
+        });
+    }
+
+    #[test]
+    fn broken_framing() {
+        let mut fields = ExtraFields::new(&[0xef, 0xbe, 0x08, 0x00, 0x01]);
+        assert!(fields.next().unwrap().is_err());
+        assert!(fields.next().is_none());
+
+        let mut fields = ExtraFields::new(&[0xef, 0xbe]);
+        assert!(fields.next().unwrap().is_err());
+        assert!(fields.next().is_none());
+    }
+}
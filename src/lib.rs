@@ -30,23 +30,28 @@
 #![warn(missing_docs)]
 
 pub use crate::compression::{CompressionMethod, SUPPORTED_COMPRESSION_METHODS};
+pub use crate::extra_field::ExtraField;
 pub use crate::read::ZipArchive;
-pub use crate::types::DateTime;
+pub use crate::types::{AesMode, AesVendorVersion, DateTime, EncryptionMethod};
 pub use crate::write::ZipWriter;
 
 #[cfg(feature = "aes-crypto")]
 mod aes;
 #[cfg(feature = "aes-crypto")]
 mod aes_ctr;
+pub mod codec;
 mod compression;
 mod cp437;
 mod crc32;
+pub mod extra_field;
 pub mod read;
 pub mod result;
 mod spec;
 mod types;
 pub mod write;
 mod zipcrypto;
+#[cfg(feature = "zstd")]
+pub mod zstd_dictionary;
 
 /// Unstable APIs
 ///
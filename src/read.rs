@@ -1,22 +1,26 @@
 //! Types for reading ZIP archives
 
 #[cfg(feature = "aes-crypto")]
-use crate::aes::{AesReader, AesReaderValid};
+use crate::aes::{AesReader, AesReaderValid, KeyCache};
+use crate::codec::{Codec, CodecRegistry};
 use crate::compression::CompressionMethod;
 use crate::cp437::FromCp437;
 use crate::crc32::Crc32Reader;
-use crate::result::{InvalidPassword, ZipError, ZipResult};
+use crate::extra_field::{AES_EXTRA_FIELD_ID, ExtraField, ExtraFields, ZIP64_EXTRA_FIELD_ID};
+use crate::result::{ErrorKind, InvalidPassword, ZipError, ZipResult};
 use crate::spec;
-use crate::types::{AesMode, AesVendorVersion, AtomicU64, DateTime, System, ZipFileData};
+use crate::types::{
+    AesMode, AesVendorVersion, AtomicU64, DateTime, EncryptionMethod, SecretBytes, System,
+    ZipFileData, secret,
+};
 use crate::zipcrypto::{ZipCryptoReader, ZipCryptoReaderValid, ZipCryptoValidator};
 use byteorder::{LittleEndian, ReadBytesExt};
-use indexmap::IndexMap;
-use std::borrow::Cow;
+use sealed::CodecInput;
 use std::io;
 use std::io::Read;
 use std::io::Seek;
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 
 #[cfg(any(
     feature = "deflate",
@@ -31,14 +35,33 @@ use deflate64::Deflate64Decoder;
 #[cfg(feature = "bzip2")]
 use bzip2::read::BzDecoder;
 
+#[cfg(feature = "zstd")]
+use crate::zstd_dictionary::{ZstdDictionaries, ZstdDictionary};
+#[cfg(feature = "zstd")]
+use std::io::BufRead;
 #[cfg(feature = "zstd")]
 use zstd::stream::read::Decoder as ZstdDecoder;
 
 #[cfg(feature = "xz")]
 use lzma_rust2::XzReader;
 
+/// Helpers shared by the extraction routines.
+pub(crate) mod extract;
+/// Glob patterns selecting entries by path.
+pub(crate) mod glob;
+/// Limits for decompressing untrusted input.
+pub(crate) mod limits;
+/// Metadata of the files in an archive, from the central directory.
+pub(crate) mod metadata;
+/// Lookup of files by name, including duplicate names.
+pub(crate) mod names;
+#[cfg(feature = "aes-crypto")]
+/// Caches derived keys when decrypting many entries.
+pub(crate) mod session;
 /// Provides high level API for reading from a stream.
 pub(crate) mod stream;
+/// A hierarchical view of the files in an archive.
+pub(crate) mod tree;
 
 #[cfg(feature = "lzma")]
 /// Provides a reader for the lzma crate
@@ -46,14 +69,22 @@ pub(crate) mod lzma_reader;
 
 // Put the struct declaration in a private module to convince rustdoc to display ZipArchive nicely
 pub(crate) mod zip_archive {
-    use indexmap::IndexMap;
 
     /// Extract immutable data from `ZipArchive` to make it cheap to clone
     #[derive(Debug)]
     pub(crate) struct Shared {
-        pub(super) files: IndexMap<String, super::ZipFileData>,
+        /// The files in the order of the central directory, including files with duplicate names
+        pub(super) files: Vec<super::ZipFileData>,
+        pub(super) names: super::names::NameIndex,
         pub(super) offset: u64,
         pub(super) comment: Vec<u8>,
+        /// The zstd dictionaries stored in the archive by id, loaded when first needed. `None`
+        /// marks ids without a valid dictionary entry.
+        #[cfg(feature = "zstd")]
+        pub(super) zstd_dictionaries:
+            std::sync::Mutex<std::collections::HashMap<u32, Option<super::ZstdDictionary>>>,
+        /// The directories of the archive, indexed when first needed
+        pub(super) directory_index: std::sync::OnceLock<super::tree::DirectoryIndex>,
     }
 
     /// ZIP archive reader
@@ -80,22 +111,119 @@ pub(crate) mod zip_archive {
     pub struct ZipArchive<R> {
         pub(super) reader: R,
         pub(super) shared: super::Arc<Shared>,
+        pub(super) authenticate_aes_first: bool,
+        pub(super) password_provider: Option<super::PasswordProvider>,
+        pub(super) max_password_attempts: usize,
+        pub(super) codecs: super::CodecRegistry,
+        pub(super) decoder_limits: super::DecoderLimits,
+        pub(super) duplicate_policy: super::DuplicatePolicy,
+        /// The index for looking up names that don't match exactly, shared by clones
+        pub(super) name_matching: Option<super::Arc<super::names::MatchingIndex>>,
     }
 }
 
+pub use extract::{ExtractOptions, ExtractProgress, OverwritePolicy};
+pub use glob::{EntryMatcher, Glob};
+pub use limits::DecoderLimits;
+pub use metadata::ZipFileMetadata;
+pub use names::{DuplicatePolicy, NameMatching};
+#[cfg(feature = "aes-crypto")]
+pub use session::DecryptionSession;
+pub use tree::{DirectoryTree, ZipDirEntry};
 pub use zip_archive::ZipArchive;
+
+/// Context passed to a password provider, see [`ZipArchive::set_password_provider`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct PasswordRequest<'a> {
+    /// Name of the entry to decrypt
+    pub name: &'a str,
+    /// Index of the entry to decrypt
+    pub index: usize,
+    /// The scheme the entry is encrypted with
+    pub encryption: EncryptionMethod,
+    /// Number of passwords that were already rejected for this entry
+    pub attempt: usize,
+}
+
+type PasswordProviderFn = dyn Fn(&PasswordRequest<'_>) -> Option<Vec<u8>> + Send + Sync;
+
+#[derive(Clone)]
+pub(crate) struct PasswordProvider(Arc<PasswordProviderFn>);
+
+impl std::fmt::Debug for PasswordProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PasswordProvider")
+    }
+}
+/// The reader a [`ZipFile`] reads the data of the archive with
+///
+/// This is `dyn Read` for files borrowing an archive or a stream, and `dyn Read + Send` for files
+/// opened with [`ZipArchive::by_index_owned`], which can be sent to other threads. The trait is
+/// sealed.
+pub trait FileInput: sealed::Input {}
+
+impl FileInput for dyn Read + '_ {}
+
+impl FileInput for dyn Read + Send + '_ {}
+
+mod sealed {
+    use super::{CryptoReader, Read, lock};
+    use crate::codec::Codec;
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    /// The input of a custom decoder, shared so that the underlying reader can be recovered
+    pub struct CodecInput<D: ?Sized + Read>(pub(super) Arc<Mutex<CryptoReader<D>>>);
+
+    impl<D: ?Sized + Read> Read for CodecInput<D> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            lock(&self.0).read(buf)
+        }
+    }
+
+    pub trait Input: Read {
+        /// Create the decoder of a custom compression method
+        fn custom_decoder(
+            codec: &dyn Codec,
+            input: CodecInput<Self>,
+            uncompressed_size: u64,
+        ) -> io::Result<Box<Self>>;
+    }
+
+    impl<'a> Input for dyn Read + 'a {
+        fn custom_decoder(
+            codec: &dyn Codec,
+            input: CodecInput<Self>,
+            uncompressed_size: u64,
+        ) -> io::Result<Box<Self>> {
+            codec.decoder(Box::new(input), uncompressed_size)
+        }
+    }
+
+    impl<'a> Input for dyn Read + Send + 'a {
+        fn custom_decoder(
+            codec: &dyn Codec,
+            input: CodecInput<Self>,
+            uncompressed_size: u64,
+        ) -> io::Result<Box<Self>> {
+            codec.send_decoder(Box::new(input), uncompressed_size)
+        }
+    }
+}
+
 #[allow(clippy::large_enum_variant)]
-enum CryptoReader<'a> {
-    Plaintext(io::Take<&'a mut dyn Read>),
-    ZipCrypto(ZipCryptoReaderValid<io::Take<&'a mut dyn Read>>),
+enum CryptoReader<D: ?Sized + Read> {
+    Plaintext(Box<D>),
+    ZipCrypto(ZipCryptoReaderValid<Box<D>>),
     #[cfg(feature = "aes-crypto")]
     Aes {
-        reader: AesReaderValid<io::Take<&'a mut dyn Read>>,
+        reader: AesReaderValid<Box<D>>,
         vendor_version: AesVendorVersion,
     },
 }
 
-impl Read for CryptoReader<'_> {
+impl<D: ?Sized + Read> Read for CryptoReader<D> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         match self {
             CryptoReader::Plaintext(r) => r.read(buf),
@@ -106,9 +234,9 @@ impl Read for CryptoReader<'_> {
     }
 }
 
-impl<'a> CryptoReader<'a> {
+impl<D: ?Sized + Read> CryptoReader<D> {
     /// Consumes this decoder, returning the underlying reader.
-    pub fn into_inner(self) -> io::Take<&'a mut dyn Read> {
+    pub fn into_inner(self) -> Box<D> {
         match self {
             CryptoReader::Plaintext(r) => r,
             CryptoReader::ZipCrypto(r) => r.into_inner(),
@@ -132,32 +260,44 @@ impl<'a> CryptoReader<'a> {
     }
 }
 
-enum ZipFileReader<'a> {
+enum ZipFileReader<D: ?Sized + Read> {
     NoReader,
-    Raw(io::Take<&'a mut dyn io::Read>),
-    Stored(Crc32Reader<CryptoReader<'a>>),
+    /// The reader could not be created, every read returns the error again
+    Failed(ZipError),
+    Raw(Box<D>),
+    Stored(Crc32Reader<CryptoReader<D>>),
     #[cfg(any(
         feature = "deflate",
         feature = "deflate-miniz",
         feature = "deflate-zlib"
     ))]
-    Deflated(Crc32Reader<flate2::read::DeflateDecoder<CryptoReader<'a>>>),
+    Deflated(Crc32Reader<flate2::read::DeflateDecoder<CryptoReader<D>>>),
     #[cfg(feature = "deflate64")]
-    Deflate64(Crc32Reader<Deflate64Decoder<io::BufReader<CryptoReader<'a>>>>),
+    Deflate64(Crc32Reader<Deflate64Decoder<io::BufReader<CryptoReader<D>>>>),
     #[cfg(feature = "bzip2")]
-    Bzip2(Crc32Reader<BzDecoder<CryptoReader<'a>>>),
+    Bzip2(Crc32Reader<BzDecoder<CryptoReader<D>>>),
     #[cfg(feature = "zstd")]
-    Zstd(Crc32Reader<ZstdDecoder<'a, io::BufReader<CryptoReader<'a>>>>),
+    Zstd(Crc32Reader<ZstdDecoder<'static, io::BufReader<CryptoReader<D>>>>),
     #[cfg(feature = "lzma")]
-    Lzma(Crc32Reader<lzma_reader::LzmaReader<CryptoReader<'a>>>),
+    Lzma(Crc32Reader<lzma_reader::LzmaReader<CryptoReader<D>>>),
     #[cfg(feature = "xz")]
-    Xz(Crc32Reader<Box<XzReader<CryptoReader<'a>>>>),
+    Xz(Crc32Reader<Box<XzReader<CryptoReader<D>>>>),
+    Custom {
+        reader: Crc32Reader<Box<D>>,
+        input: Arc<Mutex<CryptoReader<D>>>,
+    },
+}
+
+/// Lock the input of a custom decoder, which is only poisoned if another reader panicked
+fn lock<D: ?Sized + Read>(input: &Mutex<CryptoReader<D>>) -> MutexGuard<'_, CryptoReader<D>> {
+    input.lock().unwrap_or_else(PoisonError::into_inner)
 }
 
-impl Read for ZipFileReader<'_> {
+impl<D: ?Sized + Read> Read for ZipFileReader<D> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         match self {
-            ZipFileReader::NoReader => panic!("ZipFileReader was in an invalid state"),
+            ZipFileReader::NoReader => Err(ZipError::ReaderError.into()),
+            ZipFileReader::Failed(err) => Err(err.duplicate().into()),
             ZipFileReader::Raw(r) => r.read(buf),
             ZipFileReader::Stored(r) => r.read(buf),
             #[cfg(any(
@@ -176,15 +316,18 @@ impl Read for ZipFileReader<'_> {
             ZipFileReader::Lzma(r) => r.read(buf),
             #[cfg(feature = "xz")]
             ZipFileReader::Xz(r) => r.read(buf),
+            ZipFileReader::Custom { reader, .. } => reader.read(buf),
         }
     }
 }
 
-impl<'a> ZipFileReader<'a> {
+impl<D: ?Sized + Read> ZipFileReader<D> {
     /// Consumes this decoder, returning the underlying reader.
-    pub fn into_inner(self) -> io::Take<&'a mut dyn Read> {
-        match self {
-            ZipFileReader::NoReader => panic!("ZipFileReader was in an invalid state"),
+    ///
+    /// Returns `None` if there is no reader or a custom decoder kept hold of it.
+    pub fn into_inner(self) -> Option<Box<D>> {
+        Some(match self {
+            ZipFileReader::NoReader | ZipFileReader::Failed(_) => return None,
             ZipFileReader::Raw(r) => r,
             ZipFileReader::Stored(r) => r.into_inner().into_inner(),
             #[cfg(any(
@@ -203,26 +346,65 @@ impl<'a> ZipFileReader<'a> {
             ZipFileReader::Lzma(r) => r.into_inner().into_inner().into_inner(),
             #[cfg(feature = "xz")]
             ZipFileReader::Xz(r) => r.into_inner().into_inner().into_inner(),
+            ZipFileReader::Custom { reader, input } => {
+                drop(reader);
+                Arc::try_unwrap(input)
+                    .ok()?
+                    .into_inner()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .into_inner()
+            }
+        })
+    }
+}
+
+/// The metadata of a [`ZipFile`]
+enum EntryData<'a> {
+    /// Borrowed from the archive
+    Borrowed(&'a ZipFileData),
+    /// Shared with the archive, for files owning their reader
+    Shared(Arc<zip_archive::Shared>, usize),
+    /// Read from a stream
+    Owned(ZipFileData),
+}
+
+impl std::ops::Deref for EntryData<'_> {
+    type Target = ZipFileData;
+
+    fn deref(&self) -> &ZipFileData {
+        match self {
+            EntryData::Borrowed(data) => data,
+            EntryData::Shared(shared, index) => &shared.files[*index],
+            EntryData::Owned(data) => data,
         }
     }
 }
 
 /// A struct for reading a zip file
-pub struct ZipFile<'a> {
-    data: Cow<'a, ZipFileData>,
-    crypto_reader: Option<CryptoReader<'a>>,
-    reader: ZipFileReader<'a>,
+///
+/// `D` is the type of reader the file reads the archive with, see [`FileInput`].
+pub struct ZipFile<'a, D: ?Sized + FileInput = dyn Read + 'a> {
+    data: EntryData<'a>,
+    index: Option<usize>,
+    crypto_reader: Option<CryptoReader<D>>,
+    codec: Option<Arc<dyn Codec>>,
+    limits: DecoderLimits,
+    #[cfg(feature = "zstd")]
+    zstd_dictionaries: Option<Arc<ZstdDictionaries>>,
+    reader: ZipFileReader<D>,
 }
 
-fn find_content<'a>(
-    data: &ZipFileData,
-    reader: &'a mut (impl Read + Seek),
-) -> ZipResult<io::Take<&'a mut dyn Read>> {
+fn find_content<R: Read + Seek>(data: &ZipFileData, mut reader: R) -> ZipResult<io::Take<R>> {
     // Parse local header
     reader.seek(io::SeekFrom::Start(data.header_start))?;
     let signature = reader.read_u32::<LittleEndian>()?;
     if signature != spec::LOCAL_FILE_HEADER_SIGNATURE {
-        return Err(ZipError::InvalidArchive("Invalid local file header"));
+        return Err(ZipError::detailed(ErrorKind::BadSignature {
+            expected: spec::LOCAL_FILE_HEADER_SIGNATURE,
+            found: signature,
+        })
+        .at_offset(data.header_start)
+        .in_entry(&data.file_name, None));
     }
 
     reader.seek(io::SeekFrom::Current(22))?;
@@ -233,22 +415,26 @@ fn find_content<'a>(
     data.data_start.store(data_start);
 
     reader.seek(io::SeekFrom::Start(data_start))?;
-    Ok((reader as &mut dyn Read).take(data.compressed_size))
+    Ok(reader.take(data.compressed_size))
 }
 
 #[allow(clippy::too_many_arguments)]
-fn make_crypto_reader<'a>(
+fn make_crypto_reader<D: ?Sized + Read>(
     compression_method: crate::compression::CompressionMethod,
     crc32: u32,
     last_modified_time: DateTime,
     using_data_descriptor: bool,
-    reader: io::Take<&'a mut dyn io::Read>,
+    reader: Box<D>,
     password: Option<&[u8]>,
     aes_info: Option<(AesMode, AesVendorVersion)>,
+    codecs: &CodecRegistry,
     #[cfg(feature = "aes-crypto")] compressed_size: u64,
-) -> ZipResult<Result<CryptoReader<'a>, InvalidPassword>> {
-    if let CompressionMethod::Unsupported(_) = compression_method {
-        return unsupported_zip_error("Compression method not supported");
+    #[cfg(feature = "aes-crypto")] key_cache: Option<&mut KeyCache>,
+) -> ZipResult<Result<CryptoReader<D>, InvalidPassword>> {
+    if let CompressionMethod::Unsupported(id) = compression_method {
+        if codecs.get(id).is_none() {
+            return Err(ZipError::detailed(ErrorKind::UnsupportedMethod(id)));
+        }
     }
 
     let reader = match (password, aes_info) {
@@ -260,7 +446,9 @@ fn make_crypto_reader<'a>(
         }
         #[cfg(feature = "aes-crypto")]
         (Some(password), Some((aes_mode, vendor_version))) => {
-            match AesReader::new(reader, aes_mode, compressed_size).validate(password)? {
+            match AesReader::new(reader, aes_mode, compressed_size)?
+                .validate(password, key_cache)?
+            {
                 None => return Ok(Err(InvalidPassword)),
                 Some(r) => CryptoReader::Aes {
                     reader: r,
@@ -279,21 +467,29 @@ fn make_crypto_reader<'a>(
                 Some(r) => CryptoReader::ZipCrypto(r),
             }
         }
-        (None, Some(_)) => return Ok(Err(InvalidPassword)),
+        (None, Some(_)) => {
+            return Err(ZipError::InvalidArchive(
+                "AES extra data field on an unencrypted file",
+            ));
+        }
         (None, None) => CryptoReader::Plaintext(reader),
     };
     Ok(Ok(reader))
 }
 
-fn make_reader(
+#[cfg_attr(not(feature = "lzma"), allow(unused_variables))]
+fn make_reader<D: ?Sized + FileInput>(
     compression_method: CompressionMethod,
     uncompressed_size: u64,
     crc32: u32,
-    reader: CryptoReader,
-) -> ZipFileReader {
+    reader: CryptoReader<D>,
+    codec: Option<&dyn Codec>,
+    limits: DecoderLimits,
+    #[cfg(feature = "zstd")] zstd_dictionaries: Option<&ZstdDictionaries>,
+) -> ZipResult<ZipFileReader<D>> {
     let ae2_encrypted = reader.is_ae2_encrypted();
 
-    match compression_method {
+    Ok(match compression_method {
         CompressionMethod::Stored => {
             ZipFileReader::Stored(Crc32Reader::new(reader, crc32, ae2_encrypted))
         }
@@ -318,12 +514,27 @@ fn make_reader(
         }
         #[cfg(feature = "zstd")]
         CompressionMethod::Zstd => {
-            let zstd_reader = ZstdDecoder::new(reader).unwrap();
+            let mut reader = io::BufReader::new(reader);
+            // The frame header references the dictionary the entry was compressed with
+            let dictionary = match zstd::zstd_safe::get_dict_id_from_frame(reader.fill_buf()?) {
+                Some(id) => zstd_dictionaries
+                    .and_then(|dictionaries| dictionaries.get(&id.get()))
+                    .ok_or(ZipError::UnsupportedArchive(
+                        "The zstd dictionary of the entry is missing",
+                    ))?
+                    .as_bytes(),
+                None => &[],
+            };
+            let mut zstd_reader = ZstdDecoder::with_dictionary(reader, dictionary)?;
+            if let Some(window_log_max) = limits.zstd_window_log_max {
+                zstd_reader.window_log_max(window_log_max)?;
+            }
             ZipFileReader::Zstd(Crc32Reader::new(zstd_reader, crc32, ae2_encrypted))
         }
         #[cfg(feature = "lzma")]
         CompressionMethod::Lzma => {
-            let lzma_reader = lzma_reader::LzmaReader::new(reader, uncompressed_size);
+            let lzma_reader =
+                lzma_reader::LzmaReader::new(reader, uncompressed_size, limits.lzma_dict_size_max);
             ZipFileReader::Lzma(Crc32Reader::new(lzma_reader, crc32, ae2_encrypted))
         }
         #[cfg(feature = "xz")]
@@ -331,8 +542,23 @@ fn make_reader(
             let xz_reader = XzReader::new(reader, false);
             ZipFileReader::Xz(Crc32Reader::new(Box::new(xz_reader), crc32, ae2_encrypted))
         }
-        _ => panic!("Compression method not supported"),
-    }
+        _ => match codec {
+            Some(codec) => {
+                let input = Arc::new(Mutex::new(reader));
+                let decoder =
+                    D::custom_decoder(codec, CodecInput(input.clone()), uncompressed_size)?;
+                ZipFileReader::Custom {
+                    reader: Crc32Reader::new(decoder, crc32, ae2_encrypted),
+                    input,
+                }
+            }
+            None => {
+                return Err(ZipError::detailed(ErrorKind::UnsupportedMethod(
+                    compression_method.to_u16(),
+                )));
+            }
+        },
+    })
 }
 
 impl<R: Read + io::Seek> ZipArchive<R> {
@@ -460,7 +686,7 @@ impl<R: Read + io::Seek> ZipArchive<R> {
             number_of_files
         };
 
-        let mut files = IndexMap::with_capacity(file_capacity);
+        let mut files = Vec::with_capacity(file_capacity);
 
         if reader.seek(io::SeekFrom::Start(directory_start)).is_err() {
             return Err(ZipError::InvalidArchive(
@@ -469,55 +695,205 @@ impl<R: Read + io::Seek> ZipArchive<R> {
         }
 
         for _ in 0..number_of_files {
-            let file = central_header_to_zip_file(&mut reader, archive_offset)?;
-            files.insert(file.file_name.clone(), file);
+            files.push(central_header_to_zip_file(&mut reader, archive_offset)?);
         }
+        let names = names::NameIndex::new(files.iter().map(|data| data.file_name.as_str()));
 
         let shared = Arc::new(zip_archive::Shared {
             files,
+            names,
             offset: archive_offset,
             comment: footer.zip_file_comment,
+            #[cfg(feature = "zstd")]
+            zstd_dictionaries: Default::default(),
+            directory_index: Default::default(),
         });
 
-        Ok(ZipArchive { reader, shared })
+        Ok(ZipArchive {
+            reader,
+            shared,
+            authenticate_aes_first: false,
+            password_provider: None,
+            max_password_attempts: 3,
+            codecs: CodecRegistry::new(),
+            decoder_limits: DecoderLimits::default(),
+            duplicate_policy: DuplicatePolicy::default(),
+            name_matching: None,
+        })
+    }
+
+    /// Read a ZIP archive like [`ZipArchive::new`], failing if several files have the same name
+    ///
+    /// Files with the same name can be used to show different content to different tools, so
+    /// archives from untrusted sources should be rejected when they contain any. See
+    /// [`ZipArchive::duplicate_names`].
+    pub fn new_strict(reader: R) -> ZipResult<ZipArchive<R>> {
+        let archive = ZipArchive::new(reader)?;
+        if archive.duplicate_names().next().is_some() {
+            return Err(ZipError::InvalidArchive("Duplicate file name"));
+        }
+        Ok(archive)
     }
     /// Extract a Zip archive into a directory, overwriting files if they
     /// already exist. Paths are sanitized with [`ZipFile::enclosed_name`].
     ///
+    /// On unix, symlink entries are recreated as symbolic links. Links whose
+    /// target would resolve outside of `directory` are rejected, and no entry
+    /// is ever written through a previously extracted link. On other
+    /// platforms, symlinks are extracted as regular files containing the
+    /// link target.
+    ///
     /// Extraction is not atomic; If an error is encountered, some of the files
-    /// may be left on disk.
+    /// may be left on disk. Use [`ExtractOptions::atomic`] for all-or-nothing
+    /// extraction.
     pub fn extract<P: AsRef<Path>>(&mut self, directory: P) -> ZipResult<()> {
-        use std::fs;
-
-        for i in 0..self.len() {
-            let mut file = self.by_index(i)?;
-            let filepath = file
-                .enclosed_name()
-                .ok_or(ZipError::InvalidArchive("Invalid file path"))?;
+        self.extract_with_options(directory, ExtractOptions::default())
+    }
 
-            let outpath = directory.as_ref().join(filepath);
+    /// Extract a Zip archive into a directory, configured by [`ExtractOptions`].
+    ///
+    /// See [`ZipArchive::extract`] for how paths and symlinks are handled.
+    pub fn extract_with_options<P: AsRef<Path>>(
+        &mut self,
+        directory: P,
+        options: ExtractOptions<'_>,
+    ) -> ZipResult<()> {
+        let len = self.len();
+        extract::run(directory.as_ref(), options, Some(len), |extractor| {
+            for i in 0..len {
+                let mut file = self.by_index(i)?;
+                extractor.extract_entry(&mut file)?;
+            }
+            Ok(())
+        })
+    }
 
-            if file.name().ends_with('/') {
-                fs::create_dir_all(&outpath)?;
-            } else {
-                if let Some(p) = outpath.parent() {
-                    if !p.exists() {
-                        fs::create_dir_all(p)?;
+    /// Extract the files selected by `matcher` into a directory
+    ///
+    /// The matcher gets the sanitized path of each entry, and is either a [`Glob`] or a closure
+    /// taking a `&Path` and returning a `bool`. Entries that are not selected are not opened, so
+    /// no password is needed for them. Paths and symlinks are handled like in
+    /// [`ZipArchive::extract`], and directories above the selected files are created as needed.
+    ///
+    /// ```no_run
+    /// # fn main() -> ziperu::result::ZipResult<()> {
+    /// use ziperu::read::Glob;
+    ///
+    /// let mut zip = ziperu::ZipArchive::new(std::fs::File::open("archive.zip")?)?;
+    /// zip.extract_matching("output", Glob::new("assets/**/*.png"))?;
+    /// zip.extract_matching("output", |path: &std::path::Path| path.starts_with("docs"))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Fails like [`ZipArchive::extract`] if any entry has an invalid path, even if it would not
+    /// be selected.
+    pub fn extract_matching<P: AsRef<Path>, M: EntryMatcher>(
+        &mut self,
+        directory: P,
+        mut matcher: M,
+    ) -> ZipResult<()> {
+        let len = self.len();
+        extract::run(
+            directory.as_ref(),
+            ExtractOptions::default(),
+            Some(len),
+            |extractor| {
+                for i in 0..len {
+                    let path = self.shared.files[i]
+                        .enclosed_name()
+                        .and_then(extract::normalize)
+                        .ok_or(ZipError::InvalidArchive("Invalid file path"))?;
+                    if matcher.matches(&path) {
+                        let mut file = self.by_index(i)?;
+                        extractor.extract_entry(&mut file)?;
                     }
                 }
-                let mut outfile = fs::File::create(&outpath)?;
-                io::copy(&mut file, &mut outfile)?;
-            }
-            // Get and Set permissions
-            #[cfg(unix)]
-            {
-                use std::os::unix::fs::PermissionsExt;
-                if let Some(mode) = file.unix_mode() {
-                    fs::set_permissions(&outpath, fs::Permissions::from_mode(mode))?;
-                }
-            }
-        }
-        Ok(())
+                Ok(())
+            },
+        )
+    }
+
+    /// Set whether AES encrypted entries are authenticated before any of their data is released.
+    ///
+    /// By default, the authentication code of an AES encrypted entry is only checked once all of
+    /// its data has been read, so tampered data may already have been consumed when the error is
+    /// reported. When enabled, opening an entry with a password reads its encrypted data twice:
+    /// once to check the authentication code and once to decrypt it. Opening a tampered entry
+    /// then fails with an [`io::ErrorKind::InvalidData`] error.
+    ///
+    /// The default is `false`.
+    pub fn set_authenticate_aes_first(&mut self, enabled: bool) {
+        self.authenticate_aes_first = enabled;
+    }
+
+    /// Set a callback providing passwords for encrypted entries.
+    ///
+    /// When an encrypted entry is opened without a password, for example by
+    /// [`ZipArchive::by_index`], [`ZipArchive::by_name`] or [`ZipArchive::extract`], the provider
+    /// is called until it returns a password that is accepted for the entry. It returns `None` to
+    /// give up, in which case the entry cannot be opened. The number of passwords tried per entry
+    /// is limited, see [`ZipArchive::set_max_password_attempts`].
+    ///
+    /// The same weaknesses as with [`ZipArchive::by_index_decrypt`] apply: a wrong password may be
+    /// accepted, which is only detected once the data is read.
+    ///
+    /// ```no_run
+    /// # fn main() -> ziperu::result::ZipResult<()> {
+    /// let mut zip = ziperu::ZipArchive::new(std::fs::File::open("archive.zip")?)?;
+    /// let candidates: [&[u8]; 2] = [b"first", b"second"];
+    /// zip.set_password_provider(move |request| {
+    ///     candidates.get(request.attempt).map(|password| password.to_vec())
+    /// });
+    /// zip.extract("output")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_password_provider<F>(&mut self, provider: F)
+    where
+        F: Fn(&PasswordRequest<'_>) -> Option<Vec<u8>> + Send + Sync + 'static,
+    {
+        self.password_provider = Some(PasswordProvider(Arc::new(provider)));
+    }
+
+    /// Remove the callback set by [`ZipArchive::set_password_provider`].
+    pub fn clear_password_provider(&mut self) {
+        self.password_provider = None;
+    }
+
+    /// Set how many passwords from the password provider are tried for an entry.
+    ///
+    /// Once this many passwords were rejected, opening the entry fails with a
+    /// [`ZipError::Detailed`] error of kind [`crate::result::ErrorKind::WrongPassword`], as it
+    /// does when the provider gives up after rejected passwords. If the provider gives up without
+    /// returning a password, the error is [`ZipError::PASSWORD_REQUIRED`].
+    ///
+    /// The default is 3.
+    pub fn set_max_password_attempts(&mut self, attempts: usize) {
+        self.max_password_attempts = attempts;
+    }
+
+    /// Set the codecs used to decompress entries with methods that are not built in.
+    ///
+    /// See [`crate::codec`].
+    pub fn set_codecs(&mut self, codecs: CodecRegistry) {
+        self.codecs = codecs;
+    }
+
+    /// Set limits on the memory the decoders may allocate, see [`DecoderLimits`].
+    pub fn set_decoder_limits(&mut self, limits: DecoderLimits) {
+        self.decoder_limits = limits;
+    }
+
+    /// Get the scheme used to encrypt an entry, or `None` if it is not encrypted
+    ///
+    /// Unlike [`ZipFile::encryption`], this does not need a password.
+    pub fn encryption(&self, file_number: usize) -> ZipResult<Option<EncryptionMethod>> {
+        self.shared
+            .files
+            .get(file_number)
+            .map(|data| data.encryption())
+            .ok_or(ZipError::FileNotFound)
     }
 
     /// Number of files contained in this zip.
@@ -544,15 +920,169 @@ impl<R: Read + io::Seek> ZipArchive<R> {
     }
 
     /// Returns an iterator over all the file and directory names in this archive.
+    ///
+    /// Names used by several files are returned once for each of them.
     pub fn file_names(&self) -> impl Iterator<Item = &str> {
-        self.shared.files.keys().map(|s| s.as_str())
+        self.shared.files.iter().map(|data| data.file_name.as_str())
     }
 
     /// Returns true if the file exists
     ///
     /// This method does not decrypt or initialize any metadata for the file.
+    ///
+    /// Names are compared as set with [`ZipArchive::set_name_matching`].
     pub fn file_exists(&self, name: &str) -> bool {
-        self.shared.files.contains_key(name)
+        !self.shared.names.all(name).is_empty()
+            || self
+                .name_matching
+                .as_ref()
+                .is_some_and(|index| !index.candidates(name).is_empty())
+    }
+
+    /// Set which file is used by [`ZipArchive::by_name`] if several files have the same name
+    ///
+    /// The default is [`DuplicatePolicy::Last`].
+    pub fn set_duplicate_policy(&mut self, policy: DuplicatePolicy) {
+        self.duplicate_policy = policy;
+    }
+
+    /// Set how [`ZipArchive::by_name`] compares names, see [`NameMatching`]
+    ///
+    /// Matching names other than exactly builds an index of the names of the archive.
+    pub fn set_name_matching(&mut self, matching: NameMatching) {
+        self.name_matching = (!matching.is_exact()).then(|| {
+            let names = self.shared.files.iter().map(|data| data.file_name.as_str());
+            Arc::new(names::MatchingIndex::new(
+                matching,
+                names,
+                &self.shared.names,
+            ))
+        });
+    }
+
+    /// Get the index of the file with this name, chosen by the [`DuplicatePolicy`] if there are
+    /// several
+    ///
+    /// Only exact names are found, [`ZipArchive::find_name`] also compares them as set with
+    /// [`ZipArchive::set_name_matching`].
+    pub fn index_for_name(&self, name: &str) -> Option<usize> {
+        self.shared.names.get(name, self.duplicate_policy)
+    }
+
+    /// Get the index of the file [`ZipArchive::by_name`] opens for a name
+    ///
+    /// Names are compared as set with [`ZipArchive::set_name_matching`], and the
+    /// [`DuplicatePolicy`] chooses among files with the same name. Fails with
+    /// [`ZipError::FileNotFound`] if no file matches, and with [`ErrorKind::AmbiguousName`] if
+    /// the name matches several different names but none exactly.
+    pub fn find_name(&self, name: &str) -> ZipResult<usize> {
+        if let Some(index) = self.index_for_name(name) {
+            return Ok(index);
+        }
+        let candidates = match &self.name_matching {
+            Some(index) => index.candidates(name),
+            None => &[],
+        };
+        match candidates {
+            [] => Err(ZipError::FileNotFound),
+            [first] => Ok(self
+                .index_for_name(&self.shared.files[*first].file_name)
+                .unwrap_or(*first)),
+            _ => Err(ZipError::detailed(ErrorKind::AmbiguousName {
+                names: candidates
+                    .iter()
+                    .map(|&index| self.shared.files[index].file_name.clone())
+                    .collect(),
+            })),
+        }
+    }
+
+    /// Returns the groups of different names that match each other with the
+    /// [`NameMatching`] set by [`ZipArchive::set_name_matching`]
+    ///
+    /// Looking up a name of a group that is not in the archive fails. The groups are empty for
+    /// exact matching.
+    pub fn ambiguous_names(&self) -> impl Iterator<Item = Vec<&str>> {
+        let files = &self.shared.files;
+        self.name_matching
+            .iter()
+            .flat_map(|index| index.ambiguous())
+            .map(move |group| {
+                group
+                    .iter()
+                    .map(|&index| files[index].file_name.as_str())
+                    .collect()
+            })
+    }
+
+    /// Returns an iterator over the names used by more than one file
+    ///
+    /// The central directory of a well-formed archive lists each name once. All files with a
+    /// name can be found with [`ZipArchive::entries_named`].
+    pub fn duplicate_names(&self) -> impl ExactSizeIterator<Item = &str> {
+        self.shared.names.duplicates()
+    }
+
+    /// Returns an iterator over the metadata of all files with this name, in the order of the
+    /// central directory
+    pub fn entries_named(&self, name: &str) -> impl ExactSizeIterator<Item = ZipFileMetadata<'_>> {
+        let files = &self.shared.files;
+        self.shared
+            .names
+            .all(name)
+            .iter()
+            .map(|&index| ZipFileMetadata::new(&files[index], index))
+    }
+
+    /// Returns an iterator over the metadata of all files in this archive, in the order of the
+    /// central directory
+    ///
+    /// This does not read from the archive, unlike opening each file with
+    /// [`ZipArchive::by_index`].
+    pub fn entries(&self) -> impl ExactSizeIterator<Item = ZipFileMetadata<'_>> {
+        self.shared
+            .files
+            .iter()
+            .enumerate()
+            .map(|(index, data)| ZipFileMetadata::new(data, index))
+    }
+
+    /// Get a hierarchical view of the files in this archive, see [`DirectoryTree`]
+    ///
+    /// The tree is built from the names of the files when it is first needed, and shared by the
+    /// clones of this archive.
+    pub fn directory_tree(&self) -> DirectoryTree<'_> {
+        let index = self
+            .shared
+            .directory_index
+            .get_or_init(|| tree::DirectoryIndex::new(&self.shared.files));
+        DirectoryTree::new(&self.shared.files, index)
+    }
+
+    /// Returns an iterator over the metadata of the files whose path matches a [`Glob`] pattern
+    ///
+    /// Paths are normalized like in [`ZipArchive::directory_tree`], so directories are matched
+    /// without their trailing `/`. Files without an [enclosed name](ZipFile::enclosed_name) never
+    /// match. This does not read from the archive.
+    pub fn glob(&self, pattern: &str) -> impl Iterator<Item = ZipFileMetadata<'_>> + '_ {
+        let glob = Glob::new(pattern);
+        self.entries().filter(move |entry| {
+            entry
+                .enclosed_name()
+                .and_then(tree::normalize)
+                .is_some_and(|path| glob.is_match(&path))
+        })
+    }
+
+    /// Get the metadata of a file by index
+    ///
+    /// This does not read from the archive, see [`ZipArchive::entries`].
+    pub fn metadata(&self, file_number: usize) -> ZipResult<ZipFileMetadata<'_>> {
+        self.shared
+            .files
+            .get(file_number)
+            .map(|data| ZipFileMetadata::new(data, file_number))
+            .ok_or(ZipError::FileNotFound)
     }
 
     /// Search for a file entry by name, decrypt with given password
@@ -577,8 +1107,11 @@ impl<R: Read + io::Seek> ZipArchive<R> {
     }
 
     /// Search for a file entry by name
+    ///
+    /// The file is found like with [`ZipArchive::find_name`].
     pub fn by_name<'a>(&'a mut self, name: &str) -> ZipResult<ZipFile<'a>> {
-        Ok(self.by_name_with_optional_password(name, None)?.unwrap())
+        self.by_name_with_optional_password(name, None)?
+            .map_err(|InvalidPassword| ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED))
     }
 
     fn by_name_with_optional_password<'a>(
@@ -586,12 +1119,7 @@ impl<R: Read + io::Seek> ZipArchive<R> {
         name: &str,
         password: Option<&[u8]>,
     ) -> ZipResult<Result<ZipFile<'a>, InvalidPassword>> {
-        let index = match self.shared.files.get_index_of(name) {
-            Some(index) => index,
-            None => {
-                return Err(ZipError::FileNotFound);
-            }
-        };
+        let index = self.find_name(name)?;
         self.by_index_with_optional_password(index, password)
     }
 
@@ -618,9 +1146,8 @@ impl<R: Read + io::Seek> ZipArchive<R> {
 
     /// Get a contained file by index
     pub fn by_index(&mut self, file_number: usize) -> ZipResult<ZipFile<'_>> {
-        Ok(self
-            .by_index_with_optional_password(file_number, None)?
-            .unwrap())
+        self.by_index_with_optional_password(file_number, None)?
+            .map_err(|InvalidPassword| ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED))
     }
 
     /// Get a contained file by index without decompressing it
@@ -628,13 +1155,19 @@ impl<R: Read + io::Seek> ZipArchive<R> {
         let reader = &mut self.reader;
         self.shared
             .files
-            .get_index(file_number)
+            .get(file_number)
             .ok_or(ZipError::FileNotFound)
-            .and_then(move |(_, data)| {
+            .and_then(move |data| {
+                let content: Box<dyn Read> = Box::new(find_content(data, reader)?);
                 Ok(ZipFile {
                     crypto_reader: None,
-                    reader: ZipFileReader::Raw(find_content(data, reader)?),
-                    data: Cow::Borrowed(data),
+                    codec: None,
+                    limits: DecoderLimits::default(),
+                    #[cfg(feature = "zstd")]
+                    zstd_dictionaries: None,
+                    reader: ZipFileReader::Raw(content),
+                    data: EntryData::Borrowed(data),
+                    index: Some(file_number),
                 })
             })
     }
@@ -642,40 +1175,326 @@ impl<R: Read + io::Seek> ZipArchive<R> {
     fn by_index_with_optional_password<'a>(
         &'a mut self,
         file_number: usize,
-        mut password: Option<&[u8]>,
+        password: Option<&[u8]>,
     ) -> ZipResult<Result<ZipFile<'a>, InvalidPassword>> {
-        let (_, data) = self
+        #[cfg(feature = "aes-crypto")]
+        let mut key_cache = KeyCache::default();
+
+        if password.is_none() && self.password_provider.is_some() {
+            if let Some(password) = self.provide_password(
+                file_number,
+                #[cfg(feature = "aes-crypto")]
+                &mut key_cache,
+            )? {
+                return self.open_checked_entry(
+                    file_number,
+                    Some(&password),
+                    #[cfg(feature = "aes-crypto")]
+                    &mut key_cache,
+                );
+            }
+        }
+
+        self.open_entry(
+            file_number,
+            password,
+            #[cfg(feature = "aes-crypto")]
+            &mut key_cache,
+        )
+    }
+
+    /// Ask the password provider for passwords until one is accepted for the entry.
+    ///
+    /// Returns `None` if the entry is not encrypted. The returned password has already been
+    /// checked, open the entry with [`ZipArchive::open_checked_entry`] so that an AES entry isn't
+    /// authenticated twice.
+    fn provide_password(
+        &mut self,
+        file_number: usize,
+        #[cfg(feature = "aes-crypto")] key_cache: &mut KeyCache,
+    ) -> ZipResult<Option<SecretBytes>> {
+        let shared = self.shared.clone();
+        let data = shared
+            .files
+            .get(file_number)
+            .ok_or(ZipError::FileNotFound)?;
+        let name = &data.file_name;
+        let (Some(encryption), Some(provider)) =
+            (data.encryption(), self.password_provider.clone())
+        else {
+            return Ok(None);
+        };
+
+        #[cfg(feature = "aes-crypto")]
+        let authenticated = self.authenticate_aes_first && data.aes_mode.is_some();
+        #[cfg(not(feature = "aes-crypto"))]
+        let authenticated = false;
+
+        let mut rejected = false;
+        for attempt in 0..self.max_password_attempts {
+            let request = PasswordRequest {
+                name,
+                index: file_number,
+                encryption,
+                attempt,
+            };
+            let Some(password) = (provider.0)(&request).map(secret) else {
+                break;
+            };
+            let accepted = match self.check_password(
+                file_number,
+                Some(&password),
+                #[cfg(feature = "aes-crypto")]
+                key_cache,
+            )? {
+                Err(InvalidPassword) => false,
+                // The password was verified with the authentication of the entry
+                Ok(_) if authenticated => true,
+                Ok(password) => self
+                    .open_checked_entry(
+                        file_number,
+                        password,
+                        #[cfg(feature = "aes-crypto")]
+                        key_cache,
+                    )?
+                    .is_ok(),
+            };
+            if accepted {
+                return Ok(Some(password));
+            }
+            rejected = true;
+        }
+
+        match rejected {
+            true => {
+                Err(ZipError::detailed(ErrorKind::WrongPassword).in_entry(name, Some(file_number)))
+            }
+            false => Err(ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED)),
+        }
+    }
+
+    fn open_entry<'a>(
+        &'a mut self,
+        file_number: usize,
+        password: Option<&[u8]>,
+        #[cfg(feature = "aes-crypto")] key_cache: &mut KeyCache,
+    ) -> ZipResult<Result<ZipFile<'a>, InvalidPassword>> {
+        let password = match self.check_password(
+            file_number,
+            password,
+            #[cfg(feature = "aes-crypto")]
+            key_cache,
+        )? {
+            Ok(password) => password,
+            Err(InvalidPassword) => return Ok(Err(InvalidPassword)),
+        };
+        self.open_checked_entry(
+            file_number,
+            password,
+            #[cfg(feature = "aes-crypto")]
+            key_cache,
+        )
+    }
+
+    /// Like [`ZipArchive::open_entry`], with a password already checked by
+    /// [`ZipArchive::check_password`]
+    fn open_checked_entry<'a>(
+        &'a mut self,
+        file_number: usize,
+        password: Option<&[u8]>,
+        #[cfg(feature = "aes-crypto")] key_cache: &mut KeyCache,
+    ) -> ZipResult<Result<ZipFile<'a>, InvalidPassword>> {
+        #[cfg(feature = "zstd")]
+        let zstd_dictionaries = self.entry_zstd_dictionaries(
+            file_number,
+            password,
+            #[cfg(feature = "aes-crypto")]
+            key_cache,
+        )?;
+
+        let data = self
             .shared
             .files
-            .get_index(file_number)
+            .get(file_number)
             .ok_or(ZipError::FileNotFound)?;
+        let content =
+            find_content(data, &mut self.reader).map_err(|err| in_entry(err, data, file_number))?;
+        open_content(
+            EntryData::Borrowed(data),
+            file_number,
+            Box::new(content),
+            password,
+            &self.codecs,
+            self.decoder_limits,
+            #[cfg(feature = "zstd")]
+            zstd_dictionaries,
+            #[cfg(feature = "aes-crypto")]
+            Some(key_cache),
+        )
+    }
+
+    /// Like [`ZipArchive::open_checked_entry`], but the file owns the reader of the archive
+    #[cfg_attr(not(feature = "zstd"), allow(unused_mut))]
+    fn open_checked_entry_owned(
+        mut self,
+        file_number: usize,
+        password: Option<&[u8]>,
+        #[cfg(feature = "aes-crypto")] key_cache: &mut KeyCache,
+    ) -> ZipResult<Result<ZipFile<'static, dyn Read + Send>, InvalidPassword>>
+    where
+        R: Send + 'static,
+    {
+        #[cfg(feature = "zstd")]
+        let zstd_dictionaries = self.entry_zstd_dictionaries(
+            file_number,
+            password,
+            #[cfg(feature = "aes-crypto")]
+            key_cache,
+        )?;
 
-        match (password, data.encrypted) {
+        let shared = self.shared.clone();
+        let data = shared
+            .files
+            .get(file_number)
+            .ok_or(ZipError::FileNotFound)?;
+        let content =
+            find_content(data, self.reader).map_err(|err| in_entry(err, data, file_number))?;
+        open_content(
+            EntryData::Shared(shared.clone(), file_number),
+            file_number,
+            Box::new(content),
+            password,
+            &self.codecs,
+            self.decoder_limits,
+            #[cfg(feature = "zstd")]
+            zstd_dictionaries,
+            #[cfg(feature = "aes-crypto")]
+            Some(key_cache),
+        )
+    }
+
+    /// Check the password of an entry before its content is read
+    ///
+    /// Returns the password to decrypt the entry with, which is `None` if it is not encrypted.
+    /// The key derived to authenticate an AES entry is added to `key_cache`, so that decrypting
+    /// the entry afterwards doesn't derive it again.
+    fn check_password<'p>(
+        &mut self,
+        file_number: usize,
+        password: Option<&'p [u8]>,
+        #[cfg(feature = "aes-crypto")] key_cache: &mut KeyCache,
+    ) -> ZipResult<Result<Option<&'p [u8]>, InvalidPassword>> {
+        let data = self
+            .shared
+            .files
+            .get(file_number)
+            .ok_or(ZipError::FileNotFound)?;
+        let password = match (password, data.encrypted) {
             (None, true) => return Err(ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED)),
-            (Some(_), false) => password = None, //Password supplied, but none needed! Discard.
-            _ => {}
+            (_, false) => None, //Password supplied, but none needed! Discard.
+            (password, true) => password,
+        };
+
+        #[cfg(feature = "aes-crypto")]
+        if let (true, Some(password), Some((aes_mode, _))) =
+            (self.authenticate_aes_first, password, data.aes_mode)
+        {
+            let limit_reader = find_content(data, &mut self.reader)?;
+            match AesReader::new(limit_reader, aes_mode, data.compressed_size)
+                .map_err(|err| in_entry(err, data, file_number))?
+                .validate(password, Some(key_cache))
+                .map_err(|err| in_entry(err.into(), data, file_number))?
+            {
+                None => return Ok(Err(InvalidPassword)),
+                Some(reader) => reader.authenticate()?,
+            }
         }
-        let limit_reader = find_content(data, &mut self.reader)?;
-
-        match make_crypto_reader(
-            data.compression_method,
-            data.crc32,
-            data.last_modified_time,
-            data.using_data_descriptor,
-            limit_reader,
+        Ok(Ok(password))
+    }
+
+    /// The zstd dictionary needed to decompress an entry, decrypted with `password`
+    ///
+    /// The id of the dictionary is read from the frame header of the entry, so that only the
+    /// dictionaries in use are loaded.
+    #[cfg(feature = "zstd")]
+    fn entry_zstd_dictionaries(
+        &mut self,
+        file_number: usize,
+        password: Option<&[u8]>,
+        #[cfg(feature = "aes-crypto")] key_cache: &mut KeyCache,
+    ) -> ZipResult<Option<Arc<ZstdDictionaries>>> {
+        let shared = self.shared.clone();
+        let data = match shared.files.get(file_number) {
+            Some(data) if data.compression_method == CompressionMethod::Zstd => data,
+            _ => return Ok(None),
+        };
+        let content: Box<dyn Read + '_> = Box::new(
+            find_content(data, &mut self.reader).map_err(|err| in_entry(err, data, file_number))?,
+        );
+        let file = match open_content(
+            EntryData::Borrowed(data),
+            file_number,
+            content,
             password,
-            data.aes_mode,
+            &self.codecs,
+            self.decoder_limits,
+            None,
             #[cfg(feature = "aes-crypto")]
-            data.compressed_size,
-        ) {
-            Ok(Ok(crypto_reader)) => Ok(Ok(ZipFile {
-                crypto_reader: Some(crypto_reader),
-                reader: ZipFileReader::NoReader,
-                data: Cow::Borrowed(data),
-            })),
-            Err(e) => Err(e),
-            Ok(Err(e)) => Ok(Err(e)),
+            Some(key_cache),
+        )? {
+            Ok(file) => file,
+            Err(InvalidPassword) => return Ok(None),
+        };
+        let Some(id) = file.zstd_dictionary_id()? else {
+            return Ok(None);
+        };
+        Ok(self
+            .zstd_dictionary(id)?
+            .map(|dictionary| Arc::new(ZstdDictionaries::from([(id, dictionary)]))))
+    }
+
+    /// Load the zstd dictionary with this id from the archive, see [`crate::zstd_dictionary`]
+    #[cfg(feature = "zstd")]
+    fn zstd_dictionary(&mut self, id: u32) -> ZipResult<Option<ZstdDictionary>> {
+        let loaded = self
+            .shared
+            .zstd_dictionaries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&id)
+            .cloned();
+        if let Some(dictionary) = loaded {
+            return Ok(dictionary);
+        }
+        let shared = self.shared.clone();
+        let mut dictionary = None;
+        for (index, data) in shared.files.iter().enumerate() {
+            let entry_id = ZstdDictionary::id_from_entry(
+                &data.file_name,
+                data.compression_method,
+                data.compressed_size,
+            );
+            if entry_id != Some(id) || data.encrypted {
+                continue;
+            }
+            // Entries that can't be read are skipped like invalid dictionaries
+            let mut content = Vec::new();
+            let read = self
+                .by_index(index)
+                .and_then(|mut file| Ok(file.read_to_end(&mut content)?));
+            if let Some(valid) = read
+                .ok()
+                .and_then(|_| ZstdDictionary::from_entry_content(id, &content))
+            {
+                dictionary = Some(valid);
+            }
         }
+        shared
+            .zstd_dictionaries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(id, dictionary.clone());
+        Ok(dictionary)
     }
 
     /// Unwrap and return the inner reader object
@@ -686,6 +1505,138 @@ impl<R: Read + io::Seek> ZipArchive<R> {
     }
 }
 
+/// Methods returning files that own a clone of the reader
+impl<R: Read + io::Seek + Clone + Send + 'static> ZipArchive<R> {
+    /// Get a contained file by index, with a reader that doesn't borrow the archive
+    ///
+    /// The file owns a clone of the reader of the archive and shares its metadata, so it can be
+    /// returned from functions, stored in structs and sent to other threads. Files opened like
+    /// this can be read at the same time if the clones read independently of each other, as
+    /// `io::Cursor<Arc<[u8]>>` does. Clones sharing a position, such as files cloned with
+    /// `File::try_clone`, don't.
+    ///
+    /// Passwords are asked for as with [`ZipArchive::by_index`]. Entries compressed with a custom
+    /// codec are decoded with [`Codec::send_decoder`].
+    ///
+    /// ```
+    /// # fn main() -> ziperu::result::ZipResult<()> {
+    /// use std::io::{Cursor, Read};
+    /// use std::sync::Arc;
+    /// use ziperu::read::ZipFile;
+    /// use ziperu::result::ZipResult;
+    ///
+    /// fn open_first(data: Arc<[u8]>) -> ZipResult<ZipFile<'static, dyn Read + Send>> {
+    ///     let archive = ziperu::ZipArchive::new(Cursor::new(data))?;
+    ///     archive.by_index_owned(0)
+    /// }
+    ///
+    /// # let data: Arc<[u8]> = {
+    /// #     use std::io::Write;
+    /// #     let mut zip = ziperu::ZipWriter::new(Cursor::new(Vec::new()));
+    /// #     zip.start_file("hello.txt", ziperu::write::FileOptions::default())?;
+    /// #     zip.write_all(b"Hello")?;
+    /// #     zip.finish()?.into_inner().into()
+    /// # };
+    /// let mut file = open_first(data)?;
+    /// let content = std::thread::spawn(move || {
+    ///     let mut content = String::new();
+    ///     file.read_to_string(&mut content).map(|_| content)
+    /// });
+    /// # assert_eq!(content.join().unwrap()?, "Hello");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn by_index_owned(
+        &self,
+        file_number: usize,
+    ) -> ZipResult<ZipFile<'static, dyn Read + Send>> {
+        let mut archive = self.clone();
+        #[cfg(feature = "aes-crypto")]
+        let mut key_cache = KeyCache::default();
+        let provided = archive.provide_password(
+            file_number,
+            #[cfg(feature = "aes-crypto")]
+            &mut key_cache,
+        )?;
+        let password = match &provided {
+            Some(password) => Some(&password[..]),
+            None => archive
+                .check_password(
+                    file_number,
+                    None,
+                    #[cfg(feature = "aes-crypto")]
+                    &mut key_cache,
+                )?
+                .map_err(|InvalidPassword| {
+                    ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED)
+                })?,
+        };
+        archive
+            .open_checked_entry_owned(
+                file_number,
+                password,
+                #[cfg(feature = "aes-crypto")]
+                &mut key_cache,
+            )?
+            .map_err(|InvalidPassword| ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED))
+    }
+
+    /// Search for a file entry by name, with a reader that doesn't borrow the archive
+    ///
+    /// See [`ZipArchive::by_index_owned`].
+    pub fn by_name_owned(&self, name: &str) -> ZipResult<ZipFile<'static, dyn Read + Send>> {
+        let index = self.find_name(name)?;
+        self.by_index_owned(index)
+    }
+}
+
+/// Add the name, index and offset of an entry of an archive to an error
+fn in_entry(err: ZipError, data: &ZipFileData, file_number: usize) -> ZipError {
+    err.at_offset(data.header_start)
+        .in_entry(&data.file_name, Some(file_number))
+}
+
+/// Create the file reading the content of an entry, decrypting it with `password`
+#[allow(clippy::too_many_arguments)]
+fn open_content<'a, D: ?Sized + FileInput>(
+    data: EntryData<'a>,
+    file_number: usize,
+    content: Box<D>,
+    password: Option<&[u8]>,
+    codecs: &CodecRegistry,
+    limits: DecoderLimits,
+    #[cfg(feature = "zstd")] zstd_dictionaries: Option<Arc<ZstdDictionaries>>,
+    #[cfg(feature = "aes-crypto")] key_cache: Option<&mut KeyCache>,
+) -> ZipResult<Result<ZipFile<'a, D>, InvalidPassword>> {
+    match make_crypto_reader(
+        data.compression_method,
+        data.crc32,
+        data.last_modified_time,
+        data.using_data_descriptor,
+        content,
+        password,
+        data.aes_mode,
+        codecs,
+        #[cfg(feature = "aes-crypto")]
+        data.compressed_size,
+        #[cfg(feature = "aes-crypto")]
+        key_cache,
+    ) {
+        Ok(Ok(crypto_reader)) => Ok(Ok(ZipFile {
+            crypto_reader: Some(crypto_reader),
+            codec: codecs.custom(data.compression_method),
+            limits,
+            #[cfg(feature = "zstd")]
+            zstd_dictionaries,
+            reader: ZipFileReader::NoReader,
+            data,
+            index: Some(file_number),
+        })),
+        Err(e) => Err(in_entry(e, &data, file_number)),
+        Ok(Err(e)) => Ok(Err(e)),
+    }
+}
+
 fn unsupported_zip_error<T>(detail: &'static str) -> ZipResult<T> {
     Err(ZipError::UnsupportedArchive(detail))
 }
@@ -700,7 +1651,11 @@ pub(crate) fn central_header_to_zip_file<R: Read + io::Seek>(
     // Parse central header
     let signature = reader.read_u32::<LittleEndian>()?;
     if signature != spec::CENTRAL_DIRECTORY_HEADER_SIGNATURE {
-        Err(ZipError::InvalidArchive("Invalid Central Directory header"))
+        Err(ZipError::detailed(ErrorKind::BadSignature {
+            expected: spec::CENTRAL_DIRECTORY_HEADER_SIGNATURE,
+            found: signature,
+        })
+        .at_offset(central_header_start))
     } else {
         central_header_to_zip_file_inner(reader, archive_offset, central_header_start)
     }
@@ -769,6 +1724,7 @@ fn central_header_to_zip_file_inner<R: Read>(
         external_attributes: external_file_attributes,
         large_file: false,
         aes_mode: None,
+        codec_version_needed: None,
     };
 
     match parse_extra_field(&mut result) {
@@ -801,7 +1757,7 @@ fn parse_extra_field(file: &mut ZipFileData) -> ZipResult<()> {
         let mut len_left = len as i64;
         match kind {
             // Zip64 extended information extra field
-            0x0001 => {
+            ZIP64_EXTRA_FIELD_ID => {
                 if file.uncompressed_size == spec::ZIP64_BYTES_THR {
                     file.large_file = true;
                     file.uncompressed_size = reader.read_u64::<LittleEndian>()?;
@@ -817,33 +1773,19 @@ fn parse_extra_field(file: &mut ZipFileData) -> ZipResult<()> {
                     len_left -= 8;
                 }
             }
-            0x9901 => {
-                // AES
-                if len != 7 {
-                    return Err(ZipError::UnsupportedArchive(
-                        "AES extra data field has an unsupported length",
-                    ));
-                }
-                let vendor_version = reader.read_u16::<LittleEndian>()?;
-                let vendor_id = reader.read_u16::<LittleEndian>()?;
-                let aes_mode = reader.read_u8()?;
-                let compression_method = reader.read_u16::<LittleEndian>()?;
-
-                if vendor_id != 0x4541 {
-                    return Err(ZipError::InvalidArchive("Invalid AES vendor"));
+            AES_EXTRA_FIELD_ID => {
+                let mut data = vec![0; len as usize];
+                reader.read_exact(&mut data)?;
+                len_left = 0;
+                if let ExtraField::Aes {
+                    vendor_version,
+                    aes_mode,
+                    compression_method,
+                } = ExtraField::parse(kind, &data)?
+                {
+                    file.aes_mode = Some((aes_mode, vendor_version));
+                    file.compression_method = CompressionMethod::from_u16(compression_method);
                 }
-                let vendor_version = match vendor_version {
-                    0x0001 => AesVendorVersion::Ae1,
-                    0x0002 => AesVendorVersion::Ae2,
-                    _ => return Err(ZipError::InvalidArchive("Invalid AES vendor version")),
-                };
-                match aes_mode {
-                    0x01 => file.aes_mode = Some((AesMode::Aes128, vendor_version)),
-                    0x02 => file.aes_mode = Some((AesMode::Aes192, vendor_version)),
-                    0x03 => file.aes_mode = Some((AesMode::Aes256, vendor_version)),
-                    _ => return Err(ZipError::InvalidArchive("Invalid AES encryption strength")),
-                };
-                file.compression_method = CompressionMethod::from_u16(compression_method);
             }
             _ => {
                 // Other fields are ignored
@@ -859,25 +1801,49 @@ fn parse_extra_field(file: &mut ZipFileData) -> ZipResult<()> {
 }
 
 /// Methods for retrieving information on zip files
-impl<'a> ZipFile<'a> {
-    fn get_reader(&mut self) -> &mut ZipFileReader<'a> {
+impl<'a, D: ?Sized + FileInput> ZipFile<'a, D> {
+    fn get_reader(&mut self) -> ZipResult<&mut ZipFileReader<D>> {
         if let ZipFileReader::NoReader = self.reader {
             let data = &self.data;
-            let crypto_reader = self.crypto_reader.take().expect("Invalid reader state");
-            self.reader = make_reader(
+            let crypto_reader = self.crypto_reader.take().ok_or(ZipError::ReaderError)?;
+            self.reader = match make_reader(
                 data.compression_method,
                 data.uncompressed_size,
                 data.crc32,
                 crypto_reader,
-            )
+                self.codec.as_deref(),
+                self.limits,
+                #[cfg(feature = "zstd")]
+                self.zstd_dictionaries.as_deref(),
+            ) {
+                Ok(reader) => reader,
+                Err(err) => {
+                    // The input was consumed, so later reads report the same error
+                    self.reader = ZipFileReader::Failed(err.duplicate());
+                    return Err(err);
+                }
+            }
         }
-        &mut self.reader
+        Ok(&mut self.reader)
+    }
+
+    /// The id of the dictionary referenced by the zstd frame of this entry
+    #[cfg(feature = "zstd")]
+    fn zstd_dictionary_id(mut self) -> ZipResult<Option<u32>> {
+        let mut header = Vec::new();
+        if let Some(reader) = self.crypto_reader.as_mut() {
+            reader
+                .take(crate::zstd_dictionary::MAX_FRAME_HEADER_SIZE)
+                .read_to_end(&mut header)?;
+        }
+        Ok(zstd::zstd_safe::get_dict_id_from_frame(&header).map(|id| id.get()))
     }
 
     pub(crate) fn get_raw_reader(&mut self) -> &mut dyn Read {
         if let ZipFileReader::NoReader = self.reader {
-            let crypto_reader = self.crypto_reader.take().expect("Invalid reader state");
-            self.reader = ZipFileReader::Raw(crypto_reader.into_inner())
+            if let Some(crypto_reader) = self.crypto_reader.take() {
+                self.reader = ZipFileReader::Raw(crypto_reader.into_inner())
+            }
         }
         &mut self.reader
     }
@@ -991,6 +1957,13 @@ impl<'a> ZipFile<'a> {
         !self.is_dir()
     }
 
+    /// Returns whether the file is a symbolic link, according to its unix mode
+    ///
+    /// The contents of a symlink entry are the target of the link.
+    pub fn is_symlink(&self) -> bool {
+        self.data.is_symlink()
+    }
+
     /// Get unix mode for the file
     pub fn unix_mode(&self) -> Option<u32> {
         self.data.unix_mode()
@@ -1001,11 +1974,29 @@ impl<'a> ZipFile<'a> {
         self.data.crc32
     }
 
+    /// Returns whether the file is encrypted
+    pub fn is_encrypted(&self) -> bool {
+        self.data.encrypted
+    }
+
+    /// Get the scheme used to encrypt the file, or `None` if it is not encrypted
+    pub fn encryption(&self) -> Option<EncryptionMethod> {
+        self.data.encryption()
+    }
+
     /// Get the extra data of the zip header for this file
     pub fn extra_data(&self) -> &[u8] {
         &self.data.extra_field
     }
 
+    /// Get an iterator over the parsed extra fields of the zip header for this file
+    ///
+    /// This also yields the fields this crate already interprets, such as the ZIP64 extended
+    /// information, whose values are reflected in [`ZipFile::size`] and friends.
+    pub fn extra_fields(&self) -> ExtraFields<'_> {
+        ExtraFields::new(&self.data.extra_field)
+    }
+
     /// Get the starting offset of the data of the compressed file
     pub fn data_start(&self) -> u64 {
         self.data.data_start.load()
@@ -1021,28 +2012,68 @@ impl<'a> ZipFile<'a> {
     }
 }
 
-impl Read for ZipFile<'_> {
+#[cfg(feature = "zstd")]
+impl<'a> ZipFile<'a> {
+    /// Create a file reading its already decompressed `content` from memory
+    pub(crate) fn buffered(data: ZipFileData, content: &'a mut io::Cursor<Vec<u8>>) -> Self {
+        let len = content.get_ref().len() as u64;
+        let content: Box<dyn Read + 'a> = Box::new(content.take(len));
+        let reader = CryptoReader::Plaintext(content);
+        ZipFile {
+            reader: ZipFileReader::Stored(Crc32Reader::new(reader, data.crc32, false)),
+            data: EntryData::Owned(data),
+            index: None,
+            crypto_reader: None,
+            codec: None,
+            limits: DecoderLimits::default(),
+            zstd_dictionaries: None,
+        }
+    }
+}
+
+impl<D: ?Sized + FileInput> Read for ZipFile<'_, D> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.get_reader().read(buf)
+        let read = match self.get_reader() {
+            Ok(reader) => reader.read(buf),
+            Err(err) => Err(err.into()),
+        };
+        read.map_err(|err| {
+            ZipError::map_io(err, |err| {
+                let err = err.in_entry(&self.data.file_name, self.index);
+                match self.data {
+                    // The offset is unknown for files read from a stream
+                    EntryData::Owned(_) => err,
+                    _ => err.at_offset(self.data.header_start),
+                }
+            })
+        })
     }
 }
 
-impl Drop for ZipFile<'_> {
+impl<D: ?Sized + FileInput> Drop for ZipFile<'_, D> {
     fn drop(&mut self) {
         // self.data is Owned, this reader is constructed by a streaming reader.
         // In this case, we want to exhaust the reader so that the next file is accessible.
-        if let Cow::Owned(_) = self.data {
+        if let EntryData::Owned(_) = self.data {
             let mut buffer = Vec::with_capacity(65536);
 
             // Get the inner `Take` reader so all decryption, decompression and CRC calculation is skipped.
-            let mut reader: std::io::Take<&mut dyn std::io::Read> = match &mut self.reader {
-                ZipFileReader::NoReader => {
-                    let innerreader = self.crypto_reader.take();
-                    innerreader.expect("Invalid reader state").into_inner()
+            let mut reader = match &mut self.reader {
+                ZipFileReader::NoReader => match self.crypto_reader.take() {
+                    Some(innerreader) => innerreader.into_inner(),
+                    None => return,
+                },
+                // The decoder may keep a reference to its input, so drain it through the lock
+                ZipFileReader::Custom { input, .. } => {
+                    drop(lock(input).read_to_end(&mut buffer));
+                    return;
                 }
                 reader => {
                     let innerreader = std::mem::replace(reader, ZipFileReader::NoReader);
-                    innerreader.into_inner()
+                    match innerreader.into_inner() {
+                        Some(innerreader) => innerreader,
+                        None => return,
+                    }
                 }
             };
 
@@ -1070,13 +2101,35 @@ impl Drop for ZipFile<'_> {
 /// * `external_attributes`: `unix_mode()`: will return None
 pub fn read_zipfile_from_stream<'a, R: io::Read>(
     reader: &'a mut R,
+) -> ZipResult<Option<ZipFile<'a>>> {
+    read_zipfile_from_stream_with(
+        reader,
+        &CodecRegistry::new(),
+        DecoderLimits::default(),
+        #[cfg(feature = "zstd")]
+        None,
+    )
+}
+
+/// Like [`read_zipfile_from_stream`], decoding custom compression methods with `codecs`,
+/// applying `limits` to the decoders and decompressing zstd entries with `zstd_dictionaries`
+pub(crate) fn read_zipfile_from_stream_with<'a, R: io::Read>(
+    reader: &'a mut R,
+    codecs: &CodecRegistry,
+    limits: DecoderLimits,
+    #[cfg(feature = "zstd")] zstd_dictionaries: Option<&ZstdDictionaries>,
 ) -> ZipResult<Option<ZipFile<'a>>> {
     let signature = reader.read_u32::<LittleEndian>()?;
 
     match signature {
         spec::LOCAL_FILE_HEADER_SIGNATURE => (),
         spec::CENTRAL_DIRECTORY_HEADER_SIGNATURE => return Ok(None),
-        _ => return Err(ZipError::InvalidArchive("Invalid local file header")),
+        found => {
+            return Err(ZipError::detailed(ErrorKind::BadSignature {
+                expected: spec::LOCAL_FILE_HEADER_SIGNATURE,
+                found,
+            }));
+        }
     }
 
     let version_made_by = reader.read_u16::<LittleEndian>()?;
@@ -1129,6 +2182,7 @@ pub fn read_zipfile_from_stream<'a, R: io::Read>(
         external_attributes: 0,
         large_file: false,
         aes_mode: None,
+        codec_version_needed: None,
     };
 
     match parse_extra_field(&mut result) {
@@ -1143,7 +2197,7 @@ pub fn read_zipfile_from_stream<'a, R: io::Read>(
         return unsupported_zip_error("The file length is not available in the local header");
     }
 
-    let limit_reader = (reader as &'a mut dyn io::Read).take(result.compressed_size);
+    let limit_reader: Box<dyn Read + 'a> = Box::new(reader.take(result.compressed_size));
 
     let result_uncompressed_size = result.uncompressed_size;
     let result_crc32 = result.crc32;
@@ -1156,20 +2210,32 @@ pub fn read_zipfile_from_stream<'a, R: io::Read>(
         limit_reader,
         None,
         None,
+        codecs,
         #[cfg(feature = "aes-crypto")]
         result.compressed_size,
+        #[cfg(feature = "aes-crypto")]
+        None,
     )?
     .unwrap();
 
     Ok(Some(ZipFile {
-        data: Cow::Owned(result),
+        data: EntryData::Owned(result),
+        index: None,
         crypto_reader: None,
+        codec: None,
+        limits,
+        #[cfg(feature = "zstd")]
+        zstd_dictionaries: None,
         reader: make_reader(
             result_compression_method,
             result_uncompressed_size,
             result_crc32,
             crypto_reader,
-        ),
+            codecs.custom(result_compression_method).as_deref(),
+            limits,
+            #[cfg(feature = "zstd")]
+            zstd_dictionaries,
+        )?,
     }))
 }
 
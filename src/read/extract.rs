@@ -0,0 +1,657 @@
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::extra_field::ExtraField;
+use crate::read::{ZipFile, tree};
+use crate::result::{ErrorKind, ZipError, ZipResult};
+
+/// What to do when an entry would be extracted to a path that already exists
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    /// Replace the existing file
+    #[default]
+    Overwrite,
+    /// Keep the existing file and do not extract the entry
+    Skip,
+    /// Abort the extraction with an [`io::ErrorKind::AlreadyExists`] error
+    Error,
+}
+
+/// Progress of an extraction, passed to the callback set with
+/// [`ExtractOptions::progress`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct ExtractProgress<'a> {
+    /// Path of the entry that was just extracted, relative to the extraction directory
+    pub path: &'a Path,
+    /// Number of entries extracted so far, including this one
+    pub entries_extracted: usize,
+    /// Number of entries in the archive, if known upfront
+    pub total_entries: Option<usize>,
+    /// Number of uncompressed bytes written so far
+    pub bytes_written: u64,
+}
+
+type FilterFn<'a> = dyn FnMut(&Path) -> Option<PathBuf> + 'a;
+type ProgressFn<'a> = dyn FnMut(&ExtractProgress<'_>) + 'a;
+
+/// Options controlling how an archive is extracted
+///
+/// Used with [`ZipArchive::extract_with_options`]. The defaults match
+/// [`ZipArchive::extract`]: existing files are overwritten, unix permissions
+/// are applied and modification times are not restored.
+///
+/// [`ZipArchive::extract_with_options`]: crate::ZipArchive::extract_with_options
+/// [`ZipArchive::extract`]: crate::ZipArchive::extract
+pub struct ExtractOptions<'a> {
+    overwrite: OverwritePolicy,
+    filter: Option<Box<FilterFn<'a>>>,
+    restore_modified_time: bool,
+    apply_unix_mode: bool,
+    progress: Option<Box<ProgressFn<'a>>>,
+    atomic: bool,
+}
+
+impl<'a> ExtractOptions<'a> {
+    /// Set what happens when a file already exists at the destination
+    ///
+    /// Directories are always merged with existing ones.
+    #[must_use]
+    pub fn overwrite(mut self, policy: OverwritePolicy) -> ExtractOptions<'a> {
+        self.overwrite = policy;
+        self
+    }
+
+    /// Set a callback to filter or rename entries
+    ///
+    /// The callback gets the sanitized path of each entry, see
+    /// [`ZipFile::enclosed_name`], and returns the path to extract it to
+    /// relative to the extraction directory, or `None` to skip the entry.
+    /// Returned paths are subject to the same checks as entry names.
+    #[must_use]
+    pub fn filter<F>(mut self, filter: F) -> ExtractOptions<'a>
+    where
+        F: FnMut(&Path) -> Option<PathBuf> + 'a,
+    {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Set whether the modification time of extracted files and directories
+    /// is restored
+    ///
+    /// The extended timestamp extra field is used when present, otherwise the
+    /// MS-DOS timestamp of the entry is interpreted as UTC. The default is `false`.
+    #[must_use]
+    pub fn restore_modified_time(mut self, restore: bool) -> ExtractOptions<'a> {
+        self.restore_modified_time = restore;
+        self
+    }
+
+    /// Set whether the unix mode of entries is applied to extracted files
+    ///
+    /// This has no effect on other platforms. The default is `true`.
+    #[must_use]
+    pub fn apply_unix_mode(mut self, apply: bool) -> ExtractOptions<'a> {
+        self.apply_unix_mode = apply;
+        self
+    }
+
+    /// Set whether the archive is extracted all-or-nothing
+    ///
+    /// Entries are extracted into a temporary sibling of the destination
+    /// directory, which is renamed into place once every entry, including its
+    /// checksum, has been verified. On failure, the temporary directory and
+    /// everything in it is removed, and the error also describes a failure to
+    /// remove it. The destination must not exist, or be an
+    /// empty directory, so the overwrite policy has no effect in this mode.
+    /// The default is `false`.
+    #[must_use]
+    pub fn atomic(mut self, atomic: bool) -> ExtractOptions<'a> {
+        self.atomic = atomic;
+        self
+    }
+
+    /// Set a callback that is invoked after each extracted entry
+    #[must_use]
+    pub fn progress<F>(mut self, progress: F) -> ExtractOptions<'a>
+    where
+        F: FnMut(&ExtractProgress<'_>) + 'a,
+    {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+}
+
+impl Default for ExtractOptions<'_> {
+    fn default() -> Self {
+        Self {
+            overwrite: OverwritePolicy::default(),
+            filter: None,
+            restore_modified_time: false,
+            apply_unix_mode: true,
+            progress: None,
+            atomic: false,
+        }
+    }
+}
+
+impl std::fmt::Debug for ExtractOptions<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExtractOptions")
+            .field("overwrite", &self.overwrite)
+            .field("filter", &self.filter.is_some())
+            .field("restore_modified_time", &self.restore_modified_time)
+            .field("apply_unix_mode", &self.apply_unix_mode)
+            .field("progress", &self.progress.is_some())
+            .field("atomic", &self.atomic)
+            .finish()
+    }
+}
+
+/// Directory whose metadata is applied once all entries have been written,
+/// since writing into it changes its modification time and a read-only mode
+/// would prevent writing into it at all.
+struct PendingDirectory {
+    path: PathBuf,
+    unix_mode: Option<u32>,
+    modified: Option<SystemTime>,
+}
+
+/// Run an extraction into `directory`, staging it in a temporary directory
+/// when the options ask for atomic extraction.
+pub(crate) fn run<'a, F>(
+    directory: &Path,
+    options: ExtractOptions<'a>,
+    total_entries: Option<usize>,
+    extract: F,
+) -> ZipResult<()>
+where
+    F: FnOnce(&mut Extractor<'_, 'a>) -> ZipResult<()>,
+{
+    if !options.atomic {
+        let mut extractor = Extractor::new(directory, options, total_entries);
+        extract(&mut extractor)?;
+        return extractor.finish();
+    }
+
+    let directory = std::path::absolute(directory)?;
+    if let Ok(metadata) = fs::symlink_metadata(&directory) {
+        if !metadata.is_dir() || fs::read_dir(&directory)?.next().is_some() {
+            return Err(ZipError::Io(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists and is not empty", directory.display()),
+            )));
+        }
+    }
+
+    let staging = create_staging_directory(&directory)?;
+    let result = (|| {
+        let mut extractor = Extractor::new(&staging, options, total_entries);
+        extract(&mut extractor)?;
+        extractor.finish()?;
+        if directory.exists() {
+            fs::remove_dir(&directory)?;
+        }
+        fs::rename(&staging, &directory)?;
+        Ok(())
+    })();
+    if let Err(err) = result {
+        if let Err(cleanup) = remove_staging_directory(&staging) {
+            let err = io::Error::from(err);
+            return Err(ZipError::Io(io::Error::new(
+                err.kind(),
+                format!(
+                    "{err}, and removing {} failed: {cleanup}",
+                    staging.display()
+                ),
+            )));
+        }
+        return Err(err);
+    }
+    Ok(())
+}
+
+/// Remove a staging directory after a failed extraction.
+///
+/// Read-only modes may have been applied to its directories already, which
+/// would prevent removing their entries, so they are made writable first.
+fn remove_staging_directory(staging: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut pending = vec![staging.to_owned()];
+        while let Some(directory) = pending.pop() {
+            let mut permissions = fs::symlink_metadata(&directory)?.permissions();
+            permissions.set_mode(permissions.mode() | 0o700);
+            fs::set_permissions(&directory, permissions)?;
+            for entry in fs::read_dir(&directory)? {
+                let entry = entry?;
+                if entry.file_type()?.is_dir() {
+                    pending.push(entry.path());
+                }
+            }
+        }
+    }
+    fs::remove_dir_all(staging)
+}
+
+/// Create an empty, uniquely named sibling of `directory`.
+fn create_staging_directory(directory: &Path) -> ZipResult<PathBuf> {
+    let (Some(parent), Some(name)) = (directory.parent(), directory.file_name()) else {
+        return Err(ZipError::Io(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Atomic extraction needs a destination with a parent directory",
+        )));
+    };
+    fs::create_dir_all(parent)?;
+
+    let mut attempt = 0u32;
+    loop {
+        let mut staging_name = std::ffi::OsString::from(".");
+        staging_name.push(name);
+        staging_name.push(format!(".ziperu-{}-{attempt}", std::process::id()));
+        let staging = parent.join(staging_name);
+        match fs::create_dir(&staging) {
+            Ok(()) => return Ok(staging),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists && attempt < 1000 => attempt += 1,
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Extraction state shared by [`ZipArchive`] and [`ZipStreamReader`].
+///
+/// [`ZipArchive`]: crate::read::ZipArchive
+/// [`ZipStreamReader`]: crate::read::stream::ZipStreamReader
+pub(crate) struct Extractor<'r, 'a> {
+    root: &'r Path,
+    options: ExtractOptions<'a>,
+    total_entries: Option<usize>,
+    entries_extracted: usize,
+    bytes_written: u64,
+    directories: Vec<PendingDirectory>,
+}
+
+impl<'r, 'a> Extractor<'r, 'a> {
+    pub(crate) fn new(
+        root: &'r Path,
+        options: ExtractOptions<'a>,
+        total_entries: Option<usize>,
+    ) -> Self {
+        Self {
+            root,
+            options,
+            total_entries,
+            entries_extracted: 0,
+            bytes_written: 0,
+            directories: Vec::new(),
+        }
+    }
+
+    pub(crate) fn apply_unix_mode(&self) -> bool {
+        self.options.apply_unix_mode
+    }
+
+    /// Extract a single entry, returning the path it was extracted to relative
+    /// to the extraction root, or `None` if it was skipped.
+    pub(crate) fn extract_entry(&mut self, file: &mut ZipFile<'_>) -> ZipResult<Option<PathBuf>> {
+        let mut relative = file
+            .enclosed_name()
+            .and_then(normalize)
+            .ok_or(ZipError::InvalidArchive("Invalid file path"))?;
+        if let Some(filter) = &mut self.options.filter {
+            match filter(&relative) {
+                Some(path) => {
+                    relative = enclose(&path).ok_or(ZipError::InvalidArchive(
+                        "Invalid path returned by the extraction filter",
+                    ))?;
+                }
+                None => return Ok(None),
+            }
+        }
+
+        let modified = match self.options.restore_modified_time {
+            true => modification_time(file),
+            false => None,
+        };
+        let unix_mode = file.unix_mode().filter(|_| self.options.apply_unix_mode);
+
+        if file.name().ends_with('/') {
+            let outpath = prepare_destination(self.root, &relative)?;
+            fs::create_dir_all(&outpath)?;
+            self.directories.push(PendingDirectory {
+                path: outpath,
+                unix_mode,
+                modified,
+            });
+            self.report(&relative, 0);
+            return Ok(Some(relative));
+        }
+
+        if !self.may_write(&self.root.join(&relative))? {
+            return Ok(None);
+        }
+
+        #[cfg(unix)]
+        if file.is_symlink() {
+            let target = read_symlink_target(&mut *file)
+                .map_err(|err| err.in_entry(file.name(), file.index))?;
+            create_symlink(self.root, &relative, &target)?;
+            self.report(&relative, 0);
+            return Ok(Some(relative));
+        }
+
+        let outpath = prepare_destination(self.root, &relative)?;
+        if let Some(p) = outpath.parent() {
+            if !p.exists() {
+                fs::create_dir_all(p)?;
+            }
+        }
+        let mut outfile = fs::File::create(&outpath)?;
+        let written = io::copy(file, &mut outfile)?;
+        if let Some(modified) = modified {
+            outfile.set_modified(modified)?;
+        }
+        if let Some(mode) = unix_mode {
+            set_unix_mode(&outpath, mode)?;
+        }
+        self.report(&relative, written);
+        Ok(Some(relative))
+    }
+
+    /// Apply the deferred directory metadata.
+    pub(crate) fn finish(self) -> ZipResult<()> {
+        // Children before their parents, so that read-only modes are applied last
+        for directory in self.directories.iter().rev() {
+            if let Some(modified) = directory.modified {
+                set_directory_modified(&directory.path, modified)?;
+            }
+            if let Some(mode) = directory.unix_mode {
+                set_unix_mode(&directory.path, mode)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Update the unix mode of a pending directory, for callers which only
+    /// learn it after the entry has been extracted.
+    pub(crate) fn set_directory_mode(&mut self, path: &Path, mode: u32) -> bool {
+        match self.directories.iter_mut().rev().find(|d| d.path == path) {
+            Some(directory) => {
+                directory.unix_mode = Some(mode);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn root(&self) -> &'r Path {
+        self.root
+    }
+
+    fn may_write(&self, outpath: &Path) -> ZipResult<bool> {
+        if fs::symlink_metadata(outpath).is_err() {
+            return Ok(true);
+        }
+        match self.options.overwrite {
+            OverwritePolicy::Overwrite => Ok(true),
+            OverwritePolicy::Skip => Ok(false),
+            OverwritePolicy::Error => Err(ZipError::Io(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", outpath.display()),
+            ))),
+        }
+    }
+
+    fn report(&mut self, path: &Path, written: u64) {
+        self.entries_extracted += 1;
+        self.bytes_written += written;
+        if let Some(progress) = &mut self.options.progress {
+            progress(&ExtractProgress {
+                path,
+                entries_extracted: self.entries_extracted,
+                total_entries: self.total_entries,
+                bytes_written: self.bytes_written,
+            });
+        }
+    }
+}
+
+/// Modification time of an entry, preferring the extended timestamp.
+fn modification_time(file: &ZipFile<'_>) -> Option<SystemTime> {
+    let extended = file.extra_fields().find_map(|field| match field {
+        Ok(ExtraField::ExtendedTimestamp(timestamp)) => timestamp.mod_time,
+        _ => None,
+    });
+    match extended {
+        Some(seconds) if seconds >= 0 => {
+            SystemTime::UNIX_EPOCH.checked_add(Duration::from_secs(seconds as u64))
+        }
+        Some(seconds) => {
+            SystemTime::UNIX_EPOCH.checked_sub(Duration::from_secs(seconds.unsigned_abs() as u64))
+        }
+        None => SystemTime::try_from(file.last_modified()).ok(),
+    }
+}
+
+#[cfg(unix)]
+pub(crate) fn set_unix_mode(path: &Path, mode: u32) -> ZipResult<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn set_unix_mode(_path: &Path, _mode: u32) -> ZipResult<()> {
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn set_directory_modified(path: &Path, modified: SystemTime) -> io::Result<()> {
+    fs::File::open(path)?.set_modified(modified)
+}
+
+/// Windows only opens directories with backup semantics, and needs write access to set times.
+#[cfg(windows)]
+fn set_directory_modified(path: &Path, modified: SystemTime) -> io::Result<()> {
+    use std::os::windows::fs::OpenOptionsExt;
+    const FILE_WRITE_ATTRIBUTES: u32 = 0x0100;
+    const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+    fs::OpenOptions::new()
+        .access_mode(FILE_WRITE_ATTRIBUTES)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+        .open(path)?
+        .set_modified(modified)
+}
+
+/// Upper bound for the length of a symlink target stored in an archive.
+#[cfg_attr(not(unix), allow(dead_code))]
+const MAX_SYMLINK_TARGET_LEN: u64 = 4096;
+
+/// Validate and normalize a path returned by an extraction filter, like
+/// [`ZipFile::enclosed_name`] does for entry names.
+fn enclose(path: &Path) -> Option<PathBuf> {
+    if path.has_root() {
+        return None;
+    }
+    normalize(path).filter(|normalized| !normalized.as_os_str().is_empty())
+}
+
+/// Normalize a path returned by `enclosed_name`, see [`tree::normalize`]
+pub(crate) fn normalize(path: &Path) -> Option<PathBuf> {
+    tree::normalize(path).map(PathBuf::from)
+}
+
+/// Join `relative` onto `root`, failing if any parent directory of the
+/// result is a symlink, so that an archive can never write through a link it
+/// created earlier.
+pub(crate) fn check_parents(root: &Path, relative: &Path) -> ZipResult<PathBuf> {
+    let mut current = root.to_path_buf();
+    if let Some(parent) = relative.parent() {
+        for component in parent.components() {
+            current.push(component);
+            match fs::symlink_metadata(&current) {
+                Ok(metadata) if metadata.file_type().is_symlink() => {
+                    return Err(ZipError::InvalidArchive(
+                        "Entry path traverses a symbolic link",
+                    ));
+                }
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+    Ok(root.join(relative))
+}
+
+/// Prepare `root.join(relative)` for being written to.
+///
+/// In addition to [`check_parents`], a symlink at the destination itself is
+/// removed rather than followed.
+pub(crate) fn prepare_destination(root: &Path, relative: &Path) -> ZipResult<PathBuf> {
+    let outpath = check_parents(root, relative)?;
+    if is_symlink(&outpath) {
+        fs::remove_file(&outpath)?;
+    }
+    Ok(outpath)
+}
+
+/// Whether `path` exists and is a symlink. The link itself is not followed.
+pub(crate) fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink())
+}
+
+/// Check that a symlink at `link` (relative to the extraction root) pointing
+/// to `target` resolves to a location inside the extraction root.
+///
+/// `..` is only accepted at the start of the target: after a normal component
+/// it could step out of another symlink, which lexical checks can't follow.
+#[cfg_attr(not(unix), allow(dead_code))]
+pub(crate) fn validate_symlink_target(link: &Path, target: &Path) -> ZipResult<()> {
+    const ESCAPES: ZipError =
+        ZipError::InvalidArchive("Symlink target escapes the extraction directory");
+
+    let mut depth = link.components().count().saturating_sub(1);
+    let mut descended = false;
+    for component in target.components() {
+        match component {
+            Component::Normal(_) => descended = true,
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if descended {
+                    return Err(ESCAPES);
+                }
+                depth = depth.checked_sub(1).ok_or(ESCAPES)?;
+            }
+            Component::RootDir | Component::Prefix(_) => {
+                return Err(ZipError::InvalidArchive(
+                    "Symlink target is an absolute path",
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Read the target of a symlink entry from its contents.
+#[cfg_attr(not(unix), allow(dead_code))]
+pub(crate) fn read_symlink_target(reader: impl Read) -> ZipResult<PathBuf> {
+    let mut target = Vec::new();
+    reader
+        .take(MAX_SYMLINK_TARGET_LEN + 1)
+        .read_to_end(&mut target)?;
+    if target.len() as u64 > MAX_SYMLINK_TARGET_LEN {
+        return Err(ZipError::detailed(ErrorKind::LimitExceeded {
+            limit: MAX_SYMLINK_TARGET_LEN,
+        }));
+    }
+    if target.is_empty() || target.contains(&0) {
+        return Err(ZipError::InvalidArchive("Invalid symlink target"));
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStringExt;
+        Ok(PathBuf::from(std::ffi::OsString::from_vec(target)))
+    }
+    #[cfg(not(unix))]
+    {
+        String::from_utf8(target)
+            .map(PathBuf::from)
+            .map_err(|_| ZipError::InvalidArchive("Invalid symlink target"))
+    }
+}
+
+/// Create a symlink at `root.join(link)` after validating its target.
+#[cfg(unix)]
+pub(crate) fn create_symlink(root: &Path, link: &Path, target: &Path) -> ZipResult<()> {
+    validate_symlink_target(link, target)?;
+    let outpath = prepare_destination(root, link)?;
+    // Callers only get here if an existing file may be overwritten
+    if fs::symlink_metadata(&outpath).is_ok_and(|m| !m.is_dir()) {
+        fs::remove_file(&outpath)?;
+    }
+    if let Some(p) = outpath.parent() {
+        fs::create_dir_all(p)?;
+    }
+    std::os::unix::fs::symlink(target, outpath)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn symlink_targets() {
+        let ok = [
+            ("link", "file"),
+            ("a/link", "../file"),
+            ("a/b/link", "../c/./d"),
+        ];
+        for (link, target) in ok {
+            validate_symlink_target(Path::new(link), Path::new(target)).unwrap();
+        }
+
+        let bad = [
+            ("link", "../file"),
+            ("a/link", "../../file"),
+            ("a/link", "b/../../../file"),
+            ("a/b/link", "s/../../.."),
+            ("link", "/etc/passwd"),
+        ];
+        for (link, target) in bad {
+            validate_symlink_target(Path::new(link), Path::new(target)).unwrap_err();
+        }
+    }
+
+    #[test]
+    fn directory_modification_time() {
+        let directory =
+            std::env::temp_dir().join(format!("ziperu-directory-mtime-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        set_directory_modified(&directory, modified).unwrap();
+        assert_eq!(
+            fs::metadata(&directory).unwrap().modified().unwrap(),
+            modified
+        );
+        fs::remove_dir(directory).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn remove_read_only_staging_directory() {
+        let staging = std::env::temp_dir().join(format!("ziperu-staging-{}", std::process::id()));
+        fs::create_dir_all(staging.join("a/b")).unwrap();
+        fs::write(staging.join("a/b/file"), b"content").unwrap();
+        for (directory, mode) in [("a/b", 0o500), ("a", 0o000), ("", 0o500)] {
+            set_unix_mode(&staging.join(directory), mode).unwrap();
+        }
+        remove_staging_directory(&staging).unwrap();
+        assert!(!staging.exists());
+    }
+}
@@ -0,0 +1,268 @@
+use std::path::{Component, Path};
+
+/// A glob pattern matching the paths of entries
+///
+/// Patterns are matched against normalized paths relative to the root of the archive, with
+/// components separated by `/`, like `assets/icons/logo.png`. The pattern has to match the whole
+/// path, and matching is case-sensitive:
+/// * `?` matches any character except `/`
+/// * `*` matches any sequence of characters except `/`
+/// * `**` as a whole component matches any number of components, so `assets/**/*.png` matches
+///   `assets/logo.png` and `assets/icons/logo.png`, and a trailing `/**` matches everything
+///   below a directory
+/// * `[abc]`, `[a-z]` and their negations `[!abc]` and `[^abc]` match one character, except `/`
+/// * `\` matches the next character literally
+///
+/// A `[` without a closing `]` matches itself.
+///
+/// ```
+/// use ziperu::read::Glob;
+///
+/// let glob = Glob::new("assets/**/*.png");
+/// assert!(glob.is_match("assets/icons/logo.png"));
+/// assert!(!glob.is_match("assets/icons/logo.svg"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Glob {
+    pattern: String,
+    tokens: Vec<Token>,
+}
+
+#[derive(Clone, Debug)]
+enum Token {
+    Literal(char),
+    /// `?`
+    Any,
+    /// `*`
+    Star,
+    /// `**/`, any number of components followed by `/`
+    Directories,
+    /// A trailing `**`, anything
+    Rest,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Glob {
+    /// Compile a pattern
+    pub fn new(pattern: &str) -> Glob {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut tokens = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            match chars[i] {
+                '*' if chars.get(i + 1) == Some(&'*')
+                    && (i == 0 || chars[i - 1] == '/')
+                    && matches!(chars.get(i + 2), None | Some('/')) =>
+                {
+                    match chars.get(i + 2) {
+                        Some(_) => tokens.push(Token::Directories),
+                        None => tokens.push(Token::Rest),
+                    }
+                    i += 3;
+                }
+                '*' => {
+                    tokens.push(Token::Star);
+                    while chars.get(i) == Some(&'*') {
+                        i += 1;
+                    }
+                }
+                '?' => {
+                    tokens.push(Token::Any);
+                    i += 1;
+                }
+                '[' => match parse_class(&chars[i + 1..]) {
+                    Some((class, len)) => {
+                        tokens.push(class);
+                        i += len + 1;
+                    }
+                    None => {
+                        tokens.push(Token::Literal('['));
+                        i += 1;
+                    }
+                },
+                '\\' if i + 1 < chars.len() => {
+                    tokens.push(Token::Literal(chars[i + 1]));
+                    i += 2;
+                }
+                c => {
+                    tokens.push(Token::Literal(c));
+                    i += 1;
+                }
+            }
+        }
+        Glob {
+            pattern: pattern.to_owned(),
+            tokens,
+        }
+    }
+
+    /// Get the pattern this glob was compiled from
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Returns whether the pattern matches a path with components separated by `/`
+    pub fn is_match(&self, path: &str) -> bool {
+        let text: Vec<char> = path.chars().collect();
+        let mut memo = vec![None; (self.tokens.len() + 1) * (text.len() + 1)];
+        self.match_at(0, 0, &text, &mut memo)
+    }
+
+    /// Returns whether the tokens from `token` on match the text from `pos` on
+    fn match_at(&self, token: usize, pos: usize, text: &[char], memo: &mut [Option<bool>]) -> bool {
+        let key = token * (text.len() + 1) + pos;
+        if let Some(matched) = memo[key] {
+            return matched;
+        }
+        let next = text.get(pos).copied().filter(|&c| c != '/');
+        let matched = match &self.tokens.get(token) {
+            None => pos == text.len(),
+            Some(Token::Literal(c)) => {
+                text.get(pos) == Some(c) && self.match_at(token + 1, pos + 1, text, memo)
+            }
+            Some(Token::Any) => next.is_some() && self.match_at(token + 1, pos + 1, text, memo),
+            Some(Token::Class { negated, ranges }) => {
+                next.is_some_and(|c| {
+                    ranges
+                        .iter()
+                        .any(|&(start, end)| (start..=end).contains(&c))
+                        != *negated
+                }) && self.match_at(token + 1, pos + 1, text, memo)
+            }
+            Some(Token::Star) => (pos..=text.len())
+                .take_while(|&end| end == pos || text[end - 1] != '/')
+                .any(|end| self.match_at(token + 1, end, text, memo)),
+            Some(Token::Directories) => (pos..=text.len())
+                .filter(|&end| end == pos || text[end - 1] == '/')
+                .any(|end| self.match_at(token + 1, end, text, memo)),
+            Some(Token::Rest) => true,
+        };
+        memo[key] = Some(matched);
+        matched
+    }
+}
+
+/// Parse a character class following a `[`, returning it with its length including the `]`
+fn parse_class(chars: &[char]) -> Option<(Token, usize)> {
+    let negated = matches!(chars.first(), Some('!' | '^'));
+    let mut i = usize::from(negated);
+    let mut ranges = Vec::new();
+    loop {
+        let start = *chars.get(i)?;
+        // A `]` right after the opening bracket is part of the class
+        if start == ']' && !ranges.is_empty() {
+            return Some((Token::Class { negated, ranges }, i + 1));
+        }
+        match (chars.get(i + 1), chars.get(i + 2)) {
+            (Some('-'), Some(&end)) if end != ']' => {
+                ranges.push((start, end));
+                i += 3;
+            }
+            _ => {
+                ranges.push((start, start));
+                i += 1;
+            }
+        }
+    }
+}
+
+/// Selects entries by their path, see [`crate::ZipArchive::extract_matching`]
+///
+/// The path is the sanitized path of an entry relative to the extraction directory, see
+/// [`crate::read::ZipFile::enclosed_name`]. This is implemented for [`Glob`] and for closures
+/// taking a `&Path` and returning a `bool`.
+pub trait EntryMatcher {
+    /// Returns whether the entry with this path is selected
+    fn matches(&mut self, path: &Path) -> bool;
+}
+
+impl<F: FnMut(&Path) -> bool> EntryMatcher for F {
+    fn matches(&mut self, path: &Path) -> bool {
+        self(path)
+    }
+}
+
+impl EntryMatcher for Glob {
+    fn matches(&mut self, path: &Path) -> bool {
+        (&*self).matches(path)
+    }
+}
+
+impl EntryMatcher for &Glob {
+    fn matches(&mut self, path: &Path) -> bool {
+        let components: Option<Vec<&str>> = path
+            .components()
+            .map(|component| match component {
+                Component::Normal(name) => name.to_str(),
+                _ => None,
+            })
+            .collect();
+        components.is_some_and(|components| self.is_match(&components.join("/")))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn wildcards() {
+        let glob = Glob::new("src/*.rs");
+        assert!(glob.is_match("src/lib.rs"));
+        assert!(glob.is_match("src/.rs"));
+        assert!(!glob.is_match("src/read/glob.rs"));
+        assert!(!glob.is_match("lib.rs"));
+
+        let glob = Glob::new("file?.txt");
+        assert!(glob.is_match("file1.txt"));
+        assert!(!glob.is_match("file.txt"));
+        assert!(!glob.is_match("file/.txt"));
+    }
+
+    #[test]
+    fn globstar() {
+        let glob = Glob::new("assets/**/*.png");
+        assert!(glob.is_match("assets/logo.png"));
+        assert!(glob.is_match("assets/icons/small/logo.png"));
+        assert!(!glob.is_match("assets/logo.svg"));
+        assert!(!glob.is_match("other/assets/logo.png"));
+
+        let glob = Glob::new("docs/**");
+        assert!(glob.is_match("docs/a"));
+        assert!(glob.is_match("docs/a/b"));
+        assert!(!glob.is_match("docs"));
+
+        assert!(Glob::new("**").is_match("a/b/c"));
+        assert!(Glob::new("**/c").is_match("c"));
+        assert!(Glob::new("**/c").is_match("a/b/c"));
+        // Not a whole component
+        assert!(!Glob::new("a**/c").is_match("a/b/c"));
+        assert!(Glob::new("a**/c").is_match("abc/c"));
+    }
+
+    #[test]
+    fn classes() {
+        let glob = Glob::new("[a-c]x[!0-9].[]]");
+        assert!(glob.is_match("bxy.]"));
+        assert!(!glob.is_match("dxy.]"));
+        assert!(!glob.is_match("bx1.]"));
+        assert!(Glob::new("[^/]").is_match("a"));
+        assert!(!Glob::new("[^a]").is_match("/"));
+        assert!(Glob::new("a[-]b").is_match("a-b"));
+        // Unclosed
+        assert!(Glob::new("a[b").is_match("a[b"));
+        assert!(Glob::new(r"\*\?").is_match("*?"));
+        assert!(!Glob::new(r"\*").is_match("a"));
+    }
+
+    #[test]
+    fn paths() {
+        let mut glob = Glob::new("a/*.txt");
+        assert!(glob.matches(Path::new("a/b.txt")));
+        assert!(!glob.matches(Path::new("a/b/c.txt")));
+        assert!(!glob.matches(Path::new("../a/b.txt")));
+    }
+}
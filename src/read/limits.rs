@@ -0,0 +1,36 @@
+/// Limits on the memory decoders may allocate, for reading untrusted archives
+///
+/// Used with [`ZipArchive::set_decoder_limits`] and [`ZipStreamReader::with_decoder_limits`].
+/// By default the decoders use their own limits.
+///
+/// [`ZipArchive::set_decoder_limits`]: crate::ZipArchive::set_decoder_limits
+/// [`ZipStreamReader::with_decoder_limits`]: crate::unstable::stream::ZipStreamReader::with_decoder_limits
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DecoderLimits {
+    pub(crate) zstd_window_log_max: Option<u32>,
+    pub(crate) lzma_dict_size_max: Option<u32>,
+}
+
+impl DecoderLimits {
+    /// Set the base-2 logarithm of the largest zstd window to accept
+    ///
+    /// The default of zstd is 27, i.e. windows of up to 128 MiB. Entries with larger windows
+    /// fail to decompress.
+    #[must_use]
+    pub fn zstd_window_log_max(mut self, window_log: u32) -> DecoderLimits {
+        self.zstd_window_log_max = Some(window_log);
+        self
+    }
+
+    /// Set the largest LZMA dictionary to accept, in bytes
+    ///
+    /// This applies to entries compressed with LZMA. Entries with larger dictionaries fail to
+    /// decompress with [`ErrorKind::LimitExceeded`].
+    ///
+    /// [`ErrorKind::LimitExceeded`]: crate::result::ErrorKind::LimitExceeded
+    #[must_use]
+    pub fn lzma_dict_size_max(mut self, dict_size: u32) -> DecoderLimits {
+        self.lzma_dict_size_max = Some(dict_size);
+        self
+    }
+}
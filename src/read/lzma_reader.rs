@@ -1,3 +1,4 @@
+use crate::result::{self, ZipError};
 use std::io::{Error, ErrorKind, Read, Result};
 
 /// Contains the state of the actual lzma reader to allow lazy reading
@@ -5,6 +6,7 @@ enum ReaderState<R> {
     Uninitialize {
         reader: Option<R>,
         uncompressed_size: u64,
+        dict_size_max: Option<u32>,
     },
     Initialized {
         reader: Box<lzma_rust2::LzmaReader<R>>,
@@ -16,11 +18,12 @@ pub struct LzmaReader<R> {
 }
 
 impl<R: Read> LzmaReader<R> {
-    pub fn new(reader: R, uncompressed_size: u64) -> Self {
+    pub fn new(reader: R, uncompressed_size: u64, dict_size_max: Option<u32>) -> Self {
         LzmaReader {
             state: ReaderState::Uninitialize {
                 reader: Some(reader),
                 uncompressed_size,
+                dict_size_max,
             },
         }
     }
@@ -39,6 +42,7 @@ impl<R: Read> Read for LzmaReader<R> {
             ReaderState::Uninitialize {
                 reader,
                 uncompressed_size,
+                dict_size_max,
             } => {
                 let mut reader = reader
                     .take()
@@ -58,6 +62,14 @@ impl<R: Read> Read for LzmaReader<R> {
                 reader.read_exact(&mut props_data)?;
                 let props = props_data[0];
                 let dict_size = u32::from_le_bytes(props_data[1..5].try_into().unwrap());
+                if let Some(limit) = *dict_size_max {
+                    if dict_size > limit {
+                        return Err(ZipError::detailed(result::ErrorKind::LimitExceeded {
+                            limit: limit as u64,
+                        })
+                        .into());
+                    }
+                }
 
                 // We don't need to handle the end-of-stream marker here, since the LZMA reader
                 // stops at the end-of-stream marker OR when it has decoded uncompressed_size bytes, whichever comes first.
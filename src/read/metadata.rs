@@ -0,0 +1,172 @@
+use crate::compression::CompressionMethod;
+use crate::extra_field::ExtraFields;
+use crate::types::{DateTime, EncryptionMethod, ZipFileData};
+use std::path::{Path, PathBuf};
+
+/// The metadata of a file in a [`crate::ZipArchive`], taken from the central directory
+///
+/// Returned by [`crate::ZipArchive::entries`] and [`crate::ZipArchive::metadata`]. Unlike
+/// [`crate::read::ZipFile`], getting it doesn't read from the archive.
+#[derive(Clone, Copy, Debug)]
+pub struct ZipFileMetadata<'a> {
+    data: &'a ZipFileData,
+    index: usize,
+}
+
+impl<'a> ZipFileMetadata<'a> {
+    pub(crate) fn new(data: &'a ZipFileData, index: usize) -> Self {
+        ZipFileMetadata { data, index }
+    }
+
+    /// Get the index of the file in the archive
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Get the version of the file
+    pub fn version_made_by(&self) -> (u8, u8) {
+        (
+            self.data.version_made_by / 10,
+            self.data.version_made_by % 10,
+        )
+    }
+
+    /// Get the name of the file
+    ///
+    /// # Warnings
+    ///
+    /// It is dangerous to use this name directly when extracting an archive.
+    /// It may contain an absolute path (`/etc/shadow`), or break out of the
+    /// current directory (`../runtime`). Carelessly writing to these paths
+    /// allows an attacker to craft a ZIP archive that will overwrite critical
+    /// files.
+    ///
+    /// You can use the [`ZipFileMetadata::enclosed_name`] method to validate the name
+    /// as a safe path.
+    pub fn name(&self) -> &'a str {
+        &self.data.file_name
+    }
+
+    /// Get the name of the file, in the raw (internal) byte representation.
+    ///
+    /// The encoding of this data is currently undefined.
+    pub fn name_raw(&self) -> &'a [u8] {
+        &self.data.file_name_raw
+    }
+
+    /// Rewrite the path, ignoring any path components with special meaning.
+    ///
+    /// - Absolute paths are made relative
+    /// - [`ParentDir`]s are ignored
+    /// - Truncates the filename at a NULL byte
+    ///
+    /// This is appropriate if you need to be able to extract *something* from
+    /// any archive, but will easily misrepresent trivial paths like
+    /// `foo/../bar` as `foo/bar` (instead of `bar`). Because of this,
+    /// [`ZipFileMetadata::enclosed_name`] is the better option in most scenarios.
+    ///
+    /// [`ParentDir`]: `std::path::Component::ParentDir`
+    pub fn mangled_name(&self) -> PathBuf {
+        self.data.file_name_sanitized()
+    }
+
+    /// Ensure the file path is safe to use as a [`Path`].
+    ///
+    /// - It can't contain NULL bytes
+    /// - It can't resolve to a path outside the current directory
+    ///   > `foo/../bar` is fine, `foo/../../bar` is not.
+    /// - It can't be an absolute path
+    ///
+    /// This will read well-formed ZIP files correctly, and is resistant
+    /// to path-based exploits. It is recommended over
+    /// [`ZipFileMetadata::mangled_name`].
+    pub fn enclosed_name(&self) -> Option<&'a Path> {
+        self.data.enclosed_name()
+    }
+
+    /// Get the comment of the file
+    pub fn comment(&self) -> &'a str {
+        &self.data.file_comment
+    }
+
+    /// Get the compression method used to store the file
+    pub fn compression(&self) -> CompressionMethod {
+        self.data.compression_method
+    }
+
+    /// Get the size of the file, in bytes, in the archive
+    pub fn compressed_size(&self) -> u64 {
+        self.data.compressed_size
+    }
+
+    /// Get the size of the file, in bytes, when uncompressed
+    pub fn size(&self) -> u64 {
+        self.data.uncompressed_size
+    }
+
+    /// Get the time the file was last modified
+    pub fn last_modified(&self) -> DateTime {
+        self.data.last_modified_time
+    }
+
+    /// Returns whether the file is actually a directory
+    pub fn is_dir(&self) -> bool {
+        self.name()
+            .chars()
+            .next_back()
+            .is_some_and(|c| c == '/' || c == '\\')
+    }
+
+    /// Returns whether the file is a regular file
+    pub fn is_file(&self) -> bool {
+        !self.is_dir()
+    }
+
+    /// Returns whether the file is a symbolic link, according to its unix mode
+    pub fn is_symlink(&self) -> bool {
+        self.data.is_symlink()
+    }
+
+    /// Get unix mode for the file
+    pub fn unix_mode(&self) -> Option<u32> {
+        self.data.unix_mode()
+    }
+
+    /// Get the CRC32 hash of the original file
+    pub fn crc32(&self) -> u32 {
+        self.data.crc32
+    }
+
+    /// Returns whether the file is encrypted
+    pub fn is_encrypted(&self) -> bool {
+        self.data.encrypted
+    }
+
+    /// Get the scheme used to encrypt the file, or `None` if it is not encrypted
+    pub fn encryption(&self) -> Option<EncryptionMethod> {
+        self.data.encryption()
+    }
+
+    /// Get the extra data of the central header for this file
+    pub fn extra_data(&self) -> &'a [u8] {
+        &self.data.extra_field
+    }
+
+    /// Get an iterator over the parsed extra fields of the central header for this file
+    pub fn extra_fields(&self) -> ExtraFields<'a> {
+        ExtraFields::new(&self.data.extra_field)
+    }
+
+    /// Get the starting offset of the zip header for this file
+    ///
+    /// The offset of the data is only known once the local header is read, see
+    /// [`crate::read::ZipFile::data_start`].
+    pub fn header_start(&self) -> u64 {
+        self.data.header_start
+    }
+
+    /// Get the starting offset of the zip header in the central directory for this file
+    pub fn central_header_start(&self) -> u64 {
+        self.data.central_header_start
+    }
+}
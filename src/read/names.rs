@@ -0,0 +1,215 @@
+use indexmap::IndexMap;
+use std::borrow::Cow;
+use std::collections::HashMap;
+#[cfg(feature = "unicode-normalization")]
+use unicode_normalization::UnicodeNormalization;
+
+/// Which file [`crate::ZipArchive::by_name`] opens if several files have the name
+///
+/// Set with [`crate::ZipArchive::set_duplicate_policy`]. A central directory listing a name more
+/// than once can hide a file from tools that only look at one of the records, so archives from
+/// untrusted sources may be opened with [`crate::ZipArchive::new_strict`] instead, which rejects
+/// them.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Use the first file with the name in the central directory
+    First,
+    /// Use the last file with the name in the central directory, which is the one left on disk
+    /// when all files are extracted in order
+    #[default]
+    Last,
+}
+
+/// How [`crate::ZipArchive::by_name`] compares names
+///
+/// Set with [`crate::ZipArchive::set_name_matching`]. A file whose name equals the requested one
+/// is always found. Otherwise, the names are compared after converting them to lowercase and/or
+/// to Unicode normalization form C, so that `README.TXT` finds `readme.txt`, or a name written in
+/// NFD by macOS finds the same name in NFC. If several different names of the archive match, the
+/// lookup fails with [`crate::result::ErrorKind::AmbiguousName`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct NameMatching {
+    case_insensitive: bool,
+    #[cfg(feature = "unicode-normalization")]
+    unicode_normalized: bool,
+}
+
+impl NameMatching {
+    /// Match names exactly, which is the default
+    pub fn exact() -> NameMatching {
+        NameMatching::default()
+    }
+
+    /// Set whether names are compared by their lowercase forms
+    #[must_use]
+    pub fn case_insensitive(mut self, enabled: bool) -> NameMatching {
+        self.case_insensitive = enabled;
+        self
+    }
+
+    /// Set whether names are compared by their Unicode NFC forms
+    #[cfg(feature = "unicode-normalization")]
+    #[must_use]
+    pub fn unicode_normalized(mut self, enabled: bool) -> NameMatching {
+        self.unicode_normalized = enabled;
+        self
+    }
+
+    pub(crate) fn is_exact(&self) -> bool {
+        *self == NameMatching::exact()
+    }
+
+    /// The form of a name that is compared
+    fn key(&self, name: &str) -> String {
+        let name = Cow::Borrowed(name);
+        #[cfg(feature = "unicode-normalization")]
+        let name = match self.unicode_normalized {
+            true => Cow::Owned(name.nfc().collect()),
+            false => name,
+        };
+        match self.case_insensitive {
+            true => name.to_lowercase(),
+            false => name.into_owned(),
+        }
+    }
+}
+
+/// The indices of the files of an archive by name
+#[derive(Debug)]
+pub(crate) struct NameIndex {
+    /// The first file with each name
+    first: HashMap<String, usize>,
+    /// All files with names that appear more than once, in the order of the central directory
+    duplicates: IndexMap<String, Vec<usize>>,
+}
+
+impl NameIndex {
+    pub(crate) fn new<'a>(names: impl ExactSizeIterator<Item = &'a str>) -> NameIndex {
+        let mut first = HashMap::with_capacity(names.len());
+        let mut duplicates = IndexMap::new();
+        for (index, name) in names.enumerate() {
+            match first.get(name) {
+                Some(&first) => duplicates
+                    .entry(name.to_owned())
+                    .or_insert_with(|| vec![first])
+                    .push(index),
+                None => {
+                    first.insert(name.to_owned(), index);
+                }
+            }
+        }
+        NameIndex { first, duplicates }
+    }
+
+    /// The index of the file with a name, chosen by `policy` among duplicates
+    pub(crate) fn get(&self, name: &str, policy: DuplicatePolicy) -> Option<usize> {
+        match policy {
+            DuplicatePolicy::First => self.first.get(name).copied(),
+            DuplicatePolicy::Last => match self.duplicates.get(name) {
+                Some(indices) => indices.last().copied(),
+                None => self.first.get(name).copied(),
+            },
+        }
+    }
+
+    /// The indices of all files with a name, in the order of the central directory
+    pub(crate) fn all(&self, name: &str) -> &[usize] {
+        match self.duplicates.get(name) {
+            Some(indices) => indices,
+            None => self
+                .first
+                .get(name)
+                .map(std::slice::from_ref)
+                .unwrap_or(&[]),
+        }
+    }
+
+    /// The names used by more than one file, in the order their second file appears
+    pub(crate) fn duplicates(&self) -> impl ExactSizeIterator<Item = &str> {
+        self.duplicates.keys().map(String::as_str)
+    }
+}
+
+/// The files of an archive by the form of their names compared by a [`NameMatching`]
+#[derive(Debug)]
+pub(crate) struct MatchingIndex {
+    matching: NameMatching,
+    /// The first file of each distinct name, by the compared form of the name
+    keys: IndexMap<String, Vec<usize>>,
+}
+
+impl MatchingIndex {
+    pub(crate) fn new<'a>(
+        matching: NameMatching,
+        names: impl Iterator<Item = &'a str>,
+        index: &NameIndex,
+    ) -> MatchingIndex {
+        let mut keys = IndexMap::<_, Vec<_>>::new();
+        for (i, name) in names.enumerate() {
+            if index.first.get(name) == Some(&i) {
+                keys.entry(matching.key(name)).or_default().push(i);
+            }
+        }
+        MatchingIndex { matching, keys }
+    }
+
+    /// The first files of the distinct names matching `name`
+    pub(crate) fn candidates(&self, name: &str) -> &[usize] {
+        self.keys
+            .get(&self.matching.key(name))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// The first files of the groups of distinct names matching each other
+    pub(crate) fn ambiguous(&self) -> impl Iterator<Item = &[usize]> {
+        self.keys
+            .values()
+            .filter(|files| files.len() > 1)
+            .map(Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn duplicates() {
+        let index = NameIndex::new(["a", "b", "a", "c", "b", "a"].into_iter());
+        assert_eq!(index.get("a", DuplicatePolicy::First), Some(0));
+        assert_eq!(index.get("a", DuplicatePolicy::Last), Some(5));
+        assert_eq!(index.get("c", DuplicatePolicy::Last), Some(3));
+        assert_eq!(index.get("d", DuplicatePolicy::First), None);
+        assert_eq!(index.all("a"), [0, 2, 5]);
+        assert_eq!(index.all("c"), [3]);
+        assert!(index.all("d").is_empty());
+        assert_eq!(index.duplicates().collect::<Vec<_>>(), ["a", "b"]);
+    }
+
+    #[test]
+    fn matching() {
+        let names = ["Readme", "a.txt", "README", "A.TXT", "readme", "b"];
+        let index = NameIndex::new(names.into_iter());
+        let matching = NameMatching::exact().case_insensitive(true);
+        let index = MatchingIndex::new(matching, names.into_iter(), &index);
+        assert_eq!(index.candidates("readME"), [0, 2, 4]);
+        assert_eq!(index.candidates("B"), [5]);
+        assert!(index.candidates("c").is_empty());
+        assert_eq!(
+            index.ambiguous().collect::<Vec<_>>(),
+            [&[0, 2, 4][..], &[1, 3]]
+        );
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    #[test]
+    fn unicode_normalized() {
+        let matching = NameMatching::exact().unicode_normalized(true);
+        assert_eq!(matching.key("cafe\u{301}"), "caf\u{e9}");
+        assert_eq!(
+            matching.case_insensitive(true).key("CAFE\u{301}"),
+            "caf\u{e9}"
+        );
+        assert_eq!(NameMatching::exact().key("cafe\u{301}"), "cafe\u{301}");
+    }
+}
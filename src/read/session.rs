@@ -0,0 +1,138 @@
+use std::io::{Read, Seek};
+use std::path::Path;
+
+use super::{ExtractOptions, ZipArchive, ZipFile, extract, find_content};
+use crate::aes::{KeyCache, derive_key};
+use crate::result::{ErrorKind, InvalidPassword, ZipError, ZipResult};
+
+/// A decryption session for a [`ZipArchive`], created by [`ZipArchive::decryption_session`].
+///
+/// Deriving the key of an AES encrypted entry is deliberately slow. A session caches derived keys
+/// per password and salt, so entries sharing a salt, or opened several times, only pay for it once.
+/// [`DecryptionSession::derive_keys`] derives the keys of all entries in parallel upfront.
+///
+/// The cached keys are dropped along with the session.
+pub struct DecryptionSession<'a, R> {
+    archive: &'a mut ZipArchive<R>,
+    key_cache: KeyCache,
+}
+
+impl<R: Read + Seek> ZipArchive<R> {
+    /// Start a [`DecryptionSession`] to decrypt many entries of this archive.
+    pub fn decryption_session(&mut self) -> DecryptionSession<'_, R> {
+        DecryptionSession {
+            archive: self,
+            key_cache: KeyCache::default(),
+        }
+    }
+}
+
+impl<R: Read + Seek> DecryptionSession<'_, R> {
+    /// Get a contained file by index, decrypt with given password
+    ///
+    /// See [`ZipArchive::by_index_decrypt`].
+    pub fn by_index_decrypt(
+        &mut self,
+        file_number: usize,
+        password: &[u8],
+    ) -> ZipResult<Result<ZipFile<'_>, InvalidPassword>> {
+        self.archive
+            .open_entry(file_number, Some(password), &mut self.key_cache)
+    }
+
+    /// Search for a file entry by name, decrypt with given password
+    ///
+    /// See [`ZipArchive::by_name_decrypt`].
+    pub fn by_name_decrypt(
+        &mut self,
+        name: &str,
+        password: &[u8],
+    ) -> ZipResult<Result<ZipFile<'_>, InvalidPassword>> {
+        let index = self.archive.find_name(name)?;
+        self.by_index_decrypt(index, password)
+    }
+
+    /// Derive the keys of all AES encrypted entries for `password`, using all available cores.
+    pub fn derive_keys(&mut self, password: &[u8]) -> ZipResult<()> {
+        let mut pending = Vec::new();
+        for data in self.archive.shared.files.iter() {
+            let Some((aes_mode, _)) = data.aes_mode.filter(|_| data.encrypted) else {
+                continue;
+            };
+            let mut salt = vec![0; aes_mode.salt_length()];
+            find_content(data, &mut self.archive.reader)?.read_exact(&mut salt)?;
+            if !self.key_cache.contains(password, &salt)
+                && !pending.contains(&(salt.clone(), aes_mode))
+            {
+                pending.push((salt, aes_mode));
+            }
+        }
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        let threads = std::thread::available_parallelism()
+            .map_or(1, |n| n.get())
+            .min(pending.len());
+        let derived: Vec<_> = std::thread::scope(|scope| {
+            let handles: Vec<_> = pending
+                .chunks(pending.len().div_ceil(threads))
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|(salt, aes_mode)| derive_key(password, salt, *aes_mode))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| {
+                    handle
+                        .join()
+                        .unwrap_or_else(|e| std::panic::resume_unwind(e))
+                })
+                .collect()
+        });
+
+        for ((salt, _), key) in pending.into_iter().zip(derived) {
+            // A password with an invalid length is reported when opening the entry
+            if let Some(key) = key {
+                self.key_cache.insert(password, salt, key);
+            }
+        }
+        Ok(())
+    }
+
+    /// Extract the archive into a directory, decrypting entries with `password`.
+    ///
+    /// The keys of all entries are derived in parallel first, see
+    /// [`DecryptionSession::derive_keys`]. Otherwise, this behaves like
+    /// [`ZipArchive::extract_with_options`]. If the password is wrong for any entry, extraction
+    /// fails with a [`ZipError::Detailed`] error of kind [`ErrorKind::WrongPassword`].
+    pub fn extract_with_options<P: AsRef<Path>>(
+        &mut self,
+        directory: P,
+        options: ExtractOptions<'_>,
+        password: &[u8],
+    ) -> ZipResult<()> {
+        self.derive_keys(password)?;
+
+        let len = self.archive.len();
+        let shared = self.archive.shared.clone();
+        extract::run(directory.as_ref(), options, Some(len), |extractor| {
+            for i in 0..len {
+                let mut file = self
+                    .archive
+                    .open_entry(i, Some(password), &mut self.key_cache)?
+                    .map_err(|InvalidPassword| {
+                        let name = &shared.files[i].file_name;
+                        ZipError::detailed(ErrorKind::WrongPassword).in_entry(name, Some(i))
+                    })?;
+                extractor.extract_entry(&mut file)?;
+            }
+            Ok(())
+        })
+    }
+}
@@ -1,52 +1,108 @@
+use std::collections::HashMap;
+#[cfg(unix)]
 use std::fs;
-use std::io::{self, Read};
-use std::path::Path;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 
 use super::{
-    ZipError, ZipFile, ZipFileData, ZipResult, central_header_to_zip_file_inner,
-    read_zipfile_from_stream, spec,
+    DecoderLimits, EntryMatcher, ExtractOptions, ZipFile, ZipFileData, ZipResult,
+    central_header_to_zip_file_inner, extract, read_zipfile_from_stream_with, spec,
 };
+use crate::codec::CodecRegistry;
+#[cfg(feature = "zstd")]
+use crate::zstd_dictionary::{ZstdDictionaries, ZstdDictionary};
 
 use byteorder::{LittleEndian, ReadBytesExt};
 
 /// Stream decoder for zip.
 #[derive(Debug)]
-pub struct ZipStreamReader<R>(R);
+pub struct ZipStreamReader<R> {
+    reader: R,
+    codecs: CodecRegistry,
+    limits: DecoderLimits,
+}
 
 impl<R> ZipStreamReader<R> {
     /// Create a new ZipStreamReader
     pub fn new(reader: R) -> Self {
-        Self(reader)
+        Self {
+            reader,
+            codecs: CodecRegistry::new(),
+            limits: DecoderLimits::default(),
+        }
+    }
+
+    /// Set the codecs used to decompress entries with methods that are not built in.
+    ///
+    /// See [`crate::codec`].
+    #[must_use]
+    pub fn with_codecs(mut self, codecs: CodecRegistry) -> Self {
+        self.codecs = codecs;
+        self
+    }
+
+    /// Set limits on the memory the decoders may allocate, see [`DecoderLimits`].
+    #[must_use]
+    pub fn with_decoder_limits(mut self, limits: DecoderLimits) -> Self {
+        self.limits = limits;
+        self
     }
 }
 
 impl<R: Read> ZipStreamReader<R> {
-    fn parse_central_directory(&mut self) -> ZipResult<Option<ZipStreamFileMetadata>> {
+    fn parse_central_directory(&mut self) -> ZipResult<ZipStreamFileMetadata> {
         // Give archive_offset and central_header_start dummy value 0, since
         // they are not used in the output.
         let archive_offset = 0;
         let central_header_start = 0;
 
-        // Parse central header
-        let signature = self.0.read_u32::<LittleEndian>()?;
-        if signature != spec::CENTRAL_DIRECTORY_HEADER_SIGNATURE {
-            Ok(None)
-        } else {
-            central_header_to_zip_file_inner(&mut self.0, archive_offset, central_header_start)
-                .map(ZipStreamFileMetadata)
-                .map(Some)
-        }
+        central_header_to_zip_file_inner(&mut self.reader, archive_offset, central_header_start)
+            .map(ZipStreamFileMetadata)
     }
 
     /// Iteraate over the stream and extract all file and their
     /// metadata.
     pub fn visit<V: ZipStreamVisitor>(mut self, visitor: &mut V) -> ZipResult<()> {
-        while let Some(mut file) = read_zipfile_from_stream(&mut self.0)? {
+        #[cfg(feature = "zstd")]
+        let mut zstd_dictionaries = ZstdDictionaries::new();
+        while let Some(mut file) = read_zipfile_from_stream_with(
+            &mut self.reader,
+            &self.codecs,
+            self.limits,
+            #[cfg(feature = "zstd")]
+            Some(&zstd_dictionaries),
+        )? {
+            // Dictionaries are read before the visitor sees them, so it gets a copy
+            #[cfg(feature = "zstd")]
+            if let Some(id) = ZstdDictionary::id_from_entry(
+                file.name(),
+                file.compression(),
+                file.compressed_size(),
+            ) {
+                let mut content = Vec::new();
+                file.read_to_end(&mut content)?;
+                let data = ZipFileData::clone(&file.data);
+                drop(file);
+                if let Some(dictionary) = ZstdDictionary::from_entry_content(id, &content) {
+                    zstd_dictionaries.insert(id, dictionary);
+                }
+                let mut content = std::io::Cursor::new(content);
+                visitor.visit_file(&mut ZipFile::buffered(data, &mut content))?;
+                continue;
+            }
             visitor.visit_file(&mut file)?;
         }
 
-        while let Some(metadata) = self.parse_central_directory()? {
+        // The signature of the first central header has already been consumed
+        // by `read_zipfile_from_stream`.
+        loop {
+            let metadata = self.parse_central_directory()?;
             visitor.visit_additional_metadata(&metadata)?;
+
+            let signature = self.reader.read_u32::<LittleEndian>()?;
+            if signature != spec::CENTRAL_DIRECTORY_HEADER_SIGNATURE {
+                break;
+            }
         }
 
         Ok(())
@@ -55,47 +111,70 @@ impl<R: Read> ZipStreamReader<R> {
     /// Extract a Zip archive into a directory, overwriting files if they
     /// already exist. Paths are sanitized with [`ZipFile::enclosed_name`].
     ///
+    /// Symlinks are handled like in [`ZipArchive::extract`]. Since the unix
+    /// mode of an entry is only known once the central directory is reached,
+    /// symlinks are first written as regular files and converted afterwards.
+    ///
     /// Extraction is not atomic; If an error is encountered, some of the files
-    /// may be left on disk.
+    /// may be left on disk. Use [`ExtractOptions::atomic`] for all-or-nothing
+    /// extraction.
+    ///
+    /// [`ZipArchive::extract`]: crate::ZipArchive::extract
     pub fn extract<P: AsRef<Path>>(self, directory: P) -> ZipResult<()> {
-        struct Extractor<'a>(&'a Path);
-        impl ZipStreamVisitor for Extractor<'_> {
-            fn visit_file(&mut self, file: &mut ZipFile<'_>) -> ZipResult<()> {
-                let filepath = file
-                    .enclosed_name()
-                    .ok_or(ZipError::InvalidArchive("Invalid file path"))?;
-
-                let outpath = self.0.join(filepath);
-
-                if file.name().ends_with('/') {
-                    fs::create_dir_all(&outpath)?;
-                } else {
-                    if let Some(p) = outpath.parent() {
-                        fs::create_dir_all(p)?;
-                    }
-                    let mut outfile = fs::File::create(&outpath)?;
-                    io::copy(file, &mut outfile)?;
-                }
+        self.extract_with_options(directory, ExtractOptions::default())
+    }
 
+    /// Extract a Zip archive into a directory, configured by [`ExtractOptions`].
+    ///
+    /// See [`ZipStreamReader::extract`] for how paths and symlinks are handled.
+    /// The total number of entries reported to the progress callback is always
+    /// `None`.
+    pub fn extract_with_options<P: AsRef<Path>>(
+        self,
+        directory: P,
+        options: ExtractOptions<'_>,
+    ) -> ZipResult<()> {
+        struct Extractor<'e, 'r, 'a> {
+            inner: &'e mut extract::Extractor<'r, 'a>,
+            /// Entry names mapped to the path they were extracted to
+            paths: HashMap<String, PathBuf>,
+        }
+        impl ZipStreamVisitor for Extractor<'_, '_, '_> {
+            fn visit_file(&mut self, file: &mut ZipFile<'_>) -> ZipResult<()> {
+                match self.inner.extract_entry(file)? {
+                    Some(path) => self.paths.insert(file.name().to_owned(), path),
+                    None => self.paths.remove(file.name()),
+                };
                 Ok(())
             }
 
-            #[allow(unused)]
             fn visit_additional_metadata(
                 &mut self,
                 metadata: &ZipStreamFileMetadata,
             ) -> ZipResult<()> {
-                #[cfg(unix)]
-                {
-                    let filepath = metadata
-                        .enclosed_name()
-                        .ok_or(ZipError::InvalidArchive("Invalid file path"))?;
+                let Some(filepath) = self.paths.get(metadata.name()) else {
+                    return Ok(());
+                };
+                let outpath = extract::check_parents(self.inner.root(), filepath)?;
+                if extract::is_symlink(&outpath) {
+                    // Already converted by a duplicate entry
+                    return Ok(());
+                }
 
-                    let outpath = self.0.join(filepath);
+                #[cfg(unix)]
+                if metadata.is_symlink() && !metadata.name().ends_with('/') {
+                    let target = extract::read_symlink_target(fs::File::open(&outpath)?)
+                        .map_err(|err| err.in_entry(metadata.name(), None))?;
+                    fs::remove_file(&outpath)?;
+                    return extract::create_symlink(self.inner.root(), filepath, &target);
+                }
 
-                    use std::os::unix::fs::PermissionsExt;
-                    if let Some(mode) = metadata.unix_mode() {
-                        fs::set_permissions(outpath, fs::Permissions::from_mode(mode))?;
+                if let Some(mode) = metadata
+                    .unix_mode()
+                    .filter(|_| self.inner.apply_unix_mode())
+                {
+                    if !self.inner.set_directory_mode(&outpath, mode) {
+                        extract::set_unix_mode(&outpath, mode)?;
                     }
                 }
 
@@ -103,7 +182,30 @@ impl<R: Read> ZipStreamReader<R> {
             }
         }
 
-        self.visit(&mut Extractor(directory.as_ref()))
+        extract::run(directory.as_ref(), options, None, |inner| {
+            self.visit(&mut Extractor {
+                inner,
+                paths: HashMap::new(),
+            })
+        })
+    }
+
+    /// Extract the files selected by `matcher` into a directory
+    ///
+    /// The matcher gets the sanitized path of each entry, see
+    /// [`ZipArchive::extract_matching`]. Like in
+    /// [`ZipStreamReader::extract`], an entry with an invalid path fails the
+    /// extraction even if it would not be selected.
+    ///
+    /// [`ZipArchive::extract_matching`]: crate::ZipArchive::extract_matching
+    pub fn extract_matching<P: AsRef<Path>, M: EntryMatcher>(
+        self,
+        directory: P,
+        mut matcher: M,
+    ) -> ZipResult<()> {
+        let options = ExtractOptions::default()
+            .filter(move |path: &Path| matcher.matches(path).then(|| path.to_owned()));
+        self.extract_with_options(directory, options)
     }
 }
 
@@ -203,6 +305,16 @@ impl ZipStreamFileMetadata {
         self.0.data_start.load()
     }
 
+    /// Returns whether the file is a symbolic link, according to its unix mode
+    pub fn is_symlink(&self) -> bool {
+        self.0.is_symlink()
+    }
+
+    /// Get the scheme used to encrypt the file, or `None` if it is not encrypted
+    pub fn encryption(&self) -> Option<crate::EncryptionMethod> {
+        self.0.encryption()
+    }
+
     /// Get unix mode for the file
     pub fn unix_mode(&self) -> Option<u32> {
         self.0.unix_mode()
@@ -213,6 +325,7 @@ impl ZipStreamFileMetadata {
 mod test {
     use super::*;
     use std::collections::BTreeSet;
+    use std::fs;
     use std::io;
 
     struct DummyVisitor;
@@ -256,6 +369,7 @@ mod test {
         #[derive(Default)]
         struct V {
             filenames: BTreeSet<Box<str>>,
+            metadata: BTreeSet<Box<str>>,
         }
         impl ZipStreamVisitor for V {
             fn visit_file(&mut self, file: &mut ZipFile<'_>) -> ZipResult<()> {
@@ -275,13 +389,17 @@ mod test {
                         "{} is missing its file content",
                         metadata.name()
                     );
+                    self.metadata.insert(metadata.name().into());
                 }
 
                 Ok(())
             }
         }
 
-        reader.visit(&mut V::default()).unwrap();
+        let mut visitor = V::default();
+        reader.visit(&mut visitor).unwrap();
+        assert!(!visitor.filenames.is_empty());
+        assert_eq!(visitor.metadata, visitor.filenames);
     }
 
     #[test]
@@ -351,4 +469,71 @@ mod test {
         .visit(&mut DummyVisitor)
         .unwrap_err();
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn extract_symlinks() {
+        use crate::write::{FileOptions, ZipWriter};
+        use std::io::Write;
+
+        let mut zip = ZipWriter::new(io::Cursor::new(Vec::new()));
+        zip.start_file("data/file.txt", FileOptions::default())
+            .unwrap();
+        zip.write_all(b"content").unwrap();
+        zip.add_symlink("data/link", "file.txt", FileOptions::default())
+            .unwrap();
+        zip.add_symlink("escape", "../outside", FileOptions::default())
+            .unwrap();
+        let data = zip.finish().unwrap().into_inner();
+
+        let dir =
+            std::env::temp_dir().join(format!("ziperu-stream-symlinks-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        ZipStreamReader::new(io::Cursor::new(data))
+            .extract(&dir)
+            .unwrap_err();
+        assert_eq!(
+            fs::read_link(dir.join("data/link")).unwrap(),
+            Path::new("file.txt")
+        );
+        assert!(!extract::is_symlink(&dir.join("escape")));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn extract_with_options() {
+        use crate::write::{FileOptions, ZipWriter};
+        use std::io::Write;
+
+        let options = FileOptions::default().unix_permissions(0o500);
+        let mut zip = ZipWriter::new(io::Cursor::new(Vec::new()));
+        zip.add_directory("dir/", options).unwrap();
+        zip.start_file("dir/file.txt", options).unwrap();
+        zip.write_all(b"content").unwrap();
+        zip.start_file("skipped.txt", options).unwrap();
+        let data = zip.finish().unwrap().into_inner();
+
+        let dir =
+            std::env::temp_dir().join(format!("ziperu-stream-options-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut entries = 0;
+        let options = ExtractOptions::default()
+            .filter(|path| (path != Path::new("skipped.txt")).then(|| path.to_path_buf()))
+            .progress(|progress| entries = progress.entries_extracted);
+        ZipStreamReader::new(io::Cursor::new(data))
+            .extract_with_options(&dir, options)
+            .unwrap();
+        assert_eq!(entries, 2);
+        assert_eq!(fs::read(dir.join("dir/file.txt")).unwrap(), b"content");
+        assert!(!dir.join("skipped.txt").exists());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dir.join("dir")).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o500);
+            extract::set_unix_mode(&dir.join("dir"), 0o700).unwrap();
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
 }
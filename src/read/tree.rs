@@ -0,0 +1,212 @@
+use super::ZipFileMetadata;
+use crate::result::{ZipError, ZipResult};
+use crate::types::ZipFileData;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Component, Path};
+
+/// The directories of an archive, with the paths of their children
+#[derive(Debug)]
+pub(crate) struct DirectoryIndex {
+    nodes: HashMap<String, Node>,
+}
+
+#[derive(Debug, Default)]
+struct Node {
+    /// The index of the entry, `None` for implicit directories
+    entry: Option<usize>,
+    is_dir: bool,
+    /// The paths of the children, sorted
+    children: BTreeSet<String>,
+}
+
+impl DirectoryIndex {
+    pub(crate) fn new(files: &[ZipFileData]) -> DirectoryIndex {
+        let root = Node {
+            is_dir: true,
+            ..Node::default()
+        };
+        let mut index = DirectoryIndex {
+            nodes: HashMap::from([(String::new(), root)]),
+        };
+        for (entry, data) in files.iter().enumerate() {
+            let path = match data.enclosed_name().and_then(normalize) {
+                Some(path) if !path.is_empty() => path,
+                // Unsafe names and names of the root
+                _ => continue,
+            };
+            let is_dir = data.file_name.ends_with(['/', '\\']);
+            index.insert(path, entry, is_dir);
+        }
+        index
+    }
+
+    fn insert(&mut self, path: String, entry: usize, is_dir: bool) {
+        // Add the path to its parent, creating missing ancestors as implicit directories
+        let mut child = path.as_str();
+        while let Some(parent) = parent(child) {
+            let node = self.nodes.entry(parent.to_owned()).or_default();
+            node.is_dir = true;
+            if !node.children.insert(child.to_owned()) {
+                break;
+            }
+            child = parent;
+        }
+        let node = self.nodes.entry(path).or_default();
+        node.entry = Some(entry);
+        node.is_dir |= is_dir;
+    }
+}
+
+/// The parent directory of a normalized path, `None` for the root
+fn parent(path: &str) -> Option<&str> {
+    match path.rsplit_once('/') {
+        Some((parent, _)) => Some(parent),
+        None if path.is_empty() => None,
+        None => Some(""),
+    }
+}
+
+/// Normalize a path to its components joined by `/`, resolving `.` and `..`
+///
+/// A leading `/` is ignored. Returns `None` if the path leaves the root or has a prefix.
+pub(crate) fn normalize(path: &Path) -> Option<String> {
+    let mut components = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => components.push(name.to_str()?),
+            Component::ParentDir => {
+                components.pop()?;
+            }
+            Component::CurDir | Component::RootDir => (),
+            Component::Prefix(_) => return None,
+        }
+    }
+    Some(components.join("/"))
+}
+
+/// A hierarchical view of the files in a [`crate::ZipArchive`]
+///
+/// Returned by [`crate::ZipArchive::directory_tree`]. Directories that only appear in the paths
+/// of other files are included as implicit directories, without metadata.
+///
+/// Paths are normalized: `.` components and a leading `/` are ignored, and `..` removes the
+/// previous component. The root directory is the empty path. Only files with an
+/// [enclosed name](crate::read::ZipFile::enclosed_name) are part of the tree. If several files
+/// have the same normalized path, the last one is used, and a directory takes the place of a
+/// file with its path.
+#[derive(Clone, Copy, Debug)]
+pub struct DirectoryTree<'a> {
+    files: &'a [ZipFileData],
+    index: &'a DirectoryIndex,
+}
+
+impl<'a> DirectoryTree<'a> {
+    pub(crate) fn new(files: &'a [ZipFileData], index: &'a DirectoryIndex) -> DirectoryTree<'a> {
+        DirectoryTree { files, index }
+    }
+
+    /// Returns whether a file or directory exists at `path`
+    pub fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.metadata(path).is_some()
+    }
+
+    /// Get the file or directory at `path`, or `None` if there is none
+    pub fn metadata<P: AsRef<Path>>(&self, path: P) -> Option<ZipDirEntry<'a>> {
+        let path = normalize(path.as_ref())?;
+        let (path, _) = self.index.nodes.get_key_value(&path)?;
+        Some(self.entry(path))
+    }
+
+    /// Returns an iterator over the files and directories in the directory at `path`, sorted by
+    /// name
+    ///
+    /// Fails with [`ZipError::FileNotFound`] if there is no directory at `path`.
+    pub fn read_dir<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> ZipResult<impl Iterator<Item = ZipDirEntry<'a>> + use<'a, P>> {
+        let node = normalize(path.as_ref())
+            .and_then(|path| self.index.nodes.get(&path))
+            .filter(|node| node.is_dir)
+            .ok_or(ZipError::FileNotFound)?;
+        let tree = *self;
+        Ok(node.children.iter().map(move |path| tree.entry(path)))
+    }
+
+    fn entry(&self, path: &'a str) -> ZipDirEntry<'a> {
+        let node = &self.index.nodes[path];
+        ZipDirEntry {
+            path,
+            is_dir: node.is_dir,
+            metadata: node
+                .entry
+                .map(|entry| ZipFileMetadata::new(&self.files[entry], entry)),
+        }
+    }
+}
+
+/// A file or directory in a [`DirectoryTree`]
+#[derive(Clone, Copy, Debug)]
+pub struct ZipDirEntry<'a> {
+    path: &'a str,
+    is_dir: bool,
+    metadata: Option<ZipFileMetadata<'a>>,
+}
+
+impl<'a> ZipDirEntry<'a> {
+    /// Get the normalized path, with components separated by `/`
+    pub fn path(&self) -> &'a str {
+        self.path
+    }
+
+    /// Get the last component of the path
+    pub fn name(&self) -> &'a str {
+        match self.path.rsplit_once('/') {
+            Some((_, name)) => name,
+            None => self.path,
+        }
+    }
+
+    /// Returns whether this is a directory
+    pub fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+
+    /// Returns whether this is a file
+    pub fn is_file(&self) -> bool {
+        !self.is_dir
+    }
+
+    /// Returns whether this is a directory without an entry in the archive
+    pub fn is_implicit(&self) -> bool {
+        self.metadata.is_none()
+    }
+
+    /// Get the metadata of the entry, or `None` for implicit directories
+    pub fn metadata(&self) -> Option<ZipFileMetadata<'a>> {
+        self.metadata
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn normalize_paths() {
+        let normalize = |path: &str| normalize(Path::new(path));
+        assert_eq!(normalize("a/b/c.txt").as_deref(), Some("a/b/c.txt"));
+        assert_eq!(normalize("/a//b/").as_deref(), Some("a/b"));
+        assert_eq!(normalize("./a/../b/./c").as_deref(), Some("b/c"));
+        assert_eq!(normalize("").as_deref(), Some(""));
+        assert_eq!(normalize("a/..").as_deref(), Some(""));
+        assert_eq!(normalize("a/../.."), None);
+    }
+
+    #[test]
+    fn parents() {
+        assert_eq!(parent("a/b/c"), Some("a/b"));
+        assert_eq!(parent("a"), Some(""));
+        assert_eq!(parent(""), None);
+    }
+}
@@ -36,10 +36,144 @@ pub enum ZipError {
 
     /// Could not construct a reader with the given input
     ReaderError,
+
+    /// An error with structured information on its cause and where it occurred
+    Detailed(Box<ErrorDetails>),
+}
+
+/// The cause of a [`ZipError::Detailed`] error
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A header does not start with the expected signature
+    BadSignature {
+        /// The signature of the expected header
+        expected: u32,
+        /// The signature that was read
+        found: u32,
+    },
+    /// The CRC32 checksum of the decompressed data does not match the stored one
+    CrcMismatch {
+        /// The stored checksum
+        expected: u32,
+        /// The checksum of the data that was read
+        actual: u32,
+    },
+    /// The compression method with this ID is not supported
+    UnsupportedMethod(u16),
+    /// The password for an encrypted entry is wrong
+    WrongPassword,
+    /// A size exceeds a limit of this crate
+    LimitExceeded {
+        /// The largest supported size
+        limit: u64,
+    },
+    /// A name looked up with a [`crate::read::NameMatching`] matches several different names
+    AmbiguousName {
+        /// The names of the archive that match
+        names: Vec<String>,
+    },
+}
+
+impl ErrorKind {
+    fn io_kind(&self) -> io::ErrorKind {
+        match self {
+            ErrorKind::UnsupportedMethod(_) => io::ErrorKind::Unsupported,
+            ErrorKind::WrongPassword | ErrorKind::AmbiguousName { .. } => {
+                io::ErrorKind::InvalidInput
+            }
+            ErrorKind::BadSignature { .. }
+            | ErrorKind::CrcMismatch { .. }
+            | ErrorKind::LimitExceeded { .. } => io::ErrorKind::InvalidData,
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::BadSignature { expected, found } => {
+                write!(
+                    fmt,
+                    "Invalid signature (expected {expected:#010x}, found {found:#010x})"
+                )
+            }
+            ErrorKind::CrcMismatch { expected, actual } => {
+                write!(
+                    fmt,
+                    "Invalid checksum (expected {expected:#010x}, found {actual:#010x})"
+                )
+            }
+            ErrorKind::UnsupportedMethod(id) => {
+                write!(fmt, "Compression method {id} not supported")
+            }
+            ErrorKind::WrongPassword => write!(fmt, "Wrong password"),
+            ErrorKind::LimitExceeded { limit } => {
+                write!(fmt, "Size exceeds the limit of {limit} bytes")
+            }
+            ErrorKind::AmbiguousName { names } => {
+                write!(fmt, "Name matches several files: {}", names.join(", "))
+            }
+        }
+    }
+}
+
+/// The cause and location of a [`ZipError::Detailed`] error
+#[derive(Debug, Clone)]
+pub struct ErrorDetails {
+    kind: ErrorKind,
+    name: Option<String>,
+    index: Option<usize>,
+    offset: Option<u64>,
+}
+
+impl ErrorDetails {
+    /// The cause of the error
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// The name of the entry the error occurred in, if known
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The index of the entry the error occurred in, if known
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+
+    /// The offset of the malformed structure in the archive, if known
+    ///
+    /// For errors in an entry's data, this is the offset of its local file header.
+    pub fn offset(&self) -> Option<u64> {
+        self.offset
+    }
+}
+
+impl fmt::Display for ErrorDetails {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}", self.kind)?;
+        if let Some(name) = &self.name {
+            write!(fmt, " in entry {name:?}")?;
+        }
+        if let Some(index) = self.index {
+            write!(fmt, " (index {index})")?;
+        }
+        if let Some(offset) = self.offset {
+            write!(fmt, " at offset {offset}")?;
+        }
+        Ok(())
+    }
 }
 
 impl From<io::Error> for ZipError {
+    /// Unwraps errors which were converted from a [`ZipError`]
     fn from(err: io::Error) -> ZipError {
+        if err.get_ref().is_some_and(|inner| inner.is::<ZipError>()) {
+            let inner = err.into_inner().unwrap();
+            return *inner.downcast::<ZipError>().unwrap();
+        }
         ZipError::Io(err)
     }
 }
@@ -52,6 +186,13 @@ impl fmt::Display for ZipError {
             ZipError::UnsupportedArchive(err) => write!(fmt, "unsupported Zip archive: {err}"),
             ZipError::FileNotFound => write!(fmt, "specified file not found in archive"),
             ZipError::ReaderError => write!(fmt, "reader could not be constructed"),
+            ZipError::Detailed(details) => match details.kind {
+                ErrorKind::UnsupportedMethod(_) => {
+                    write!(fmt, "unsupported Zip archive: {details}")
+                }
+                ErrorKind::WrongPassword => write!(fmt, "{details}"),
+                _ => write!(fmt, "invalid Zip archive: {details}"),
+            },
         }
     }
 }
@@ -78,11 +219,78 @@ impl ZipError {
     /// # ()
     /// ```
     pub const PASSWORD_REQUIRED: &'static str = "Password required to decrypt file";
+
+    /// Create a [`ZipError::Detailed`] error
+    pub(crate) fn detailed(kind: ErrorKind) -> ZipError {
+        ZipError::Detailed(Box::new(ErrorDetails {
+            kind,
+            name: None,
+            index: None,
+            offset: None,
+        }))
+    }
+
+    /// Copy this error to report it again, I/O errors keep only their kind and message
+    pub(crate) fn duplicate(&self) -> ZipError {
+        match self {
+            ZipError::Io(err) => ZipError::Io(io::Error::new(err.kind(), err.to_string())),
+            ZipError::InvalidArchive(err) => ZipError::InvalidArchive(err),
+            ZipError::UnsupportedArchive(err) => ZipError::UnsupportedArchive(err),
+            ZipError::FileNotFound => ZipError::FileNotFound,
+            ZipError::ReaderError => ZipError::ReaderError,
+            ZipError::Detailed(details) => ZipError::Detailed(details.clone()),
+        }
+    }
+
+    /// The structured information on this error, if it has any
+    pub fn details(&self) -> Option<&ErrorDetails> {
+        match self {
+            ZipError::Detailed(details) => Some(details),
+            _ => None,
+        }
+    }
+
+    /// Set the offset of a [`ZipError::Detailed`] error, if it is not known yet
+    pub(crate) fn at_offset(mut self, offset: u64) -> ZipError {
+        if let ZipError::Detailed(details) = &mut self {
+            details.offset.get_or_insert(offset);
+        }
+        self
+    }
+
+    /// Set the entry of a [`ZipError::Detailed`] error, if it is not known yet
+    pub(crate) fn in_entry(mut self, name: &str, index: Option<usize>) -> ZipError {
+        if let ZipError::Detailed(details) = &mut self {
+            details.name.get_or_insert_with(|| name.to_owned());
+            details.index = details.index.or(index);
+        }
+        self
+    }
+
+    /// Apply `f` to a [`ZipError`] wrapped by an I/O error
+    pub(crate) fn map_io(err: io::Error, f: impl FnOnce(ZipError) -> ZipError) -> io::Error {
+        if err.get_ref().is_some_and(|inner| inner.is::<ZipError>()) {
+            io::Error::from(f(ZipError::from(err)))
+        } else {
+            err
+        }
+    }
 }
 
 impl From<ZipError> for io::Error {
+    /// Wraps the error with a matching [`io::ErrorKind`], so it can be recovered with
+    /// [`io::Error::into_inner`] and [`Box::downcast`], or converted back with [`ZipError::from`].
+    /// [`ZipError::Io`] is unwrapped.
     fn from(err: ZipError) -> io::Error {
-        io::Error::other(err)
+        let kind = match err {
+            ZipError::Io(err) => return err,
+            ZipError::InvalidArchive(_) => io::ErrorKind::InvalidData,
+            ZipError::UnsupportedArchive(_) => io::ErrorKind::Unsupported,
+            ZipError::FileNotFound => io::ErrorKind::NotFound,
+            ZipError::ReaderError => io::ErrorKind::Other,
+            ZipError::Detailed(ref details) => details.kind.io_kind(),
+        };
+        io::Error::new(kind, err)
     }
 }
 
@@ -5,6 +5,7 @@ use std::io::prelude::*;
 
 pub const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x04034b50;
 pub const CENTRAL_DIRECTORY_HEADER_SIGNATURE: u32 = 0x02014b50;
+pub const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x08074b50;
 const CENTRAL_DIRECTORY_END_SIGNATURE: u32 = 0x06054b50;
 pub const ZIP64_CENTRAL_DIRECTORY_END_SIGNATURE: u32 = 0x06064b50;
 const ZIP64_CENTRAL_DIRECTORY_END_LOCATOR_SIGNATURE: u32 = 0x07064b50;
@@ -12,6 +13,64 @@ const ZIP64_CENTRAL_DIRECTORY_END_LOCATOR_SIGNATURE: u32 = 0x07064b50;
 pub const ZIP64_BYTES_THR: u64 = u32::MAX as u64;
 pub const ZIP64_ENTRY_THR: usize = u16::MAX as usize;
 
+/// The number of bytes read at once when searching for a signature
+const SEARCH_BLOCK_SIZE: u64 = 8 * 1024;
+
+/// Find the last position in `start..=last` at which `signature` is stored
+///
+/// The bytes are read in blocks going backwards from `last`, so that a match close to `last`
+/// needs a single read.
+fn rfind_signature<T: Read + io::Seek>(
+    reader: &mut T,
+    start: u64,
+    last: u64,
+    signature: u32,
+) -> ZipResult<Option<u64>> {
+    let signature = signature.to_le_bytes();
+    let mut buffer = Vec::new();
+    // The end of the bytes that are left to search, blocks overlap so that no match is split
+    let mut end = last + 4;
+    while end >= start + 4 {
+        let block_start = end.saturating_sub(SEARCH_BLOCK_SIZE).max(start);
+        buffer.resize((end - block_start) as usize, 0);
+        reader.seek(io::SeekFrom::Start(block_start))?;
+        reader.read_exact(&mut buffer)?;
+        if let Some(i) = buffer.windows(4).rposition(|bytes| bytes == signature) {
+            return Ok(Some(block_start + i as u64));
+        }
+        if block_start == start {
+            break;
+        }
+        end = block_start + 3;
+    }
+    Ok(None)
+}
+
+/// Find the first position in `first..=last` at which `signature` is stored
+///
+/// The bytes are read in blocks going forwards from `first`.
+fn find_signature<T: Read + io::Seek>(
+    reader: &mut T,
+    first: u64,
+    last: u64,
+    signature: u32,
+) -> ZipResult<Option<u64>> {
+    let signature = signature.to_le_bytes();
+    let mut buffer = Vec::new();
+    let mut start = first;
+    while start <= last {
+        let end = (last + 4).min(start.saturating_add(SEARCH_BLOCK_SIZE));
+        buffer.resize((end - start) as usize, 0);
+        reader.seek(io::SeekFrom::Start(start))?;
+        reader.read_exact(&mut buffer)?;
+        if let Some(i) = buffer.windows(4).position(|bytes| bytes == signature) {
+            return Ok(Some(start + i as u64));
+        }
+        start = end - 3;
+    }
+    Ok(None)
+}
+
 pub struct CentralDirectoryEnd {
     pub disk_number: u16,
     pub disk_with_central_directory: u16,
@@ -36,17 +95,20 @@ impl CentralDirectoryEnd {
     }
 
     pub fn parse<T: Read>(reader: &mut T) -> ZipResult<CentralDirectoryEnd> {
-        let magic = reader.read_u32::<LittleEndian>()?;
+        let mut header = [0; 22];
+        reader.read_exact(&mut header)?;
+        let mut header = &header[..];
+        let magic = header.read_u32::<LittleEndian>()?;
         if magic != CENTRAL_DIRECTORY_END_SIGNATURE {
             return Err(ZipError::InvalidArchive("Invalid digital signature header"));
         }
-        let disk_number = reader.read_u16::<LittleEndian>()?;
-        let disk_with_central_directory = reader.read_u16::<LittleEndian>()?;
-        let number_of_files_on_this_disk = reader.read_u16::<LittleEndian>()?;
-        let number_of_files = reader.read_u16::<LittleEndian>()?;
-        let central_directory_size = reader.read_u32::<LittleEndian>()?;
-        let central_directory_offset = reader.read_u32::<LittleEndian>()?;
-        let zip_file_comment_length = reader.read_u16::<LittleEndian>()? as usize;
+        let disk_number = header.read_u16::<LittleEndian>()?;
+        let disk_with_central_directory = header.read_u16::<LittleEndian>()?;
+        let number_of_files_on_this_disk = header.read_u16::<LittleEndian>()?;
+        let number_of_files = header.read_u16::<LittleEndian>()?;
+        let central_directory_size = header.read_u32::<LittleEndian>()?;
+        let central_directory_offset = header.read_u32::<LittleEndian>()?;
+        let zip_file_comment_length = header.read_u16::<LittleEndian>()? as usize;
         let mut zip_file_comment = vec![0; zip_file_comment_length];
         reader.read_exact(&mut zip_file_comment)?;
 
@@ -65,7 +127,6 @@ impl CentralDirectoryEnd {
         reader: &mut T,
     ) -> ZipResult<(CentralDirectoryEnd, u64)> {
         const HEADER_SIZE: u64 = 22;
-        const BYTES_BETWEEN_MAGIC_AND_COMMENT_SIZE: u64 = HEADER_SIZE - 6;
         let file_length = reader.seek(io::SeekFrom::End(0))?;
 
         let search_upper_bound = file_length.saturating_sub(HEADER_SIZE + u16::MAX as u64);
@@ -74,24 +135,17 @@ impl CentralDirectoryEnd {
             return Err(ZipError::InvalidArchive("Invalid zip header"));
         }
 
-        let mut pos = file_length - HEADER_SIZE;
-        while pos >= search_upper_bound {
-            reader.seek(io::SeekFrom::Start(pos))?;
-            if reader.read_u32::<LittleEndian>()? == CENTRAL_DIRECTORY_END_SIGNATURE {
-                reader.seek(io::SeekFrom::Current(
-                    BYTES_BETWEEN_MAGIC_AND_COMMENT_SIZE as i64,
-                ))?;
-                let cde_start_pos = reader.seek(io::SeekFrom::Start(pos))?;
-                return CentralDirectoryEnd::parse(reader).map(|cde| (cde, cde_start_pos));
-            }
-            pos = match pos.checked_sub(1) {
-                Some(p) => p,
-                None => break,
-            };
-        }
-        Err(ZipError::InvalidArchive(
+        let pos = rfind_signature(
+            reader,
+            search_upper_bound,
+            file_length - HEADER_SIZE,
+            CENTRAL_DIRECTORY_END_SIGNATURE,
+        )?
+        .ok_or(ZipError::InvalidArchive(
             "Could not find central directory end",
-        ))
+        ))?;
+        let cde_start_pos = reader.seek(io::SeekFrom::Start(pos))?;
+        CentralDirectoryEnd::parse(reader).map(|cde| (cde, cde_start_pos))
     }
 
     pub fn write<T: Write>(&self, writer: &mut T) -> ZipResult<()> {
@@ -116,15 +170,18 @@ pub struct Zip64CentralDirectoryEndLocator {
 
 impl Zip64CentralDirectoryEndLocator {
     pub fn parse<T: Read>(reader: &mut T) -> ZipResult<Zip64CentralDirectoryEndLocator> {
-        let magic = reader.read_u32::<LittleEndian>()?;
+        let mut locator = [0; 20];
+        reader.read_exact(&mut locator)?;
+        let mut locator = &locator[..];
+        let magic = locator.read_u32::<LittleEndian>()?;
         if magic != ZIP64_CENTRAL_DIRECTORY_END_LOCATOR_SIGNATURE {
             return Err(ZipError::InvalidArchive(
                 "Invalid zip64 locator digital signature header",
             ));
         }
-        let disk_with_central_directory = reader.read_u32::<LittleEndian>()?;
-        let end_of_central_directory_offset = reader.read_u64::<LittleEndian>()?;
-        let number_of_disks = reader.read_u32::<LittleEndian>()?;
+        let disk_with_central_directory = locator.read_u32::<LittleEndian>()?;
+        let end_of_central_directory_offset = locator.read_u64::<LittleEndian>()?;
+        let number_of_disks = locator.read_u32::<LittleEndian>()?;
 
         Ok(Zip64CentralDirectoryEndLocator {
             disk_with_central_directory,
@@ -160,46 +217,47 @@ impl Zip64CentralDirectoryEnd {
         nominal_offset: u64,
         search_upper_bound: u64,
     ) -> ZipResult<(Zip64CentralDirectoryEnd, u64)> {
-        let mut pos = nominal_offset;
-
-        while pos <= search_upper_bound {
-            reader.seek(io::SeekFrom::Start(pos))?;
-
-            if reader.read_u32::<LittleEndian>()? == ZIP64_CENTRAL_DIRECTORY_END_SIGNATURE {
-                let archive_offset = pos - nominal_offset;
-
-                let _record_size = reader.read_u64::<LittleEndian>()?;
-                // We would use this value if we did anything with the "zip64 extensible data sector".
-
-                let version_made_by = reader.read_u16::<LittleEndian>()?;
-                let version_needed_to_extract = reader.read_u16::<LittleEndian>()?;
-                let disk_number = reader.read_u32::<LittleEndian>()?;
-                let disk_with_central_directory = reader.read_u32::<LittleEndian>()?;
-                let number_of_files_on_this_disk = reader.read_u64::<LittleEndian>()?;
-                let number_of_files = reader.read_u64::<LittleEndian>()?;
-                let central_directory_size = reader.read_u64::<LittleEndian>()?;
-                let central_directory_offset = reader.read_u64::<LittleEndian>()?;
-
-                return Ok((
-                    Zip64CentralDirectoryEnd {
-                        version_made_by,
-                        version_needed_to_extract,
-                        disk_number,
-                        disk_with_central_directory,
-                        number_of_files_on_this_disk,
-                        number_of_files,
-                        central_directory_size,
-                        central_directory_offset,
-                    },
-                    archive_offset,
-                ));
-            }
+        let pos = find_signature(
+            reader,
+            nominal_offset,
+            search_upper_bound,
+            ZIP64_CENTRAL_DIRECTORY_END_SIGNATURE,
+        )?
+        .ok_or(ZipError::InvalidArchive(
+            "Could not find ZIP64 central directory end",
+        ))?;
+        let archive_offset = pos - nominal_offset;
 
-            pos += 1;
-        }
+        let mut record = [0; 56];
+        reader.seek(io::SeekFrom::Start(pos))?;
+        reader.read_exact(&mut record)?;
+        // Skip the signature
+        let mut record = &record[4..];
 
-        Err(ZipError::InvalidArchive(
-            "Could not find ZIP64 central directory end",
+        let _record_size = record.read_u64::<LittleEndian>()?;
+        // We would use this value if we did anything with the "zip64 extensible data sector".
+
+        let version_made_by = record.read_u16::<LittleEndian>()?;
+        let version_needed_to_extract = record.read_u16::<LittleEndian>()?;
+        let disk_number = record.read_u32::<LittleEndian>()?;
+        let disk_with_central_directory = record.read_u32::<LittleEndian>()?;
+        let number_of_files_on_this_disk = record.read_u64::<LittleEndian>()?;
+        let number_of_files = record.read_u64::<LittleEndian>()?;
+        let central_directory_size = record.read_u64::<LittleEndian>()?;
+        let central_directory_offset = record.read_u64::<LittleEndian>()?;
+
+        Ok((
+            Zip64CentralDirectoryEnd {
+                version_made_by,
+                version_needed_to_extract,
+                disk_number,
+                disk_with_central_directory,
+                number_of_files_on_this_disk,
+                number_of_files,
+                central_directory_size,
+                central_directory_offset,
+            },
+            archive_offset,
         ))
     }
 
@@ -217,3 +275,100 @@ impl Zip64CentralDirectoryEnd {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    const SIGNATURE: u32 = CENTRAL_DIRECTORY_END_SIGNATURE;
+
+    /// Data with the signature at each of the positions
+    fn data(len: usize, positions: &[usize]) -> Cursor<Vec<u8>> {
+        let mut data = vec![0; len];
+        for &pos in positions {
+            data[pos..pos + 4].copy_from_slice(&SIGNATURE.to_le_bytes());
+        }
+        Cursor::new(data)
+    }
+
+    #[test]
+    fn signature_across_blocks() {
+        let block = SEARCH_BLOCK_SIZE as usize;
+        let len = 3 * block;
+        // Every position a match can be split at by the blocks, from both ends
+        let boundaries = [block, 2 * block - 3, len - block, len - 2 * block + 3];
+        for boundary in boundaries {
+            for pos in boundary - 4..=boundary {
+                let last = (len - 4) as u64;
+                let mut reader = data(len, &[pos]);
+                assert_eq!(
+                    rfind_signature(&mut reader, 0, last, SIGNATURE).unwrap(),
+                    Some(pos as u64)
+                );
+                assert_eq!(
+                    find_signature(&mut reader, 0, last, SIGNATURE).unwrap(),
+                    Some(pos as u64)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn signature_bounds() {
+        let mut reader = data(100, &[10, 50]);
+        assert_eq!(
+            rfind_signature(&mut reader, 0, 96, SIGNATURE).unwrap(),
+            Some(50)
+        );
+        assert_eq!(
+            rfind_signature(&mut reader, 0, 49, SIGNATURE).unwrap(),
+            Some(10)
+        );
+        assert_eq!(
+            rfind_signature(&mut reader, 11, 49, SIGNATURE).unwrap(),
+            None
+        );
+        assert_eq!(
+            rfind_signature(&mut reader, 50, 50, SIGNATURE).unwrap(),
+            Some(50)
+        );
+        assert_eq!(
+            find_signature(&mut reader, 0, 96, SIGNATURE).unwrap(),
+            Some(10)
+        );
+        assert_eq!(
+            find_signature(&mut reader, 11, 96, SIGNATURE).unwrap(),
+            Some(50)
+        );
+        assert_eq!(
+            find_signature(&mut reader, 11, 49, SIGNATURE).unwrap(),
+            None
+        );
+        assert_eq!(
+            find_signature(&mut reader, 60, 50, SIGNATURE).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn central_directory_end_in_comment() {
+        let end = CentralDirectoryEnd {
+            disk_number: 0,
+            disk_with_central_directory: 0,
+            number_of_files_on_this_disk: 0,
+            number_of_files: 0,
+            central_directory_size: 0,
+            central_directory_offset: 0,
+            zip_file_comment: vec![b'c'; u16::MAX as usize],
+        };
+        let mut data = vec![0; 100];
+        end.write(&mut data).unwrap();
+        let (found, pos) = CentralDirectoryEnd::find_and_parse(&mut Cursor::new(data)).unwrap();
+        assert_eq!(pos, 100);
+        assert_eq!(found.zip_file_comment.len(), u16::MAX as usize);
+
+        let mut data = Cursor::new(vec![0; 200_000]);
+        assert!(CentralDirectoryEnd::find_and_parse(&mut data).is_err());
+    }
+}
@@ -3,14 +3,15 @@ use std::path;
 
 #[cfg(target_has_atomic = "64")]
 use std::sync::atomic;
-#[cfg(not(feature = "time"))]
 use std::time::SystemTime;
 #[cfg(doc)]
 use {crate::read::ZipFile, crate::write::FileOptions};
 
 mod ffi {
+    pub const S_IFMT: u32 = 0o0170000;
     pub const S_IFDIR: u32 = 0o0040000;
     pub const S_IFREG: u32 = 0o0100000;
+    pub const S_IFLNK: u32 = 0o0120000;
 }
 
 #[cfg(not(target_has_atomic = "64"))]
@@ -42,7 +43,6 @@ mod atomic {
 }
 
 use crate::CompressionMethod;
-#[cfg(feature = "time")]
 use crate::result::DateTimeRangeError;
 #[cfg(feature = "time")]
 use time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time, error::ComponentRange};
@@ -264,6 +264,85 @@ impl TryFrom<OffsetDateTime> for DateTime {
         }
     }
 }
+impl TryFrom<SystemTime> for DateTime {
+    type Error = DateTimeRangeError;
+
+    /// Converts a SystemTime to a DateTime in UTC, truncating sub-second precision
+    fn try_from(time: SystemTime) -> Result<Self, Self::Error> {
+        let seconds = time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_err(|_| DateTimeRangeError)?
+            .as_secs();
+        let (days, seconds) = (seconds / 86400, seconds % 86400);
+
+        // Convert the days since the unix epoch to a civil date, see
+        // https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let z = days + 719468;
+        let era = z / 146097;
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + (month <= 2) as u64;
+
+        DateTime::from_date_and_time(
+            u16::try_from(year).map_err(|_| DateTimeRangeError)?,
+            month as u8,
+            day as u8,
+            (seconds / 3600) as u8,
+            (seconds % 3600 / 60) as u8,
+            (seconds % 60) as u8,
+        )
+        .map_err(|_| DateTimeRangeError)
+    }
+}
+
+impl TryFrom<DateTime> for SystemTime {
+    type Error = DateTimeRangeError;
+
+    /// Converts a DateTime, interpreted as UTC, to a SystemTime
+    ///
+    /// Fails if the DateTime holds unreasonable values, which may happen when
+    /// it was read from a zip file.
+    fn try_from(dt: DateTime) -> Result<Self, Self::Error> {
+        let valid =
+            DateTime::from_date_and_time(dt.year, dt.month, dt.day, dt.hour, dt.minute, dt.second)
+                .map_err(|_| DateTimeRangeError)?;
+
+        // Convert the civil date to days since the unix epoch, see
+        // https://howardhinnant.github.io/date_algorithms.html#days_from_civil
+        let year = valid.year as u64 - (valid.month <= 2) as u64;
+        let month = valid.month as u64;
+        let era = year / 400;
+        let yoe = year - era * 400;
+        let doy =
+            (153 * if month > 2 { month - 3 } else { month + 9 } + 2) / 5 + valid.day as u64 - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        let days = era * 146097 + doe - 719468;
+
+        let seconds = days * 86400
+            + valid.hour as u64 * 3600
+            + valid.minute as u64 * 60
+            + valid.second as u64;
+        Ok(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(seconds))
+    }
+}
+
+/// Bytes holding secrets, which are wiped on drop with the `zeroize` feature
+#[cfg(feature = "zeroize")]
+pub(crate) type SecretBytes = zeroize::Zeroizing<Vec<u8>>;
+#[cfg(not(feature = "zeroize"))]
+pub(crate) type SecretBytes = Vec<u8>;
+
+/// Take ownership of bytes holding a secret
+pub(crate) fn secret(bytes: Vec<u8>) -> SecretBytes {
+    #[cfg(feature = "zeroize")]
+    let bytes = zeroize::Zeroizing::new(bytes);
+    bytes
+}
+
 // This defined the major (6) + minor (3) version of the This defined the major (6)
 // + minor (3) version of the implemented APPNOTE spec
 pub const APPNOTE_SPEC_VERSION: u8 = 6 * 10 + 3;
@@ -345,6 +424,8 @@ pub struct ZipFileData {
     pub large_file: bool,
     /// AES mode if applicable
     pub aes_mode: Option<(AesMode, AesVendorVersion)>,
+    /// Version needed by the custom codec compressing the file, if any
+    pub codec_version_needed: Option<u16>,
 }
 
 impl ZipFileData {
@@ -392,6 +473,17 @@ impl ZipFileData {
         Some(path)
     }
 
+    pub fn encryption(&self) -> Option<EncryptionMethod> {
+        match (self.encrypted, self.aes_mode) {
+            (false, _) => None,
+            (true, None) => Some(EncryptionMethod::ZipCrypto),
+            (true, Some((mode, vendor_version))) => Some(EncryptionMethod::Aes {
+                mode,
+                vendor_version,
+            }),
+        }
+    }
+
     /// Get unix mode for the file
     pub(crate) fn unix_mode(&self) -> Option<u32> {
         if self.external_attributes == 0 {
@@ -422,6 +514,11 @@ impl ZipFileData {
             .is_some_and(|mode| (mode & ffi::S_IFDIR) == ffi::S_IFDIR)
     }
 
+    pub fn is_symlink(&self) -> bool {
+        self.unix_mode()
+            .is_some_and(|mode| (mode & ffi::S_IFMT) == ffi::S_IFLNK)
+    }
+
     pub fn version_needed(&self) -> u16 {
         // See chapter 4.4.3.2 in https://pkware.cachefly.net/webdocs/casestudies/APPNOTE.TXT
         let compression = match self.compression_method {
@@ -438,10 +535,18 @@ impl ZipFileData {
             CompressionMethod::Bzip2 => 46,
             #[cfg(feature = "lzma")]
             CompressionMethod::Lzma => 63,
-            _ => DEFAULT_MINIMUM_ZIP_SPECIFICATION_VERSION,
+            _ => self
+                .codec_version_needed
+                .unwrap_or(DEFAULT_MINIMUM_ZIP_SPECIFICATION_VERSION),
         };
 
-        let encryption = if self.aes_mode.is_some() { 51 } else { 10 };
+        let encryption = if self.aes_mode.is_some() {
+            51
+        } else if self.encrypted {
+            20
+        } else {
+            10
+        };
 
         let features = if self.large_file {
             45
@@ -455,30 +560,52 @@ impl ZipFileData {
     }
 }
 
+/// The scheme used to encrypt a file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EncryptionMethod {
+    /// The traditional PKWARE encryption, also known as ZipCrypto
+    ZipCrypto,
+    /// WinZip AES encryption
+    Aes {
+        /// The key length
+        mode: AesMode,
+        /// Whether the CRC of the plaintext is stored
+        vendor_version: AesVendorVersion,
+    },
+}
+
 /// The encryption specification used to encrypt a file with AES.
 ///
 /// According to the [specification](https://www.winzip.com/win/en/aes_info.html#winzip11) AE-2
 /// does not make use of the CRC check.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum AesVendorVersion {
+    /// AE-1, which stores the CRC of the plaintext
     Ae1,
+    /// AE-2, which does not store a CRC
     Ae2,
 }
 
 /// AES variant used.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum AesMode {
+    /// AES with a 128 bit key
     Aes128,
+    /// AES with a 192 bit key
     Aes192,
+    /// AES with a 256 bit key
     Aes256,
 }
 
 #[cfg(feature = "aes-crypto")]
 impl AesMode {
+    /// Get the length of the salt in bytes
     pub fn salt_length(&self) -> usize {
         self.key_length() / 2
     }
 
+    /// Get the length of the key in bytes
     pub fn key_length(&self) -> usize {
         match self {
             Self::Aes128 => 16,
@@ -524,6 +651,7 @@ mod test {
             external_attributes: 0,
             large_file: false,
             aes_mode: None,
+            codec_version_needed: None,
         };
         assert_eq!(
             data.file_name_sanitized(),
@@ -636,6 +764,40 @@ mod test {
         assert!(dt.to_time().is_err());
     }
 
+    #[test]
+    fn system_time_conversion() {
+        use super::DateTime;
+        use std::time::{Duration, SystemTime};
+
+        // 2018-11-17 10:38:30
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_542_451_110);
+        let dt = DateTime::try_from(time).unwrap();
+        assert_eq!(
+            (
+                dt.year(),
+                dt.month(),
+                dt.day(),
+                dt.hour(),
+                dt.minute(),
+                dt.second()
+            ),
+            (2018, 11, 17, 10, 38, 30)
+        );
+
+        // 2000-02-29 23:59:59
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(951_868_799);
+        let dt = DateTime::try_from(time).unwrap();
+        assert_eq!((dt.year(), dt.month(), dt.day()), (2000, 2, 29));
+        assert_eq!(SystemTime::try_from(dt).unwrap(), time);
+        assert_eq!(
+            SystemTime::try_from(DateTime::default()).unwrap(),
+            SystemTime::UNIX_EPOCH + Duration::from_secs(315_532_800)
+        );
+        assert!(SystemTime::try_from(DateTime::from_msdos(0, 0)).is_err());
+
+        assert!(DateTime::try_from(SystemTime::UNIX_EPOCH).is_err());
+    }
+
     #[cfg(feature = "time")]
     #[test]
     fn time_at_january() {
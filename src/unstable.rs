@@ -6,14 +6,17 @@ pub mod stream {
 pub mod write {
     use crate::write::FileOptions;
     /// Unstable methods for [`FileOptions`].
-    pub trait FileOptionsExt {
+    pub trait FileOptionsExt<'k> {
         /// Write the file with the given password using the deprecated ZipCrypto algorithm.
         ///
         /// This is not recommended for new archives, as ZipCrypto is not secure.
-        fn with_deprecated_encryption(self, password: &[u8]) -> Self;
+        ///
+        /// The password is borrowed for as long as the options are used, the keys are derived
+        /// when a file is started.
+        fn with_deprecated_encryption(self, password: &'k [u8]) -> Self;
     }
-    impl FileOptionsExt for FileOptions {
-        fn with_deprecated_encryption(self, password: &[u8]) -> Self {
+    impl<'k> FileOptionsExt<'k> for FileOptions<'k> {
+        fn with_deprecated_encryption(self, password: &'k [u8]) -> Self {
             self.with_deprecated_encryption(password)
         }
     }
@@ -1,17 +1,46 @@
 //! Types for creating ZIP archives
 
+mod auto_compression;
+#[cfg(any(
+    feature = "deflate",
+    feature = "deflate-miniz",
+    feature = "deflate-zlib",
+    feature = "bzip2"
+))]
+mod buffer_encoder;
+mod compression_params;
 mod file_options;
 mod generic_writer;
+mod reproducible;
 
+use crate::codec::CodecRegistry;
 use crate::compression::CompressionMethod;
+use crate::extra_field::{self, ALIGNMENT_EXTRA_FIELD_ID, ExtraField};
 use crate::read::{ZipArchive, ZipFile, central_header_to_zip_file};
 use crate::result::{ZipError, ZipResult};
 use crate::spec;
 use crate::types::{
     APPNOTE_SPEC_VERSION, AtomicU64, DEFAULT_MINIMUM_ZIP_SPECIFICATION_VERSION, System, ZipFileData,
 };
+pub use crate::write::auto_compression::AutoCompression;
+#[cfg(feature = "bzip2")]
+pub use crate::write::compression_params::Bzip2Params;
+pub use crate::write::compression_params::CompressionParams;
+#[cfg(feature = "zstd")]
+pub use crate::write::compression_params::ZstdParams;
+#[cfg(any(
+    feature = "deflate",
+    feature = "deflate-miniz",
+    feature = "deflate-zlib"
+))]
+pub use crate::write::compression_params::{DeflateParams, DeflateStrategy};
+#[cfg(feature = "xz")]
+pub use crate::write::compression_params::{XzFilter, XzParams};
 pub use crate::write::file_options::FileOptions;
 use crate::write::generic_writer::GenericZipWriter;
+pub use crate::write::reproducible::Reproducible;
+#[cfg(feature = "zstd")]
+use crate::zstd_dictionary::{ZstdDictionaries, ZstdDictionary};
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use crc32fast::Hasher;
 use std::borrow::Cow;
@@ -19,6 +48,7 @@ use std::default::Default;
 use std::io::{self, Read, Seek, SeekFrom, Write};
 use std::mem;
 use std::path::Path;
+use std::sync::Arc;
 
 pub(crate) enum MaybeEncrypted<W> {
     Unencrypted(W),
@@ -39,6 +69,13 @@ impl<W: Write> Write for MaybeEncrypted<W> {
     }
 }
 
+/// The start of a file, kept until its compression method is chosen
+struct AutoSample {
+    data: Vec<u8>,
+    /// The policy the file was started with, which chooses the method even if it is replaced
+    policy: Arc<AutoCompression>,
+}
+
 #[derive(Default)]
 struct ZipWriterStats {
     hasher: Hasher,
@@ -95,7 +132,19 @@ pub struct ZipWriter<W: Write + Seek> {
     writing_to_extra_field: bool,
     writing_to_central_extra_field_only: bool,
     writing_raw: bool,
+    /// Length of the typed extra fields at the start of the current file's central extra field
+    typed_extra_field_length: usize,
     comment: Vec<u8>,
+    codecs: CodecRegistry,
+    /// Parameters of the current file, applied once its extra data is written
+    compression_params: Option<CompressionParams>,
+    #[cfg(feature = "zstd")]
+    zstd_dictionaries: ZstdDictionaries,
+    auto_compression: Option<Arc<AutoCompression>>,
+    auto_sample: Option<AutoSample>,
+    /// Whether the compression method of the current file differs from its local header
+    method_changed: bool,
+    reproducible: Option<Reproducible>,
 }
 
 impl<W: Write + Seek> Write for ZipWriter<W> {
@@ -103,6 +152,9 @@ impl<W: Write + Seek> Write for ZipWriter<W> {
         if !self.writing_to_file {
             return Err(io::Error::other("No file has been started"));
         }
+        if self.auto_sample.is_some() {
+            return self.write_sample(buf);
+        }
         match self.inner.ref_mut() {
             Some(ref mut w) => {
                 if self.writing_to_extra_field {
@@ -111,12 +163,6 @@ impl<W: Write + Seek> Write for ZipWriter<W> {
                     let write_result = w.write(buf);
                     if let Ok(count) = write_result {
                         self.stats.update(&buf[0..count]);
-                        if self.stats.bytes_written > spec::ZIP64_BYTES_THR
-                            && !self.files.last_mut().unwrap().large_file
-                        {
-                            let _inner = mem::replace(&mut self.inner, GenericZipWriter::Closed);
-                            return Err(io::Error::other("Large file option has not been set"));
-                        }
                     }
                     write_result
                 }
@@ -152,6 +198,12 @@ impl<A: Read + Write + Seek> ZipWriter<A> {
 
         let (archive_offset, directory_start, number_of_files) =
             ZipArchive::get_directory_counts(&mut readwriter, &footer, cde_start_pos)?;
+        // New files are written at the start of the central directory
+        if directory_start > cde_start_pos {
+            return Err(ZipError::InvalidArchive(
+                "Invalid central directory size or offset",
+            ));
+        }
 
         if readwriter.seek(SeekFrom::Start(directory_start)).is_err() {
             return Err(ZipError::InvalidArchive(
@@ -172,7 +224,16 @@ impl<A: Read + Write + Seek> ZipWriter<A> {
             writing_to_file: false,
             writing_to_extra_field: false,
             writing_to_central_extra_field_only: false,
+            typed_extra_field_length: 0,
             comment: footer.zip_file_comment,
+            codecs: CodecRegistry::new(),
+            compression_params: None,
+            #[cfg(feature = "zstd")]
+            zstd_dictionaries: ZstdDictionaries::new(),
+            auto_compression: None,
+            auto_sample: None,
+            method_changed: false,
+            reproducible: None,
             writing_raw: true, // avoid recomputing the last file's header
         })
     }
@@ -191,7 +252,16 @@ impl<W: Write + Seek> ZipWriter<W> {
             writing_to_extra_field: false,
             writing_to_central_extra_field_only: false,
             writing_raw: false,
+            typed_extra_field_length: 0,
             comment: Vec::new(),
+            codecs: CodecRegistry::new(),
+            compression_params: None,
+            #[cfg(feature = "zstd")]
+            zstd_dictionaries: ZstdDictionaries::new(),
+            auto_compression: None,
+            auto_sample: None,
+            method_changed: false,
+            reproducible: None,
         }
     }
 
@@ -211,6 +281,27 @@ impl<W: Write + Seek> ZipWriter<W> {
         self.comment = comment;
     }
 
+    /// Set the codecs used to compress files with methods that are not built in.
+    ///
+    /// See [`crate::codec`].
+    pub fn set_codecs(&mut self, codecs: CodecRegistry) {
+        self.codecs = codecs;
+    }
+
+    /// Set a policy choosing the compression method of files from their name and content.
+    ///
+    /// See [`AutoCompression`], `None` disables it. This applies to the files started afterwards.
+    pub fn set_auto_compression(&mut self, policy: Option<AutoCompression>) {
+        self.auto_compression = policy.map(Arc::new);
+    }
+
+    /// Make the archive reproducible, see [`Reproducible`].
+    ///
+    /// This applies to the entries added afterwards, `None` disables it.
+    pub fn set_reproducible(&mut self, reproducible: Option<Reproducible>) {
+        self.reproducible = reproducible;
+    }
+
     /// Start a new file for with the requested options.
     fn start_entry<S>(
         &mut self,
@@ -223,6 +314,15 @@ impl<W: Write + Seek> ZipWriter<W> {
     {
         self.finish_file()?;
 
+        let options = match &self.reproducible {
+            Some(reproducible) => reproducible.normalize(options),
+            None => options,
+        };
+        let local_extra_field = serialize_extra_fields(options.local_extra_fields)?;
+        let central_extra_field = serialize_extra_fields(options.central_extra_fields)?;
+
+        // Files of unknown size may need a ZIP64 extra field once they are written
+        let reserve_zip64 = raw_values.is_none() && options.large_file.is_none();
         let raw_values = raw_values.unwrap_or(ZipRawValues {
             crc32: 0,
             compressed_size: 0,
@@ -230,7 +330,7 @@ impl<W: Write + Seek> ZipWriter<W> {
         });
 
         {
-            let writer = self.inner.get_plain();
+            let writer = self.inner.get_plain()?;
             let header_start = writer.stream_position()?;
 
             let permissions = options.permissions.unwrap_or(0o100644);
@@ -238,7 +338,8 @@ impl<W: Write + Seek> ZipWriter<W> {
                 system: System::Unix,
                 version_made_by: APPNOTE_SPEC_VERSION,
                 encrypted: options.encrypt_with.is_some(),
-                using_data_descriptor: false,
+                // The CRC is unknown when the encryption header is written
+                using_data_descriptor: options.encrypt_with.is_some(),
                 compression_method: options.compression_method,
                 compression_level: options.compression_level,
                 last_modified_time: options.last_modified_time,
@@ -247,16 +348,22 @@ impl<W: Write + Seek> ZipWriter<W> {
                 uncompressed_size: raw_values.uncompressed_size,
                 file_name: name.into(),
                 file_name_raw: Vec::new(), // Never used for saving
-                extra_field: Vec::new(),
+                extra_field: local_extra_field,
                 file_comment: String::new(),
                 header_start,
                 data_start: AtomicU64::new(0),
                 central_header_start: 0,
                 external_attributes: permissions << 16,
-                large_file: options.large_file,
+                large_file: options.large_file.unwrap_or(false),
                 aes_mode: None,
+                codec_version_needed: self
+                    .codecs
+                    .custom(options.compression_method)
+                    .map(|codec| codec.version_needed()),
             };
-            write_local_file_header(writer, &file)?;
+            write_local_file_header(writer, &file, reserve_zip64)?;
+            file.extra_field = central_extra_field;
+            self.typed_extra_field_length = file.extra_field.len();
 
             let header_end = writer.stream_position()?;
             self.stats.start = header_end;
@@ -266,16 +373,14 @@ impl<W: Write + Seek> ZipWriter<W> {
             self.stats.hasher = Hasher::new();
 
             self.files.push(file);
+            self.compression_params = options.compression_params;
         }
-        if let Some(keys) = options.encrypt_with {
-            let mut zipwriter = crate::zipcrypto::ZipCryptoWriter {
-                writer: core::mem::replace(&mut self.inner, GenericZipWriter::Closed).unwrap(),
-                buffer: vec![],
-                keys,
-            };
-            let crypto_header = [0u8; 12];
-
-            zipwriter.write_all(&crypto_header)?;
+        if let Some(password) = options.encrypt_with {
+            let zipwriter = crate::zipcrypto::ZipCryptoWriter::new(
+                core::mem::replace(&mut self.inner, GenericZipWriter::Closed).unwrap()?,
+                crate::zipcrypto::ZipCryptoKeys::derive(password),
+                options.last_modified_time.timepart(),
+            )?;
             self.inner = GenericZipWriter::Storer(MaybeEncrypted::Encrypted(zipwriter));
         }
         Ok(())
@@ -286,17 +391,17 @@ impl<W: Write + Seek> ZipWriter<W> {
             // Implicitly calling [`ZipWriter::end_extra_data`] for empty files.
             self.end_extra_data()?;
         }
-        self.inner.switch_to(CompressionMethod::Stored, None)?;
-        match core::mem::replace(&mut self.inner, GenericZipWriter::Closed) {
-            GenericZipWriter::Storer(MaybeEncrypted::Encrypted(writer)) => {
-                let crc32 = self.stats.hasher.clone().finalize();
-                self.inner =
-                    GenericZipWriter::Storer(MaybeEncrypted::Unencrypted(writer.finish(crc32)?))
-            }
-            GenericZipWriter::Storer(w) => self.inner = GenericZipWriter::Storer(w),
-            _ => unreachable!(),
-        }
-        let writer = self.inner.get_plain();
+        self.choose_compression()?;
+        self.inner.switch_to(
+            CompressionMethod::Stored,
+            None,
+            None,
+            &self.codecs,
+            #[cfg(feature = "zstd")]
+            &self.zstd_dictionaries,
+        )?;
+        self.inner.finish_encryption()?;
+        let writer = self.inner.get_plain()?;
 
         if !self.writing_raw {
             let file = match self.files.last_mut() {
@@ -309,15 +414,122 @@ impl<W: Write + Seek> ZipWriter<W> {
             let file_end = writer.stream_position()?;
             file.compressed_size = file_end - self.stats.start;
 
-            update_local_file_header(writer, file)?;
-            writer.seek(SeekFrom::Start(file_end))?;
+            // The local header has no room for ZIP64 sizes, so they are moved to a data descriptor.
+            // Check compressed size as well as it can also be slightly larger than uncompressed size.
+            let promote = !file.large_file
+                && file.compressed_size.max(file.uncompressed_size) > spec::ZIP64_BYTES_THR;
+            if promote {
+                file.large_file = true;
+                file.using_data_descriptor = true;
+            }
+
+            if file.using_data_descriptor {
+                write_data_descriptor(writer, file)?;
+            }
+            let descriptor_end = writer.stream_position()?;
+
+            if self.method_changed {
+                update_local_compression_method(writer, file)?;
+            }
+            if promote {
+                promote_local_file_header(writer, file)?;
+            } else {
+                update_local_file_header(writer, file)?;
+            }
+            writer.seek(SeekFrom::Start(descriptor_end))?;
         }
 
         self.writing_to_file = false;
         self.writing_raw = false;
+        self.method_changed = false;
+        Ok(())
+    }
+
+    /// Buffer the start of a file until its compression method is chosen
+    fn write_sample(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let Some(AutoSample { data, policy }) = &mut self.auto_sample else {
+            return Err(io::Error::other("No sample is being collected"));
+        };
+        let count = buf.len().min(policy.sample_len() - data.len());
+        data.extend_from_slice(&buf[..count]);
+        self.stats.update(&buf[..count]);
+        if data.len() == policy.sample_len() {
+            self.choose_compression()?;
+        }
+        Ok(count)
+    }
+
+    /// Choose the compression method of the current file from its sample, and write the sample
+    fn choose_compression(&mut self) -> ZipResult<()> {
+        let Some(AutoSample {
+            data: sample,
+            policy,
+        }) = self.auto_sample.take()
+        else {
+            return Ok(());
+        };
+        let file = self
+            .files
+            .last()
+            .ok_or(ZipError::InvalidArchive("No file has been started"))?;
+        let (preferred, level, params) = (
+            file.compression_method,
+            file.compression_level,
+            self.compression_params,
+        );
+        let method = policy.choose(&sample, preferred, |method| {
+            let (level, params) = match method == preferred {
+                true => (level, params),
+                false => (None, None),
+            };
+            generic_writer::compressed_size(
+                &sample,
+                method,
+                level,
+                params,
+                &self.codecs,
+                #[cfg(feature = "zstd")]
+                &self.zstd_dictionaries,
+            )
+        })?;
+        self.switch_file_method(method)?;
+        self.inner
+            .ref_mut()
+            .ok_or(ZipError::Io(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "ZipWriter was already closed",
+            )))?
+            .write_all(&sample)?;
         Ok(())
     }
 
+    /// Start compressing the current file with `method`, which replaces the method of its options
+    /// if it differs
+    fn switch_file_method(&mut self, method: CompressionMethod) -> ZipResult<()> {
+        let file = self
+            .files
+            .last_mut()
+            .ok_or(ZipError::InvalidArchive("No file has been started"))?;
+        if method != file.compression_method {
+            file.compression_method = method;
+            file.compression_level = None;
+            file.codec_version_needed = self
+                .codecs
+                .custom(method)
+                .map(|codec| codec.version_needed());
+            self.compression_params = None;
+            self.method_changed = true;
+        }
+        self.inner.switch_to(
+            method,
+            file.compression_level,
+            self.compression_params,
+            &self.codecs,
+            #[cfg(feature = "zstd")]
+            &self.zstd_dictionaries,
+        )
+    }
+
     /// Create a file in the archive and start writing its' contents.
     ///
     /// Use [`ZipWriter::start_file_from_path`] if you want the path to be normalized first.
@@ -333,10 +545,24 @@ impl<W: Write + Seek> ZipWriter<W> {
         }
         *options.permissions.as_mut().unwrap() |= 0o100000;
         self.start_entry(name, options, None)?;
-        self.inner
-            .switch_to(options.compression_method, options.compression_level)?;
         self.writing_to_file = true;
-        Ok(())
+        match &self.auto_compression {
+            Some(policy) if options.compression_method != CompressionMethod::Stored => {
+                let name = &self.files.last().unwrap().file_name;
+                match policy.method_for_name(name) {
+                    Some(method) => self.switch_file_method(method),
+                    None => {
+                        // Stay stored until the sample is complete
+                        self.auto_sample = Some(AutoSample {
+                            data: Vec::new(),
+                            policy: policy.clone(),
+                        });
+                        Ok(())
+                    }
+                }
+            }
+            _ => self.switch_file_method(options.compression_method),
+        }
     }
 
     /// Create a file in the archive and start writing its' contents.
@@ -392,6 +618,8 @@ impl<W: Write + Seek> ZipWriter<W> {
     /// Returns the preliminary starting offset of the file data without any extra data allowing to
     /// align the file data by calculating a pad length to be prepended as part of the extra data.
     ///
+    /// The fields known to this crate are easier to add with [`FileOptions::extra_fields`].
+    ///
     /// The data should be written using the [`io::Write`] implementation on this [`ZipWriter`]
     ///
     /// ```
@@ -424,7 +652,7 @@ impl<W: Write + Seek> ZipWriter<W> {
     ///     let data_start = data_start as usize + 4 + extra_data.len() + 4;
     ///     let align = 64;
     ///     let pad_length = (align - data_start % align) % align;
-    ///     assert_eq!(pad_length, 19);
+    ///     assert_eq!(pad_length, 43);
     ///     zip.write_u16::<LittleEndian>(0xdead)?;
     ///     zip.write_u16::<LittleEndian>(pad_length as u16)?;
     ///     zip.write_all(&vec![0; pad_length])?;
@@ -454,6 +682,12 @@ impl<W: Write + Seek> ZipWriter<W> {
     where
         S: Into<String>,
     {
+        if options.encrypt_with.is_some() {
+            // The encryption header is written right after the local file header
+            return Err(ZipError::UnsupportedArchive(
+                "Extra data can't be added to encrypted files",
+            ));
+        }
         if options.permissions.is_none() {
             options.permissions = Some(0o644);
         }
@@ -469,7 +703,11 @@ impl<W: Write + Seek> ZipWriter<W> {
     /// Returns the final starting offset of the file data.
     pub fn end_local_start_central_extra_data(&mut self) -> ZipResult<u64> {
         let data_start = self.end_extra_data()?;
-        self.files.last_mut().unwrap().extra_field.clear();
+        self.files
+            .last_mut()
+            .unwrap()
+            .extra_field
+            .truncate(self.typed_extra_field_length);
         self.writing_to_extra_field = true;
         self.writing_to_central_extra_field_only = true;
         Ok(data_start)
@@ -484,31 +722,45 @@ impl<W: Write + Seek> ZipWriter<W> {
             return Err(ZipError::Io(io::Error::other("Not writing to extra field")));
         }
         let file = self.files.last_mut().unwrap();
+        let extra_data = &file.extra_field[self.typed_extra_field_length..];
 
-        validate_extra_data(file)?;
+        validate_extra_data(extra_data)?;
 
         let data_start = file.data_start.get_mut();
 
         if !self.writing_to_central_extra_field_only {
-            let writer = self.inner.get_plain();
+            let writer = self.inner.get_plain()?;
 
             // Append extra data to local file header and keep it for central file header.
-            writer.write_all(&file.extra_field)?;
+            writer.write_all(extra_data)?;
 
             // Update final `data_start`.
-            let header_end = *data_start + file.extra_field.len() as u64;
+            let header_end = *data_start + extra_data.len() as u64;
             self.stats.start = header_end;
             *data_start = header_end;
 
-            // Update extra field length in local file header.
+            // Update extra field length in local file header, which also covers the ZIP64 and
+            // typed extra fields written along with the header.
             let extra_field_length =
-                if file.large_file { 20 } else { 0 } + file.extra_field.len() as u16;
+                u16::try_from(header_end - file.header_start - 30 - file.file_name.len() as u64)
+                    .map_err(|_| {
+                        ZipError::Io(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "Extra data exceeds extra field",
+                        ))
+                    })?;
             writer.seek(SeekFrom::Start(file.header_start + 28))?;
             writer.write_u16::<LittleEndian>(extra_field_length)?;
             writer.seek(SeekFrom::Start(header_end))?;
 
-            self.inner
-                .switch_to(file.compression_method, file.compression_level)?;
+            self.inner.switch_to(
+                file.compression_method,
+                file.compression_level,
+                self.compression_params,
+                &self.codecs,
+                #[cfg(feature = "zstd")]
+                &self.zstd_dictionaries,
+            )?;
         }
 
         self.writing_to_extra_field = false;
@@ -611,6 +863,7 @@ impl<W: Write + Seek> ZipWriter<W> {
         }
         *options.permissions.as_mut().unwrap() |= 0o40000;
         options.compression_method = CompressionMethod::Stored;
+        options.large_file.get_or_insert(false);
 
         let name_as_string = name.into();
         // Append a slash to the filename if it does not end with it.
@@ -646,7 +899,36 @@ impl<W: Write + Seek> ZipWriter<W> {
     pub fn finish(&mut self) -> ZipResult<W> {
         self.finalize()?;
         let inner = mem::replace(&mut self.inner, GenericZipWriter::Closed);
-        Ok(inner.unwrap())
+        inner.unwrap()
+    }
+
+    /// Add a zstd dictionary, which files can then be compressed with
+    ///
+    /// The dictionary is stored as an uncompressed entry, see [`crate::zstd_dictionary`]. Adding
+    /// it again has no effect, and neither has adding a dictionary that is already stored in an
+    /// archive opened with [`ZipWriter::new_append`].
+    #[cfg(feature = "zstd")]
+    pub fn add_zstd_dictionary(&mut self, dictionary: ZstdDictionary) -> ZipResult<()> {
+        if let Some(added) = self.zstd_dictionaries.get(&dictionary.id()) {
+            if *added != dictionary {
+                return Err(ZipError::UnsupportedArchive(
+                    "A different zstd dictionary with the same id was added",
+                ));
+            }
+            return Ok(());
+        }
+
+        let name = dictionary.entry_name();
+        if !self.files.iter().any(|file| file.file_name == name) {
+            let options = FileOptions::default()
+                .compression_method(CompressionMethod::Stored)
+                .unix_permissions(0o644);
+            self.start_file(name, options)?;
+            self.write_all(dictionary.as_bytes())?;
+            self.writing_to_file = false;
+        }
+        self.zstd_dictionaries.insert(dictionary.id(), dictionary);
+        Ok(())
     }
 
     /// Add a symlink entry.
@@ -678,6 +960,7 @@ impl<W: Write + Seek> ZipWriter<W> {
         // The symlink target is stored as file content. And compressing the target path
         // likely wastes space. So always store.
         options.compression_method = CompressionMethod::Stored;
+        options.large_file.get_or_insert(false);
 
         self.start_entry(name, options, None)?;
         self.writing_to_file = true;
@@ -687,11 +970,135 @@ impl<W: Write + Seek> ZipWriter<W> {
         Ok(())
     }
 
+    /// Recursively add the contents of a directory to the archive.
+    ///
+    /// Every entry below `src_dir` is stored under `prefix`, followed by its path relative to
+    /// `src_dir`. `src_dir` itself is not added. Entries are added in sorted order, so the same
+    /// directory always produces the same archive.
+    ///
+    /// Files, directories and symlinks are added with [`ZipWriter::start_file`],
+    /// [`ZipWriter::add_directory`] and [`ZipWriter::add_symlink`]. Symlinks are never followed.
+    /// The modification time and, on unix, the permissions of each entry are taken from the file
+    /// system and override the ones in `options`.
+    ///
+    /// ```no_run
+    /// # fn main() -> ziperu::result::ZipResult<()> {
+    /// use ziperu::{write::FileOptions, ZipWriter};
+    ///
+    /// let mut zip = ZipWriter::new(std::fs::File::create("assets.zip")?);
+    /// zip.add_dir_all("assets", "static", FileOptions::default())?;
+    /// zip.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn add_dir_all<P: AsRef<Path>>(
+        &mut self,
+        src_dir: P,
+        prefix: &str,
+        options: FileOptions,
+    ) -> ZipResult<()> {
+        self.add_dir_all_filtered(src_dir, prefix, options, |_| true)
+    }
+
+    /// Recursively add the contents of a directory to the archive, skipping entries rejected by
+    /// `filter`.
+    ///
+    /// `filter` is called with the path of each entry relative to `src_dir`. If it returns
+    /// `false` for a directory, the directory and everything below it is skipped. See
+    /// [`ZipWriter::add_dir_all`] for how entries are added.
+    ///
+    /// ```no_run
+    /// # fn main() -> ziperu::result::ZipResult<()> {
+    /// use ziperu::{write::FileOptions, ZipWriter};
+    ///
+    /// let mut zip = ZipWriter::new(std::fs::File::create("src.zip")?);
+    /// zip.add_dir_all_filtered("project", "", FileOptions::default(), |path| {
+    ///     !path.starts_with("target") && path.extension().is_none_or(|ext| ext != "tmp")
+    /// })?;
+    /// zip.finish()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn add_dir_all_filtered<P, F>(
+        &mut self,
+        src_dir: P,
+        prefix: &str,
+        options: FileOptions,
+        mut filter: F,
+    ) -> ZipResult<()>
+    where
+        P: AsRef<Path>,
+        F: FnMut(&Path) -> bool,
+    {
+        let src_dir = src_dir.as_ref();
+        let prefix = path_to_string(prefix);
+        let mut pending = vec![src_dir.to_path_buf()];
+
+        while let Some(dir) = pending.pop() {
+            let mut entries = std::fs::read_dir(&dir)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<io::Result<Vec<_>>>()?;
+            entries.sort();
+
+            let mut subdirs = Vec::new();
+            for path in entries {
+                let relative = path
+                    .strip_prefix(src_dir)
+                    .expect("read_dir only yields paths below src_dir");
+                if !filter(relative) {
+                    continue;
+                }
+
+                let name = match (prefix.is_empty(), path_to_string(relative)) {
+                    (true, relative) => relative.into_string(),
+                    (false, relative) => format!("{prefix}/{relative}"),
+                };
+                let metadata = std::fs::symlink_metadata(&path)?;
+                let mut options = options;
+                if let Ok(modified) = metadata.modified() {
+                    if let Ok(modified) = modified.try_into() {
+                        options = options.last_modified_time(modified);
+                    }
+                }
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    options = options.unix_permissions(metadata.permissions().mode());
+                }
+
+                if metadata.is_symlink() {
+                    let target = std::fs::read_link(&path)?;
+                    self.add_symlink(name, target.to_string_lossy(), options)?;
+                } else if metadata.is_dir() {
+                    self.add_directory(name, options)?;
+                    subdirs.push(path);
+                } else {
+                    let options = options.large_file(metadata.len() > spec::ZIP64_BYTES_THR);
+                    self.start_file(name, options)?;
+                    io::copy(&mut std::fs::File::open(&path)?, self)?;
+                }
+            }
+
+            // Visit the subdirectories in sorted order after the entries of this directory
+            pending.extend(subdirs.into_iter().rev());
+        }
+
+        Ok(())
+    }
+
     fn finalize(&mut self) -> ZipResult<()> {
         self.finish_file()?;
 
+        if self
+            .reproducible
+            .is_some_and(|reproducible| reproducible.sorts_entries())
+        {
+            self.files
+                .sort_by(|a, b| a.file_name.as_bytes().cmp(b.file_name.as_bytes()));
+        }
+
         {
-            let writer = self.inner.get_plain();
+            let writer = self.inner.get_plain()?;
 
             let central_start = writer.stream_position()?;
             for file in self.files.iter() {
@@ -702,11 +1109,13 @@ impl<W: Write + Seek> ZipWriter<W> {
             if self.files.len() > spec::ZIP64_ENTRY_THR
                 || central_size.max(central_start) > spec::ZIP64_BYTES_THR
             {
+                // NTFS on windows, unless the archive has to be reproducible, and unix otherwise
+                let host: u16 = match cfg!(windows) && self.reproducible.is_none() {
+                    true => 10,
+                    false => 3,
+                };
                 let zip64_footer = spec::Zip64CentralDirectoryEnd {
-                    #[cfg(not(windows))]
-                    version_made_by: (3 << 8) | APPNOTE_SPEC_VERSION as u16,
-                    #[cfg(windows)]
-                    version_made_by: (10 << 8) | APPNOTE_SPEC_VERSION as u16,
+                    version_made_by: (host << 8) | APPNOTE_SPEC_VERSION as u16,
                     version_needed_to_extract: DEFAULT_MINIMUM_ZIP_SPECIFICATION_VERSION,
                     disk_number: 0,
                     disk_with_central_directory: 0,
@@ -755,18 +1164,32 @@ impl<W: Write + Seek> Drop for ZipWriter<W> {
     }
 }
 
-fn write_local_file_header<T: Write>(writer: &mut T, file: &ZipFileData) -> ZipResult<()> {
+fn general_purpose_flag(file: &ZipFileData) -> u16 {
+    let utf8 = if !file.file_name.is_ascii() {
+        1 << 11
+    } else {
+        0
+    };
+    let encrypted = if file.encrypted { 1 << 0 } else { 0 };
+    let data_descriptor = if file.using_data_descriptor {
+        1 << 3
+    } else {
+        0
+    };
+    utf8 | encrypted | data_descriptor
+}
+
+fn write_local_file_header<T: Write>(
+    writer: &mut T,
+    file: &ZipFileData,
+    reserve_zip64: bool,
+) -> ZipResult<()> {
     // local file header signature
     writer.write_u32::<LittleEndian>(spec::LOCAL_FILE_HEADER_SIGNATURE)?;
     // version needed to extract
     writer.write_u16::<LittleEndian>(file.version_needed())?;
     // general purpose bit flag
-    let flag = if !file.file_name.is_ascii() {
-        1u16 << 11
-    } else {
-        0
-    } | if file.encrypted { 1u16 << 0 } else { 0 };
-    writer.write_u16::<LittleEndian>(flag)?;
+    writer.write_u16::<LittleEndian>(general_purpose_flag(file))?;
     // Compression method
     writer.write_u16::<LittleEndian>(file.compression_method.to_u16())?;
     // last mod file time and last mod file date
@@ -785,15 +1208,52 @@ fn write_local_file_header<T: Write>(writer: &mut T, file: &ZipFileData) -> ZipR
     // file name length
     writer.write_u16::<LittleEndian>(file.file_name.len() as u16)?;
     // extra field length
-    let extra_field_length = if file.large_file { 20 } else { 0 } + file.extra_field.len() as u16;
+    let zip64_extra_field_length = if file.large_file || reserve_zip64 {
+        20
+    } else {
+        0
+    };
+    let extra_field_length = zip64_extra_field_length + file.extra_field.len() as u16;
     writer.write_u16::<LittleEndian>(extra_field_length)?;
     // file name
     writer.write_all(file.file_name.as_bytes())?;
     // zip64 extra field
     if file.large_file {
         write_local_zip64_extra_field(writer, file)?;
+    } else if reserve_zip64 {
+        write_local_zip64_placeholder(writer)?;
+    }
+    // extra field
+    writer.write_all(&file.extra_field)?;
+
+    Ok(())
+}
+
+fn write_data_descriptor<T: Write>(writer: &mut T, file: &ZipFileData) -> ZipResult<()> {
+    writer.write_u32::<LittleEndian>(spec::DATA_DESCRIPTOR_SIGNATURE)?;
+    writer.write_u32::<LittleEndian>(file.crc32)?;
+    if file.large_file {
+        writer.write_u64::<LittleEndian>(file.compressed_size)?;
+        writer.write_u64::<LittleEndian>(file.uncompressed_size)?;
+    } else {
+        writer.write_u32::<LittleEndian>(file.compressed_size.min(spec::ZIP64_BYTES_THR) as u32)?;
+        writer
+            .write_u32::<LittleEndian>(file.uncompressed_size.min(spec::ZIP64_BYTES_THR) as u32)?;
     }
+    Ok(())
+}
 
+/// Rewrite the version needed and compression method of a local header after the compression
+/// method was chosen automatically.
+fn update_local_compression_method<T: Write + Seek>(
+    writer: &mut T,
+    file: &ZipFileData,
+) -> ZipResult<()> {
+    const VERSION_NEEDED_OFFSET: u64 = 4;
+    writer.seek(SeekFrom::Start(file.header_start + VERSION_NEEDED_OFFSET))?;
+    writer.write_u16::<LittleEndian>(file.version_needed())?;
+    writer.write_u16::<LittleEndian>(general_purpose_flag(file))?;
+    writer.write_u16::<LittleEndian>(file.compression_method.to_u16())?;
     Ok(())
 }
 
@@ -804,19 +1264,37 @@ fn update_local_file_header<T: Write + Seek>(writer: &mut T, file: &ZipFileData)
     if file.large_file {
         update_local_zip64_extra_field(writer, file)?;
     } else {
-        // check compressed size as well as it can also be slightly larger than uncompressed size
-        if file.compressed_size > spec::ZIP64_BYTES_THR {
-            return Err(ZipError::Io(io::Error::other(
-                "Large file option has not been set",
-            )));
-        }
         writer.write_u32::<LittleEndian>(file.compressed_size as u32)?;
-        // uncompressed size is already checked on write to catch it as soon as possible
         writer.write_u32::<LittleEndian>(file.uncompressed_size as u32)?;
     }
     Ok(())
 }
 
+/// Mark a local file header written with a ZIP64 placeholder to use a ZIP64 data descriptor.
+///
+/// The placeholder is replaced with a ZIP64 extra field, which tells readers that the sizes in the
+/// data descriptor are 8 bytes long. As required when the data descriptor flag is set, the CRC and
+/// the sizes in the extra field are zeroed.
+fn promote_local_file_header<T: Write + Seek>(writer: &mut T, file: &ZipFileData) -> ZipResult<()> {
+    const VERSION_NEEDED_OFFSET: u64 = 4;
+    writer.seek(SeekFrom::Start(file.header_start + VERSION_NEEDED_OFFSET))?;
+    writer.write_u16::<LittleEndian>(file.version_needed())?;
+    writer.write_u16::<LittleEndian>(general_purpose_flag(file))?;
+    const CRC32_OFFSET: u64 = 14;
+    writer.seek(SeekFrom::Start(file.header_start + CRC32_OFFSET))?;
+    writer.write_u32::<LittleEndian>(0)?;
+    // The sizes are in the ZIP64 extra field, as readers expect when it is present
+    writer.write_u32::<LittleEndian>(spec::ZIP64_BYTES_THR as u32)?;
+    writer.write_u32::<LittleEndian>(spec::ZIP64_BYTES_THR as u32)?;
+    let zip64_extra_field = file.header_start + 30 + file.file_name.len() as u64;
+    writer.seek(SeekFrom::Start(zip64_extra_field))?;
+    writer.write_u16::<LittleEndian>(0x0001)?;
+    writer.write_u16::<LittleEndian>(16)?;
+    writer.write_u64::<LittleEndian>(0)?;
+    writer.write_u64::<LittleEndian>(0)?;
+    Ok(())
+}
+
 fn write_central_directory_header<T: Write>(writer: &mut T, file: &ZipFileData) -> ZipResult<()> {
     // buffer zip64 extra field to determine its variable length
     let mut zip64_extra_field = [0; 28];
@@ -831,12 +1309,7 @@ fn write_central_directory_header<T: Write>(writer: &mut T, file: &ZipFileData)
     // version needed to extract
     writer.write_u16::<LittleEndian>(file.version_needed())?;
     // general puprose bit flag
-    let flag = if !file.file_name.is_ascii() {
-        1u16 << 11
-    } else {
-        0
-    } | if file.encrypted { 1u16 << 0 } else { 0 };
-    writer.write_u16::<LittleEndian>(flag)?;
+    writer.write_u16::<LittleEndian>(general_purpose_flag(file))?;
     // compression method
     writer.write_u16::<LittleEndian>(file.compression_method.to_u16())?;
     // last mod file time + date
@@ -874,9 +1347,34 @@ fn write_central_directory_header<T: Write>(writer: &mut T, file: &ZipFileData)
     Ok(())
 }
 
-fn validate_extra_data(file: &ZipFileData) -> ZipResult<()> {
-    let mut data = file.extra_field.as_slice();
+fn serialize_extra_fields(fields: &[ExtraField]) -> ZipResult<Vec<u8>> {
+    for field in fields {
+        match field {
+            ExtraField::Zip64(_) | ExtraField::Aes { .. } => {
+                return Err(ZipError::Io(io::Error::other(format!(
+                    "Extra data header ID {:#06x} is managed by the writer",
+                    field.header_id()
+                ))));
+            }
+            ExtraField::Unknown { .. } => {
+                validate_extra_data(&extra_field::serialize(std::slice::from_ref(field))?)?
+            }
+            _ => {}
+        }
+    }
+
+    let data = extra_field::serialize(fields)?;
+    // Leave room for the ZIP64 extra field, which may be added to the same header
+    if data.len() > spec::ZIP64_ENTRY_THR - 28 {
+        return Err(ZipError::Io(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Extra data exceeds extra field",
+        )));
+    }
+    Ok(data)
+}
 
+fn validate_extra_data(mut data: &[u8]) -> ZipResult<()> {
     if data.len() > spec::ZIP64_ENTRY_THR {
         return Err(ZipError::Io(io::Error::new(
             io::ErrorKind::InvalidData,
@@ -934,6 +1432,17 @@ fn write_local_zip64_extra_field<T: Write>(writer: &mut T, file: &ZipFileData) -
     Ok(())
 }
 
+/// Reserve room for a ZIP64 extra field in a local header, in case the file grows past 4 GiB
+///
+/// The placeholder is an alignment field to a 1 byte boundary, which readers skip.
+fn write_local_zip64_placeholder<T: Write>(writer: &mut T) -> ZipResult<()> {
+    writer.write_u16::<LittleEndian>(ALIGNMENT_EXTRA_FIELD_ID)?;
+    writer.write_u16::<LittleEndian>(16)?;
+    writer.write_u16::<LittleEndian>(1)?;
+    writer.write_all(&[0; 14])?;
+    Ok(())
+}
+
 fn update_local_zip64_extra_field<T: Write + Seek>(
     writer: &mut T,
     file: &ZipFileData,
@@ -1006,6 +1515,15 @@ fn path_to_string<P: AsRef<Path>>(path: P) -> Box<str> {
     normalized_path.join("/").into_boxed_str()
 }
 
+#[cfg(not(feature = "unreserved"))]
+const EXTRA_FIELD_MAPPING: [u16; 49] = [
+    0x0001, 0x0007, 0x0008, 0x0009, 0x000a, 0x000c, 0x000d, 0x000e, 0x000f, 0x0014, 0x0015, 0x0016,
+    0x0017, 0x0018, 0x0019, 0x0020, 0x0021, 0x0022, 0x0023, 0x0065, 0x0066, 0x4690, 0x07c8, 0x2605,
+    0x2705, 0x2805, 0x334d, 0x4341, 0x4453, 0x4704, 0x470f, 0x4b46, 0x4c41, 0x4d49, 0x4f4c, 0x5356,
+    0x5455, 0x554e, 0x5855, 0x6375, 0x6542, 0x7075, 0x756e, 0x7855, 0xa11e, 0xa220, 0xfd4a, 0x9901,
+    0x9902,
+];
+
 #[cfg(test)]
 mod test {
     use super::{FileOptions, ZipWriter};
@@ -1136,10 +1654,13 @@ mod test {
         let options = FileOptions {
             compression_method: CompressionMethod::Stored,
             compression_level: None,
+            compression_params: None,
             last_modified_time: DateTime::default(),
             permissions: Some(33188),
-            large_file: false,
+            large_file: Some(false),
             encrypt_with: None,
+            local_extra_fields: &[],
+            central_extra_fields: &[],
         };
         writer.start_file("mimetype", options).unwrap();
         writer
@@ -1167,12 +1688,3 @@ mod test {
         assert_eq!(super::path_to_string(&path), "system32".into());
     }
 }
-
-#[cfg(not(feature = "unreserved"))]
-const EXTRA_FIELD_MAPPING: [u16; 49] = [
-    0x0001, 0x0007, 0x0008, 0x0009, 0x000a, 0x000c, 0x000d, 0x000e, 0x000f, 0x0014, 0x0015, 0x0016,
-    0x0017, 0x0018, 0x0019, 0x0020, 0x0021, 0x0022, 0x0023, 0x0065, 0x0066, 0x4690, 0x07c8, 0x2605,
-    0x2705, 0x2805, 0x334d, 0x4341, 0x4453, 0x4704, 0x470f, 0x4b46, 0x4c41, 0x4d49, 0x4f4c, 0x5356,
-    0x5455, 0x554e, 0x5855, 0x6375, 0x6542, 0x7075, 0x756e, 0x7855, 0xa11e, 0xa220, 0xfd4a, 0x9901,
-    0x9902,
-];
@@ -0,0 +1,228 @@
+use crate::compression::CompressionMethod;
+use crate::result::ZipResult;
+use std::collections::HashMap;
+
+/// Extensions of formats that are already compressed
+const COMPRESSED_EXTENSIONS: &[&str] = &[
+    "7z", "apk", "avif", "bz2", "docx", "flac", "gif", "gz", "heic", "jar", "jpeg", "jpg", "m4a",
+    "mkv", "mov", "mp3", "mp4", "odt", "ogg", "png", "pptx", "rar", "tgz", "webm", "webp", "woff2",
+    "xlsx", "xz", "zip", "zst",
+];
+
+/// Magic numbers of formats that are already compressed, with their MIME type
+const MAGIC_NUMBERS: &[(usize, &[u8], &str)] = &[
+    (0, b"\xff\xd8\xff", "image/jpeg"),
+    (0, b"\x89PNG\r\n\x1a\n", "image/png"),
+    (0, b"GIF87a", "image/gif"),
+    (0, b"GIF89a", "image/gif"),
+    (8, b"WEBP", "image/webp"),
+    (4, b"ftyp", "video/mp4"),
+    (0, b"ID3", "audio/mpeg"),
+    (0, b"OggS", "audio/ogg"),
+    (0, b"fLaC", "audio/flac"),
+    (0, b"PK\x03\x04", "application/zip"),
+    (0, b"\x1f\x8b", "application/gzip"),
+    (0, b"BZh", "application/x-bzip2"),
+    (0, b"\xfd7zXZ\x00", "application/x-xz"),
+    (0, b"\x28\xb5\x2f\xfd", "application/zstd"),
+    (0, b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+    (0, b"Rar!\x1a\x07", "application/vnd.rar"),
+];
+
+/// A policy choosing the compression method of each file from its name and content
+///
+/// Set with [`crate::ZipWriter::set_auto_compression`]. It applies to files started with
+/// [`crate::ZipWriter::start_file`] whose compression method is not
+/// [`CompressionMethod::Stored`]. The method of the [`crate::write::FileOptions`] is the preferred
+/// one, other candidates can be added with [`AutoCompression::candidate`].
+///
+/// For each file, the policy looks at:
+/// 1. The extension of the name. A rule for it, see [`AutoCompression::extension`], picks the
+///    method without looking at the content.
+/// 2. The MIME type of the content, detected from the magic numbers of common formats, see
+///    [`AutoCompression::mime_type`].
+/// 3. A sample of the content, which is compressed with every candidate. The file is stored
+///    uncompressed unless the best candidate compresses the sample to at most
+///    [`AutoCompression::max_ratio`] of its size. Otherwise it uses the best candidate.
+///
+/// The sample is the start of the file, and the whole file if it fits. [`AutoCompression::new`]
+/// starts with rules storing well-known compressed formats such as JPEG, PNG and ZIP.
+///
+/// The compression level and parameters of the options are only used if the preferred method is
+/// chosen, the other candidates use their defaults.
+#[derive(Clone, Debug)]
+pub struct AutoCompression {
+    candidates: Vec<CompressionMethod>,
+    sample_size: usize,
+    max_ratio: f64,
+    extensions: HashMap<String, CompressionMethod>,
+    mime_types: HashMap<String, CompressionMethod>,
+}
+
+impl AutoCompression {
+    /// Create a policy with a 64 KiB sample, a maximum ratio of 0.9 and rules storing well-known
+    /// compressed formats
+    pub fn new() -> AutoCompression {
+        let policy = AutoCompression {
+            candidates: Vec::new(),
+            sample_size: 64 * 1024,
+            max_ratio: 0.9,
+            extensions: HashMap::new(),
+            mime_types: HashMap::new(),
+        };
+        let policy = COMPRESSED_EXTENSIONS
+            .iter()
+            .fold(policy, |policy, extension| {
+                policy.extension(extension, CompressionMethod::Stored)
+            });
+        MAGIC_NUMBERS
+            .iter()
+            .fold(policy, |policy, (_, _, mime_type)| {
+                policy.mime_type(mime_type, CompressionMethod::Stored)
+            })
+    }
+
+    /// Also try `method` when compressing the sample
+    #[must_use]
+    pub fn candidate(mut self, method: CompressionMethod) -> AutoCompression {
+        if !self.candidates.contains(&method) {
+            self.candidates.push(method);
+        }
+        self
+    }
+
+    /// Set the number of bytes at the start of a file that are compressed to choose its method
+    ///
+    /// The sample is kept in memory until the method is chosen.
+    #[must_use]
+    pub fn sample_size(mut self, sample_size: usize) -> AutoCompression {
+        self.sample_size = sample_size.max(1);
+        self
+    }
+
+    /// Set the largest ratio of compressed to uncompressed sample size for which a file is
+    /// compressed
+    #[must_use]
+    pub fn max_ratio(mut self, max_ratio: f64) -> AutoCompression {
+        self.max_ratio = max_ratio;
+        self
+    }
+
+    /// Use `method` for files with this extension, which is matched case-insensitively
+    #[must_use]
+    pub fn extension(mut self, extension: &str, method: CompressionMethod) -> AutoCompression {
+        self.extensions
+            .insert(extension.to_ascii_lowercase(), method);
+        self
+    }
+
+    /// Use `method` for files whose content has this MIME type
+    ///
+    /// The MIME types that are detected are `image/jpeg`, `image/png`, `image/gif`, `image/webp`,
+    /// `video/mp4`, `audio/mpeg`, `audio/ogg`, `audio/flac`, `application/zip`, `application/gzip`,
+    /// `application/x-bzip2`, `application/x-xz`, `application/zstd`,
+    /// `application/x-7z-compressed` and `application/vnd.rar`.
+    #[must_use]
+    pub fn mime_type(mut self, mime_type: &str, method: CompressionMethod) -> AutoCompression {
+        self.mime_types.insert(mime_type.to_owned(), method);
+        self
+    }
+
+    /// Remove all extension and MIME type rules, including the built-in ones
+    #[must_use]
+    pub fn clear_rules(mut self) -> AutoCompression {
+        self.extensions.clear();
+        self.mime_types.clear();
+        self
+    }
+
+    pub(crate) fn sample_len(&self) -> usize {
+        self.sample_size
+    }
+
+    /// The method for a file name, if a rule covers its extension
+    pub(crate) fn method_for_name(&self, name: &str) -> Option<CompressionMethod> {
+        let file_name = name.rsplit('/').next()?;
+        let (_, extension) = file_name.rsplit_once('.')?;
+        self.extensions
+            .get(&extension.to_ascii_lowercase())
+            .copied()
+    }
+
+    /// Choose the method for a sample of a file, `compressed_size` compresses it with a method
+    pub(crate) fn choose(
+        &self,
+        sample: &[u8],
+        preferred: CompressionMethod,
+        mut compressed_size: impl FnMut(CompressionMethod) -> ZipResult<u64>,
+    ) -> ZipResult<CompressionMethod> {
+        if let Some(&method) = mime_type(sample).and_then(|mime| self.mime_types.get(mime)) {
+            return Ok(method);
+        }
+        if sample.is_empty() {
+            return Ok(CompressionMethod::Stored);
+        }
+
+        let mut best = (CompressionMethod::Stored, sample.len() as u64);
+        let candidates = std::iter::once(preferred).chain(self.candidates.iter().copied());
+        for method in candidates.filter(|&method| method != CompressionMethod::Stored) {
+            let size = compressed_size(method)?;
+            if size < best.1 {
+                best = (method, size);
+            }
+        }
+        match best.1 as f64 <= self.max_ratio * sample.len() as f64 {
+            true => Ok(best.0),
+            false => Ok(CompressionMethod::Stored),
+        }
+    }
+}
+
+impl Default for AutoCompression {
+    fn default() -> Self {
+        AutoCompression::new()
+    }
+}
+
+/// Detect the MIME type of compressed formats from their magic numbers
+fn mime_type(data: &[u8]) -> Option<&'static str> {
+    MAGIC_NUMBERS
+        .iter()
+        .find(|(offset, magic, _)| {
+            data.get(*offset..)
+                .is_some_and(|data| data.starts_with(magic))
+        })
+        .map(|(_, _, mime_type)| *mime_type)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn detect_mime_type() {
+        assert_eq!(
+            mime_type(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+            Some("image/png")
+        );
+        assert_eq!(mime_type(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+        assert_eq!(mime_type(b"\0\0\0\x20ftypisom"), Some("video/mp4"));
+        assert_eq!(mime_type(b"plain text"), None);
+        assert_eq!(mime_type(b""), None);
+    }
+
+    #[test]
+    fn extension_rules() {
+        let policy = AutoCompression::new().extension("TXT", CompressionMethod::BZIP2);
+        assert_eq!(
+            policy.method_for_name("photos/IMG_0001.JPG"),
+            Some(CompressionMethod::Stored)
+        );
+        assert_eq!(
+            policy.method_for_name("notes.txt"),
+            Some(CompressionMethod::BZIP2)
+        );
+        assert_eq!(policy.method_for_name("docs.d/README"), None);
+        assert_eq!(policy.clear_rules().method_for_name("image.png"), None);
+    }
+}
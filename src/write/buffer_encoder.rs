@@ -0,0 +1,156 @@
+//! A writer for compressors that work on buffers, used when compression parameters are needed
+//! that the writers of the compression crates don't support.
+
+use std::io::{self, Write};
+
+const BUFFER_SIZE: usize = 32 * 1024;
+
+/// A compressor working on buffers
+pub(crate) trait BufferCompressor: Send {
+    /// Compress `input`, appending to `output` without growing it.
+    ///
+    /// Returns the number of bytes consumed from `input` and whether the stream is finished.
+    fn compress(
+        &mut self,
+        input: &[u8],
+        output: &mut Vec<u8>,
+        finish: bool,
+    ) -> io::Result<(usize, bool)>;
+}
+
+pub(crate) struct BufferEncoder<W, C> {
+    inner: W,
+    compressor: C,
+    buffer: Vec<u8>,
+}
+
+impl<C: BufferCompressor + ?Sized> BufferCompressor for Box<C> {
+    fn compress(
+        &mut self,
+        input: &[u8],
+        output: &mut Vec<u8>,
+        finish: bool,
+    ) -> io::Result<(usize, bool)> {
+        (**self).compress(input, output, finish)
+    }
+}
+
+impl<W: Write, C: BufferCompressor> BufferEncoder<W, C> {
+    pub(crate) fn new(inner: W, compressor: C) -> Self {
+        BufferEncoder {
+            inner,
+            compressor,
+            buffer: Vec::with_capacity(BUFFER_SIZE),
+        }
+    }
+
+    /// Compress the remaining data and return the underlying writer
+    pub(crate) fn finish(mut self) -> io::Result<W> {
+        loop {
+            self.buffer.clear();
+            let (_, finished) = self.compressor.compress(&[], &mut self.buffer, true)?;
+            self.inner.write_all(&self.buffer)?;
+            if finished {
+                return Ok(self.inner);
+            }
+            if self.buffer.is_empty() {
+                return Err(io::Error::other("Compressor made no progress"));
+            }
+        }
+    }
+}
+
+impl<W: Write, C: BufferCompressor> Write for BufferEncoder<W, C> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            self.buffer.clear();
+            let (consumed, _) = self.compressor.compress(buf, &mut self.buffer, false)?;
+            self.inner.write_all(&self.buffer)?;
+            if consumed > 0 {
+                return Ok(consumed);
+            }
+            if self.buffer.is_empty() {
+                return Err(io::Error::other("Compressor made no progress"));
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(feature = "bzip2")]
+impl BufferCompressor for bzip2::Compress {
+    fn compress(
+        &mut self,
+        input: &[u8],
+        output: &mut Vec<u8>,
+        finish: bool,
+    ) -> io::Result<(usize, bool)> {
+        let action = match finish {
+            true => bzip2::Action::Finish,
+            false => bzip2::Action::Run,
+        };
+        let before = self.total_in();
+        let status = self
+            .compress_vec(input, output, action)
+            .map_err(io::Error::other)?;
+        Ok((
+            (self.total_in() - before) as usize,
+            status == bzip2::Status::StreamEnd,
+        ))
+    }
+}
+
+#[cfg(feature = "deflate-zlib")]
+impl BufferCompressor for flate2::Compress {
+    fn compress(
+        &mut self,
+        input: &[u8],
+        output: &mut Vec<u8>,
+        finish: bool,
+    ) -> io::Result<(usize, bool)> {
+        let flush = match finish {
+            true => flate2::FlushCompress::Finish,
+            false => flate2::FlushCompress::None,
+        };
+        let before = self.total_in();
+        let status = self
+            .compress_vec(input, output, flush)
+            .map_err(io::Error::other)?;
+        Ok((
+            (self.total_in() - before) as usize,
+            status == flate2::Status::StreamEnd,
+        ))
+    }
+}
+
+#[cfg(any(feature = "deflate", feature = "deflate-miniz"))]
+impl BufferCompressor for miniz_oxide::deflate::core::CompressorOxide {
+    fn compress(
+        &mut self,
+        input: &[u8],
+        output: &mut Vec<u8>,
+        finish: bool,
+    ) -> io::Result<(usize, bool)> {
+        let flush = match finish {
+            true => miniz_oxide::MZFlush::Finish,
+            false => miniz_oxide::MZFlush::None,
+        };
+        let len = output.len();
+        output.resize(output.capacity(), 0);
+        let result = miniz_oxide::deflate::stream::deflate(self, input, &mut output[len..], flush);
+        output.truncate(len + result.bytes_written);
+        let status = result
+            .status
+            .map_err(|err| io::Error::other(format!("Deflate compression failed: {err:?}")))?;
+        Ok((
+            result.bytes_consumed,
+            status == miniz_oxide::MZStatus::StreamEnd,
+        ))
+    }
+}
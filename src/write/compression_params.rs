@@ -0,0 +1,265 @@
+use crate::compression::CompressionMethod;
+#[cfg(feature = "xz")]
+use crate::result::{ZipError, ZipResult};
+
+/// Method specific compression parameters, see [`crate::write::FileOptions::compression_params`]
+///
+/// The variant has to match the compression method of the file, starting a file with parameters
+/// for another method fails.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CompressionParams {
+    /// Parameters for [`CompressionMethod::Deflated`]
+    #[cfg(any(
+        feature = "deflate",
+        feature = "deflate-miniz",
+        feature = "deflate-zlib"
+    ))]
+    Deflate(DeflateParams),
+    /// Parameters for [`CompressionMethod::Bzip2`]
+    #[cfg(feature = "bzip2")]
+    Bzip2(Bzip2Params),
+    /// Parameters for [`CompressionMethod::Zstd`]
+    #[cfg(feature = "zstd")]
+    Zstd(ZstdParams),
+    /// Parameters for [`CompressionMethod::Xz`]
+    #[cfg(feature = "xz")]
+    Xz(XzParams),
+}
+
+impl CompressionParams {
+    /// The compression method these parameters apply to
+    pub fn method(&self) -> CompressionMethod {
+        match *self {
+            #[cfg(any(
+                feature = "deflate",
+                feature = "deflate-miniz",
+                feature = "deflate-zlib"
+            ))]
+            CompressionParams::Deflate(_) => CompressionMethod::Deflated,
+            #[cfg(feature = "bzip2")]
+            CompressionParams::Bzip2(_) => CompressionMethod::Bzip2,
+            #[cfg(feature = "zstd")]
+            CompressionParams::Zstd(_) => CompressionMethod::Zstd,
+            #[cfg(feature = "xz")]
+            CompressionParams::Xz(_) => CompressionMethod::Xz,
+        }
+    }
+}
+
+/// The strategy used to find matches when compressing with deflate
+///
+/// Strategies other than [`DeflateStrategy::Default`] need the `deflate` or `deflate-miniz`
+/// feature.
+#[cfg(any(
+    feature = "deflate",
+    feature = "deflate-miniz",
+    feature = "deflate-zlib"
+))]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DeflateStrategy {
+    /// Look for matches normally
+    #[default]
+    Default,
+    /// Only use matches that are at least 5 bytes long, for data with small random variations
+    Filtered,
+    /// Don't look for matches, only Huffman encode the data
+    HuffmanOnly,
+    /// Only look for matches with a distance of 1, i.e. run-length encoding
+    Rle,
+    /// Only use the fixed Huffman codes
+    Fixed,
+}
+
+/// Parameters for deflate compression
+#[cfg(any(
+    feature = "deflate",
+    feature = "deflate-miniz",
+    feature = "deflate-zlib"
+))]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeflateParams {
+    pub(crate) strategy: DeflateStrategy,
+    pub(crate) window_bits: Option<u8>,
+}
+
+#[cfg(any(
+    feature = "deflate",
+    feature = "deflate-miniz",
+    feature = "deflate-zlib"
+))]
+impl DeflateParams {
+    /// Set the strategy used to find matches
+    #[must_use]
+    pub fn strategy(mut self, strategy: DeflateStrategy) -> DeflateParams {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Set the base-2 logarithm of the window size, from 9 to 15
+    ///
+    /// The default is 15. Smaller windows need less memory, but compress worse. This needs the
+    /// `deflate-zlib` feature and can't be combined with a strategy.
+    #[must_use]
+    pub fn window_bits(mut self, window_bits: u8) -> DeflateParams {
+        self.window_bits = Some(window_bits);
+        self
+    }
+}
+
+/// Parameters for bzip2 compression
+#[cfg(feature = "bzip2")]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Bzip2Params {
+    pub(crate) work_factor: Option<u32>,
+}
+
+#[cfg(feature = "bzip2")]
+impl Bzip2Params {
+    /// Set how hard to try before falling back to a slower sorting algorithm for repetitive
+    /// data, from 1 to 250
+    ///
+    /// The default is 30. The compressed data is the same for all work factors.
+    #[must_use]
+    pub fn work_factor(mut self, work_factor: u32) -> Bzip2Params {
+        self.work_factor = Some(work_factor);
+        self
+    }
+}
+
+/// Parameters for zstd compression
+#[cfg(feature = "zstd")]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ZstdParams {
+    pub(crate) window_log: Option<u32>,
+    pub(crate) long_distance_matching: bool,
+    pub(crate) checksum: bool,
+    pub(crate) workers: u32,
+    pub(crate) dictionary: Option<u32>,
+}
+
+#[cfg(feature = "zstd")]
+impl ZstdParams {
+    /// Set the base-2 logarithm of the window size
+    ///
+    /// The default depends on the compression level. Decoders may refuse windows larger than
+    /// 2^27 bytes unless configured otherwise, see [`crate::read::DecoderLimits`].
+    #[must_use]
+    pub fn window_log(mut self, window_log: u32) -> ZstdParams {
+        self.window_log = Some(window_log);
+        self
+    }
+
+    /// Enable long distance matching, which finds matches far back in large inputs
+    #[must_use]
+    pub fn long_distance_matching(mut self, enabled: bool) -> ZstdParams {
+        self.long_distance_matching = enabled;
+        self
+    }
+
+    /// Store a checksum in the zstd frame, in addition to the CRC of the ZIP entry
+    #[must_use]
+    pub fn checksum(mut self, enabled: bool) -> ZstdParams {
+        self.checksum = enabled;
+        self
+    }
+
+    /// Set the number of worker threads compressing in the background
+    ///
+    /// The default is 0, which compresses on the calling thread. Using workers needs zstd to be
+    /// built with multithreading, by enabling the `zstdmt` feature of the `zstd` crate.
+    #[must_use]
+    pub fn workers(mut self, workers: u32) -> ZstdParams {
+        self.workers = workers;
+        self
+    }
+
+    /// Compress with the dictionary with this id
+    ///
+    /// The dictionary has to be added to the archive with
+    /// [`crate::ZipWriter::add_zstd_dictionary`] before starting the file, see
+    /// [`crate::zstd_dictionary`].
+    #[must_use]
+    pub fn dictionary(mut self, id: u32) -> ZstdParams {
+        self.dictionary = Some(id);
+        self
+    }
+}
+
+/// A filter applied to the data before xz compresses it
+#[cfg(feature = "xz")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum XzFilter {
+    /// Store the difference between bytes that are `distance` bytes apart, from 1 to 256
+    Delta {
+        /// Distance of the bytes to subtract
+        distance: u32,
+    },
+    /// Convert x86 branch targets to absolute addresses
+    X86,
+    /// Convert ARM branch targets to absolute addresses
+    Arm,
+    /// Convert ARM Thumb branch targets to absolute addresses
+    ArmThumb,
+    /// Convert ARM64 branch targets to absolute addresses
+    Arm64,
+    /// Convert PowerPC branch targets to absolute addresses
+    PowerPc,
+    /// Convert SPARC branch targets to absolute addresses
+    Sparc,
+    /// Convert IA-64 branch targets to absolute addresses
+    Ia64,
+}
+
+/// The maximum number of filters applied before xz compression
+#[cfg(feature = "xz")]
+const MAX_XZ_FILTERS: usize = 3;
+
+/// Parameters for xz compression
+///
+/// The preset is set with [`crate::write::FileOptions::compression_level`].
+#[cfg(feature = "xz")]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct XzParams {
+    pub(crate) dict_size: Option<u32>,
+    filters: [Option<XzFilter>; MAX_XZ_FILTERS],
+}
+
+#[cfg(feature = "xz")]
+impl XzParams {
+    /// Set the dictionary size in bytes, overriding the one of the preset
+    #[must_use]
+    pub fn dict_size(mut self, dict_size: u32) -> XzParams {
+        self.dict_size = Some(dict_size);
+        self
+    }
+
+    /// Add a filter
+    ///
+    /// The filters are applied in the order they were added, before the data is compressed.
+    /// At most 3 filters can be used, adding more fails. Delta distances outside of 1 to 256
+    /// are rejected.
+    pub fn filter(mut self, filter: XzFilter) -> ZipResult<XzParams> {
+        if let XzFilter::Delta { distance } = filter {
+            if !(1..=256).contains(&distance) {
+                return Err(ZipError::UnsupportedArchive(
+                    "Unsupported xz delta distance",
+                ));
+            }
+        }
+        let slot = self
+            .filters
+            .iter_mut()
+            .find(|slot| slot.is_none())
+            .ok_or(ZipError::UnsupportedArchive("Too many xz filters"))?;
+        *slot = Some(filter);
+        Ok(self)
+    }
+
+    /// The filters in the order they were added
+    pub(crate) fn filters(&self) -> impl Iterator<Item = XzFilter> + '_ {
+        self.filters.iter().flatten().copied()
+    }
+}
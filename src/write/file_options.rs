@@ -1,27 +1,34 @@
+#[cfg(feature = "time")]
 use std::convert::TryInto;
 
-use crate::{DateTime, compression::CompressionMethod};
+use super::CompressionParams;
+use crate::{DateTime, compression::CompressionMethod, extra_field::ExtraField};
 #[cfg(feature = "time")]
 use time::OffsetDateTime;
 
 /// Metadata for a file to be written
 #[derive(Copy, Clone)]
-pub struct FileOptions {
+pub struct FileOptions<'k> {
     pub(super) compression_method: CompressionMethod,
     pub(super) compression_level: Option<i32>,
+    pub(super) compression_params: Option<CompressionParams>,
     pub(super) last_modified_time: DateTime,
     pub(super) permissions: Option<u32>,
-    pub(super) large_file: bool,
-    pub(super) encrypt_with: Option<crate::zipcrypto::ZipCryptoKeys>,
+    /// `None` if it is not known whether the file is large
+    pub(super) large_file: Option<bool>,
+    /// The password is borrowed, so that no key material is copied along with the options
+    pub(super) encrypt_with: Option<&'k [u8]>,
+    pub(super) local_extra_fields: &'k [ExtraField],
+    pub(super) central_extra_fields: &'k [ExtraField],
 }
 
-impl FileOptions {
+impl<'k> FileOptions<'k> {
     /// Set the compression method for the new file
     ///
     /// The default is `CompressionMethod::Deflated`. If the deflate compression feature is
     /// disabled, `CompressionMethod::Stored` becomes the default.
     #[must_use]
-    pub fn compression_method(mut self, method: CompressionMethod) -> FileOptions {
+    pub fn compression_method(mut self, method: CompressionMethod) -> FileOptions<'k> {
         self.compression_method = method;
         self
     }
@@ -36,17 +43,26 @@ impl FileOptions {
     /// * `Zstd`: -7 - 22, with zero being mapped to default level. Default is 3
     /// * others: only `None` is allowed
     #[must_use]
-    pub fn compression_level(mut self, level: Option<i32>) -> FileOptions {
+    pub fn compression_level(mut self, level: Option<i32>) -> FileOptions<'k> {
         self.compression_level = level;
         self
     }
 
+    /// Set parameters specific to the compression method, such as the zstd window size
+    ///
+    /// The parameters have to match the compression method, see [`CompressionParams`].
+    #[must_use]
+    pub fn compression_params(mut self, params: CompressionParams) -> FileOptions<'k> {
+        self.compression_params = Some(params);
+        self
+    }
+
     /// Set the last modified time
     ///
     /// The default is the current timestamp if the 'time' feature is enabled, and 1980-01-01
     /// otherwise
     #[must_use]
-    pub fn last_modified_time(mut self, mod_time: DateTime) -> FileOptions {
+    pub fn last_modified_time(mut self, mod_time: DateTime) -> FileOptions<'k> {
         self.last_modified_time = mod_time;
         self
     }
@@ -61,28 +77,58 @@ impl FileOptions {
     /// higher file mode bits. So it cannot be used to denote an entry as a directory,
     /// symlink, or other special file type.
     #[must_use]
-    pub fn unix_permissions(mut self, mode: u32) -> FileOptions {
+    pub fn unix_permissions(mut self, mode: u32) -> FileOptions<'k> {
         self.permissions = Some(mode & 0o777);
         self
     }
 
-    /// Set whether the new file's compressed and uncompressed size is less than 4 GiB.
+    /// Set whether the new file's compressed and uncompressed size may exceed 4 GiB.
+    ///
+    /// If set to `true`, the local file header gets a ZIP64 extra field, readers will require
+    /// ZIP64 support and if the file does not exceed the limit, 20 B are wasted.
+    ///
+    /// If not set, 20 B are reserved in the local file header as well. A file exceeding the limit
+    /// is then switched to ZIP64 when it is finished: the reserved space becomes a ZIP64 extra
+    /// field and the local file header is marked to use a data descriptor, which holds the ZIP64
+    /// sizes. Such a file can't be read by [`crate::read::read_zipfile_from_stream`], so set this
+    /// option if the archive is meant to be streamed and large files are expected.
+    ///
+    /// If set to `false`, nothing is reserved, which some formats require for their first entry,
+    /// like the `mimetype` file of EPUB and OpenDocument. A file exceeding the limit is still
+    /// switched to a ZIP64 data descriptor, but readers that rely on the local file header may
+    /// misread it.
+    #[must_use]
+    pub fn large_file(mut self, large: bool) -> FileOptions<'k> {
+        self.large_file = Some(large);
+        self
+    }
+
+    /// Set the extra fields written to both the local and the central file header.
     ///
-    /// If set to `false` and the file exceeds the limit, an I/O error is thrown. If set to `true`,
-    /// readers will require ZIP64 support and if the file does not exceed the limit, 20 B are
-    /// wasted. The default is `false`.
+    /// The ZIP64 and AES fields are managed by the writer and can't be set. Extra data added
+    /// with [`crate::ZipWriter::start_file_with_extra_data`] is appended after these fields.
     #[must_use]
-    pub fn large_file(mut self, large: bool) -> FileOptions {
-        self.large_file = large;
+    pub fn extra_fields(mut self, fields: &'k [ExtraField]) -> FileOptions<'k> {
+        self.local_extra_fields = fields;
+        self.central_extra_fields = fields;
         self
     }
-    pub(crate) fn with_deprecated_encryption(mut self, password: &[u8]) -> FileOptions {
-        self.encrypt_with = Some(crate::zipcrypto::ZipCryptoKeys::derive(password));
+
+    /// Set the extra fields written to the central file header only, replacing the ones set
+    /// with [`FileOptions::extra_fields`] there.
+    #[must_use]
+    pub fn central_extra_fields(mut self, fields: &'k [ExtraField]) -> FileOptions<'k> {
+        self.central_extra_fields = fields;
+        self
+    }
+
+    pub(crate) fn with_deprecated_encryption(mut self, password: &'k [u8]) -> FileOptions<'k> {
+        self.encrypt_with = Some(password);
         self
     }
 }
 
-impl Default for FileOptions {
+impl Default for FileOptions<'_> {
     /// Construct a new FileOptions object
     fn default() -> Self {
         Self {
@@ -99,13 +145,16 @@ impl Default for FileOptions {
             )))]
             compression_method: CompressionMethod::Stored,
             compression_level: None,
+            compression_params: None,
             #[cfg(feature = "time")]
             last_modified_time: OffsetDateTime::now_utc().try_into().unwrap_or_default(),
             #[cfg(not(feature = "time"))]
             last_modified_time: DateTime::default(),
             permissions: None,
-            large_file: false,
+            large_file: None,
             encrypt_with: None,
+            local_extra_fields: &[],
+            central_extra_fields: &[],
         }
     }
 }
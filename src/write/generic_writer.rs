@@ -1,9 +1,18 @@
+#[cfg(any(
+    feature = "deflate",
+    feature = "deflate-miniz",
+    feature = "deflate-zlib",
+    feature = "bzip2"
+))]
+use super::buffer_encoder::{BufferCompressor, BufferEncoder};
+use super::compression_params::CompressionParams;
 use crate::{
     CompressionMethod,
-    result::{ZipError, ZipResult},
+    codec::{CodecRegistry, Encoder},
+    result::{self, ZipError, ZipResult},
     write::MaybeEncrypted,
 };
-use std::io::{Error, ErrorKind, Seek, Write};
+use std::io::{self, Error, ErrorKind, Seek, Write};
 
 #[cfg(any(
     feature = "deflate",
@@ -15,6 +24,8 @@ use flate2::write::DeflateEncoder;
 #[cfg(feature = "bzip2")]
 use bzip2::write::BzEncoder;
 
+#[cfg(feature = "zstd")]
+use crate::zstd_dictionary::ZstdDictionaries;
 #[cfg(feature = "zstd")]
 use zstd::stream::write::Encoder as ZstdEncoder;
 
@@ -33,6 +44,43 @@ pub(crate) enum GenericZipWriter<W: Write + Seek> {
     Zstd(ZstdEncoder<'static, MaybeEncrypted<W>>),
     #[cfg(feature = "xz")]
     Xz(Box<lzma_rust2::XzWriter<MaybeEncrypted<W>>>),
+    /// Compressing with parameters that the encoders above don't support
+    #[cfg(any(
+        feature = "deflate",
+        feature = "deflate-miniz",
+        feature = "deflate-zlib",
+        feature = "bzip2"
+    ))]
+    Buffered(
+        CompressionMethod,
+        BufferEncoder<MaybeEncrypted<W>, Box<dyn BufferCompressor>>,
+    ),
+    Custom(CodecWriter<W>),
+}
+
+/// Writes through the encoder of a custom codec
+pub(crate) struct CodecWriter<W: Write + Seek> {
+    method: u16,
+    encoder: Box<dyn Encoder>,
+    inner: MaybeEncrypted<W>,
+}
+
+impl<W: Write + Seek> Write for CodecWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.encoder.write(buf, &mut self.inner)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write + Seek> CodecWriter<W> {
+    fn finish(mut self) -> io::Result<MaybeEncrypted<W>> {
+        self.encoder.finish(&mut self.inner)?;
+        Ok(self.inner)
+    }
 }
 
 impl<W: Write + Seek> GenericZipWriter<W> {
@@ -40,14 +88,18 @@ impl<W: Write + Seek> GenericZipWriter<W> {
         &mut self,
         compression: CompressionMethod,
         compression_level: Option<i32>,
+        params: Option<CompressionParams>,
+        codecs: &CodecRegistry,
+        #[cfg(feature = "zstd")] zstd_dictionaries: &ZstdDictionaries,
     ) -> ZipResult<()> {
+        if params.is_some_and(|params| params.method() != compression) {
+            return Err(ZipError::UnsupportedArchive(
+                "Compression parameters don't match the compression method",
+            ));
+        }
         match self.current_compression() {
             Some(method) if method == compression => return Ok(()),
-            None => {
-                return Err(
-                    Error::new(ErrorKind::BrokenPipe, "ZipWriter was already closed").into(),
-                );
-            }
+            None => return Err(closed_error()),
             _ => {}
         }
 
@@ -65,11 +117,15 @@ impl<W: Write + Seek> GenericZipWriter<W> {
             GenericZipWriter::Zstd(w) => w.finish()?,
             #[cfg(feature = "xz")]
             GenericZipWriter::Xz(w) => w.finish()?,
-            GenericZipWriter::Closed => {
-                return Err(
-                    Error::new(ErrorKind::BrokenPipe, "ZipWriter was already closed").into(),
-                );
-            }
+            #[cfg(any(
+                feature = "deflate",
+                feature = "deflate-miniz",
+                feature = "deflate-zlib",
+                feature = "bzip2"
+            ))]
+            GenericZipWriter::Buffered(_, w) => w.finish()?,
+            GenericZipWriter::Custom(w) => w.finish()?,
+            GenericZipWriter::Closed => return Err(closed_error()),
         };
 
         *self = {
@@ -88,9 +144,8 @@ impl<W: Write + Seek> GenericZipWriter<W> {
                     feature = "deflate-miniz",
                     feature = "deflate-zlib"
                 ))]
-                CompressionMethod::Deflated => GenericZipWriter::Deflater(DeflateEncoder::new(
-                    bare,
-                    flate2::Compression::new(
+                CompressionMethod::Deflated => {
+                    let level = flate2::Compression::new(
                         clamp_opt(
                             compression_level
                                 .unwrap_or(flate2::Compression::default().level() as i32),
@@ -99,8 +154,14 @@ impl<W: Write + Seek> GenericZipWriter<W> {
                         .ok_or(ZipError::UnsupportedArchive(
                             "Unsupported compression level",
                         ))? as u32,
-                    ),
-                )),
+                    );
+                    match params {
+                        Some(CompressionParams::Deflate(params)) => {
+                            deflater_with_params(bare, level, params)?
+                        }
+                        _ => GenericZipWriter::Deflater(DeflateEncoder::new(bare, level)),
+                    }
+                }
                 #[cfg(feature = "deflate64")]
                 CompressionMethod::Deflate64 => {
                     return Err(ZipError::UnsupportedArchive(
@@ -108,9 +169,8 @@ impl<W: Write + Seek> GenericZipWriter<W> {
                     ));
                 }
                 #[cfg(feature = "bzip2")]
-                CompressionMethod::Bzip2 => GenericZipWriter::Bzip2(BzEncoder::new(
-                    bare,
-                    bzip2::Compression::new(
+                CompressionMethod::Bzip2 => {
+                    let level = bzip2::Compression::new(
                         clamp_opt(
                             compression_level
                                 .unwrap_or(bzip2::Compression::default().level() as i32),
@@ -119,16 +179,47 @@ impl<W: Write + Seek> GenericZipWriter<W> {
                         .ok_or(ZipError::UnsupportedArchive(
                             "Unsupported compression level",
                         ))? as u32,
-                    ),
-                )),
+                    );
+                    match params {
+                        Some(CompressionParams::Bzip2(super::Bzip2Params {
+                            work_factor: Some(work_factor),
+                        })) => {
+                            if !(1..=250).contains(&work_factor) {
+                                return Err(ZipError::UnsupportedArchive(
+                                    "Unsupported bzip2 work factor",
+                                ));
+                            }
+                            GenericZipWriter::Buffered(
+                                CompressionMethod::Bzip2,
+                                BufferEncoder::new(
+                                    bare,
+                                    Box::new(bzip2::Compress::new(level, work_factor)),
+                                ),
+                            )
+                        }
+                        _ => GenericZipWriter::Bzip2(BzEncoder::new(bare, level)),
+                    }
+                }
                 CompressionMethod::AES => {
                     return Err(ZipError::UnsupportedArchive(
                         "AES compression is not supported for writing",
                     ));
                 }
                 #[cfg(feature = "zstd")]
-                CompressionMethod::Zstd => GenericZipWriter::Zstd(
-                    ZstdEncoder::new(
+                CompressionMethod::Zstd => {
+                    let dictionary = match params {
+                        Some(CompressionParams::Zstd(super::ZstdParams {
+                            dictionary: Some(id),
+                            ..
+                        })) => zstd_dictionaries
+                            .get(&id)
+                            .ok_or(ZipError::UnsupportedArchive(
+                                "The zstd dictionary was not added to the archive",
+                            ))?
+                            .as_bytes(),
+                        _ => &[],
+                    };
+                    let mut encoder = ZstdEncoder::with_dictionary(
                         bare,
                         clamp_opt(
                             compression_level.unwrap_or(zstd::DEFAULT_COMPRESSION_LEVEL),
@@ -137,9 +228,23 @@ impl<W: Write + Seek> GenericZipWriter<W> {
                         .ok_or(ZipError::UnsupportedArchive(
                             "Unsupported compression level",
                         ))?,
+                        dictionary,
                     )
-                    .map_err(ZipError::Io)?,
-                ),
+                    .map_err(ZipError::Io)?;
+                    if let Some(CompressionParams::Zstd(params)) = params {
+                        if let Some(window_log) = params.window_log {
+                            encoder.window_log(window_log)?;
+                        }
+                        encoder.long_distance_matching(params.long_distance_matching)?;
+                        encoder.include_checksum(params.checksum)?;
+                        if params.workers > 0 {
+                            encoder.set_parameter(zstd::zstd_safe::CParameter::NbWorkers(
+                                params.workers,
+                            ))?;
+                        }
+                    }
+                    GenericZipWriter::Zstd(encoder)
+                }
                 #[cfg(feature = "lzma")]
                 CompressionMethod::Lzma => {
                     return Err(ZipError::UnsupportedArchive(
@@ -147,20 +252,32 @@ impl<W: Write + Seek> GenericZipWriter<W> {
                     ));
                 }
                 #[cfg(feature = "xz")]
-                CompressionMethod::Xz => GenericZipWriter::Xz(Box::new(
-                    lzma_rust2::XzWriter::new(
-                        bare,
-                        lzma_rust2::XzOptions::with_preset(
-                            clamp_opt(compression_level.unwrap_or(6), 0..=9).ok_or(
-                                ZipError::UnsupportedArchive("Unsupported compression level"),
-                            )? as u32,
-                        ),
-                    )
-                    .map_err(ZipError::Io)?,
-                )),
-                CompressionMethod::Unsupported(..) => {
-                    return Err(ZipError::UnsupportedArchive("Unsupported compression"));
+                CompressionMethod::Xz => {
+                    let mut options = lzma_rust2::XzOptions::with_preset(
+                        clamp_opt(compression_level.unwrap_or(6), 0..=9).ok_or(
+                            ZipError::UnsupportedArchive("Unsupported compression level"),
+                        )? as u32,
+                    );
+                    if let Some(CompressionParams::Xz(params)) = params {
+                        if let Some(dict_size) = params.dict_size {
+                            options.lzma_options.dict_size = dict_size;
+                        }
+                        options.filters = params.filters().map(xz_filter).collect();
+                    }
+                    GenericZipWriter::Xz(Box::new(
+                        lzma_rust2::XzWriter::new(bare, options).map_err(ZipError::Io)?,
+                    ))
                 }
+                CompressionMethod::Unsupported(id) => match codecs.get(id) {
+                    Some(codec) => GenericZipWriter::Custom(CodecWriter {
+                        method: id,
+                        encoder: codec.encoder(compression_level)?,
+                        inner: bare,
+                    }),
+                    None => {
+                        return Err(ZipError::detailed(result::ErrorKind::UnsupportedMethod(id)));
+                    }
+                },
             }
         };
 
@@ -182,6 +299,14 @@ impl<W: Write + Seek> GenericZipWriter<W> {
             GenericZipWriter::Zstd(ref mut w) => Some(w as &mut dyn Write),
             #[cfg(feature = "xz")]
             GenericZipWriter::Xz(ref mut w) => Some(w as &mut dyn Write),
+            #[cfg(any(
+                feature = "deflate",
+                feature = "deflate-miniz",
+                feature = "deflate-zlib",
+                feature = "bzip2"
+            ))]
+            GenericZipWriter::Buffered(_, ref mut w) => Some(w as &mut dyn Write),
+            GenericZipWriter::Custom(ref mut w) => Some(w as &mut dyn Write),
             GenericZipWriter::Closed => None,
         }
     }
@@ -190,13 +315,27 @@ impl<W: Write + Seek> GenericZipWriter<W> {
         matches!(*self, GenericZipWriter::Closed)
     }
 
-    pub(super) fn get_plain(&mut self) -> &mut W {
+    pub(super) fn get_plain(&mut self) -> ZipResult<&mut W> {
         match *self {
-            GenericZipWriter::Storer(MaybeEncrypted::Unencrypted(ref mut w)) => w,
-            _ => panic!("Should have switched to stored and unencrypted beforehand"),
+            GenericZipWriter::Storer(MaybeEncrypted::Unencrypted(ref mut w)) => Ok(w),
+            GenericZipWriter::Closed => Err(closed_error()),
+            _ => Err(ZipError::Io(Error::other(
+                "Should have switched to stored and unencrypted beforehand",
+            ))),
         }
     }
 
+    /// Write the remaining encrypted data, if the writer is encrypted.
+    pub(super) fn finish_encryption(&mut self) -> ZipResult<()> {
+        *self = match std::mem::replace(self, GenericZipWriter::Closed) {
+            GenericZipWriter::Storer(MaybeEncrypted::Encrypted(w)) => {
+                GenericZipWriter::Storer(MaybeEncrypted::Unencrypted(w.finish()?))
+            }
+            other => other,
+        };
+        Ok(())
+    }
+
     fn current_compression(&self) -> Option<CompressionMethod> {
         match *self {
             GenericZipWriter::Storer(..) => Some(CompressionMethod::Stored),
@@ -212,18 +351,140 @@ impl<W: Write + Seek> GenericZipWriter<W> {
             GenericZipWriter::Zstd(..) => Some(CompressionMethod::Zstd),
             #[cfg(feature = "xz")]
             GenericZipWriter::Xz(..) => Some(CompressionMethod::Xz),
+            #[cfg(any(
+                feature = "deflate",
+                feature = "deflate-miniz",
+                feature = "deflate-zlib",
+                feature = "bzip2"
+            ))]
+            GenericZipWriter::Buffered(method, _) => Some(method),
+            GenericZipWriter::Custom(ref w) => Some(CompressionMethod::Unsupported(w.method)),
             GenericZipWriter::Closed => None,
         }
     }
 
-    pub(super) fn unwrap(self) -> W {
+    pub(super) fn unwrap(self) -> ZipResult<W> {
         match self {
-            GenericZipWriter::Storer(MaybeEncrypted::Unencrypted(w)) => w,
-            _ => panic!("Should have switched to stored and unencrypted beforehand"),
+            GenericZipWriter::Storer(MaybeEncrypted::Unencrypted(w)) => Ok(w),
+            GenericZipWriter::Closed => Err(closed_error()),
+            _ => Err(ZipError::Io(Error::other(
+                "Should have switched to stored and unencrypted beforehand",
+            ))),
         }
     }
 }
 
+/// Compress `data` with a method and return the compressed size, to compare methods
+pub(super) fn compressed_size(
+    data: &[u8],
+    compression: CompressionMethod,
+    compression_level: Option<i32>,
+    params: Option<CompressionParams>,
+    codecs: &CodecRegistry,
+    #[cfg(feature = "zstd")] zstd_dictionaries: &ZstdDictionaries,
+) -> ZipResult<u64> {
+    let mut writer =
+        GenericZipWriter::Storer(MaybeEncrypted::Unencrypted(io::Cursor::new(Vec::new())));
+    writer.switch_to(
+        compression,
+        compression_level,
+        params,
+        codecs,
+        #[cfg(feature = "zstd")]
+        zstd_dictionaries,
+    )?;
+    writer.ref_mut().ok_or_else(closed_error)?.write_all(data)?;
+    writer.switch_to(
+        CompressionMethod::Stored,
+        None,
+        None,
+        codecs,
+        #[cfg(feature = "zstd")]
+        zstd_dictionaries,
+    )?;
+    Ok(writer.get_plain()?.position())
+}
+
+fn closed_error() -> ZipError {
+    Error::new(ErrorKind::BrokenPipe, "ZipWriter was already closed").into()
+}
+
+/// Create a deflate writer using a strategy or window size, which `DeflateEncoder` doesn't support
+#[cfg(any(
+    feature = "deflate",
+    feature = "deflate-miniz",
+    feature = "deflate-zlib"
+))]
+fn deflater_with_params<W: Write + Seek>(
+    bare: MaybeEncrypted<W>,
+    level: flate2::Compression,
+    params: super::DeflateParams,
+) -> ZipResult<GenericZipWriter<W>> {
+    use super::DeflateStrategy;
+
+    let compressor: Box<dyn BufferCompressor> = match (params.strategy, params.window_bits) {
+        (DeflateStrategy::Default, None) => {
+            return Ok(GenericZipWriter::Deflater(DeflateEncoder::new(bare, level)));
+        }
+        #[cfg(feature = "deflate-zlib")]
+        (DeflateStrategy::Default, Some(window_bits)) => {
+            if !(9..=15).contains(&window_bits) {
+                return Err(ZipError::UnsupportedArchive(
+                    "Unsupported deflate window size",
+                ));
+            }
+            Box::new(flate2::Compress::new_with_window_bits(
+                level,
+                false,
+                window_bits,
+            ))
+        }
+        #[cfg(any(feature = "deflate", feature = "deflate-miniz"))]
+        (strategy, None) => {
+            use miniz_oxide::deflate::core::{
+                CompressionStrategy, CompressorOxide, create_comp_flags_from_zip_params,
+            };
+            let strategy = match strategy {
+                DeflateStrategy::Default => CompressionStrategy::Default,
+                DeflateStrategy::Filtered => CompressionStrategy::Filtered,
+                DeflateStrategy::HuffmanOnly => CompressionStrategy::HuffmanOnly,
+                DeflateStrategy::Rle => CompressionStrategy::RLE,
+                DeflateStrategy::Fixed => CompressionStrategy::Fixed,
+            };
+            // Negative window bits produce raw deflate data without a zlib header
+            let flags =
+                create_comp_flags_from_zip_params(level.level() as i32, -15, strategy as i32);
+            Box::new(CompressorOxide::new(flags))
+        }
+        _ => {
+            return Err(ZipError::UnsupportedArchive(
+                "Unsupported deflate parameters",
+            ));
+        }
+    };
+    Ok(GenericZipWriter::Buffered(
+        CompressionMethod::Deflated,
+        BufferEncoder::new(bare, compressor),
+    ))
+}
+
+#[cfg(feature = "xz")]
+fn xz_filter(filter: super::XzFilter) -> lzma_rust2::FilterConfig {
+    use super::XzFilter;
+    use lzma_rust2::FilterConfig;
+
+    match filter {
+        XzFilter::Delta { distance } => FilterConfig::new_delta(distance),
+        XzFilter::X86 => FilterConfig::new_bcj_x86(0),
+        XzFilter::Arm => FilterConfig::new_bcj_arm(0),
+        XzFilter::ArmThumb => FilterConfig::new_bcj_arm_thumb(0),
+        XzFilter::Arm64 => FilterConfig::new_bcj_arm64(0),
+        XzFilter::PowerPc => FilterConfig::new_bcj_ppc(0),
+        XzFilter::Sparc => FilterConfig::new_bcj_sparc(0),
+        XzFilter::Ia64 => FilterConfig::new_bcj_ia64(0),
+    }
+}
+
 #[cfg(any(
     feature = "deflate",
     feature = "deflate-miniz",
@@ -0,0 +1,148 @@
+use crate::DateTime;
+use crate::write::FileOptions;
+use std::time::{Duration, SystemTime};
+
+/// Settings making the archives written by a [`crate::ZipWriter`] reproducible
+///
+/// Set with [`crate::ZipWriter::set_reproducible`]. Writing the same entries with the same data
+/// in the same order then gives byte-identical archives, independent of the time they were
+/// written at, the permissions of the source files and the system the writer runs on:
+/// * Modification times later than the epoch are clamped to it. The epoch is taken from the
+///   [`SOURCE_DATE_EPOCH`] environment variable, and is 1980-01-01 00:00:00 otherwise, the
+///   earliest time a ZIP archive can store.
+/// * Permissions are normalized to `0o755` for directories and executable files, `0o644` for
+///   other files and `0o777` for symlinks.
+/// * The host system fields always name unix.
+///
+/// Extra fields are written as given. Encrypted entries are never reproducible, since their
+/// encryption header or salt is random.
+///
+/// [`SOURCE_DATE_EPOCH`]: https://reproducible-builds.org/specs/source-date-epoch/
+#[derive(Copy, Clone, Debug)]
+pub struct Reproducible {
+    epoch: DateTime,
+    sort_entries: bool,
+}
+
+impl Reproducible {
+    /// Create the settings, with the epoch from `SOURCE_DATE_EPOCH` if it is set
+    ///
+    /// Values of `SOURCE_DATE_EPOCH` that are no unix timestamp or lie outside the range of ZIP
+    /// timestamps are ignored.
+    pub fn new() -> Reproducible {
+        let epoch = std::env::var("SOURCE_DATE_EPOCH")
+            .ok()
+            .and_then(|value| parse_epoch(&value))
+            .unwrap_or_default();
+        Reproducible {
+            epoch,
+            sort_entries: false,
+        }
+    }
+
+    /// Set the latest modification time of the entries, overriding `SOURCE_DATE_EPOCH`
+    #[must_use]
+    pub fn epoch(mut self, epoch: DateTime) -> Reproducible {
+        self.epoch = epoch;
+        self
+    }
+
+    /// Set whether the central directory lists the entries sorted by name
+    ///
+    /// The default is `false`, which lists them in the order they were added. The data of the
+    /// entries is always written in that order, so the entries still need to be added in a
+    /// deterministic order, as [`crate::ZipWriter::add_dir_all`] does.
+    #[must_use]
+    pub fn sort_entries(mut self, sort: bool) -> Reproducible {
+        self.sort_entries = sort;
+        self
+    }
+
+    pub(crate) fn sorts_entries(&self) -> bool {
+        self.sort_entries
+    }
+
+    /// Clamp the modification time and normalize the permissions of an entry
+    pub(crate) fn normalize<'k>(&self, mut options: FileOptions<'k>) -> FileOptions<'k> {
+        let time = &options.last_modified_time;
+        if (time.datepart(), time.timepart()) > (self.epoch.datepart(), self.epoch.timepart()) {
+            options.last_modified_time = self.epoch;
+        }
+        options.permissions = options.permissions.map(|mode| {
+            let file_type = mode & 0o170000;
+            let permissions = match file_type {
+                0o120000 => 0o777,
+                0o040000 => 0o755,
+                _ if mode & 0o111 != 0 => 0o755,
+                _ => 0o644,
+            };
+            file_type | permissions
+        });
+        options
+    }
+}
+
+impl Default for Reproducible {
+    fn default() -> Self {
+        Reproducible::new()
+    }
+}
+
+/// Parse a value of `SOURCE_DATE_EPOCH`, the number of seconds since the unix epoch
+fn parse_epoch(value: &str) -> Option<DateTime> {
+    let seconds = value.trim().parse().ok()?;
+    let time = SystemTime::UNIX_EPOCH.checked_add(Duration::from_secs(seconds))?;
+    DateTime::try_from(time).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn source_date_epoch() {
+        let epoch = parse_epoch("1700000000").unwrap();
+        assert_eq!((epoch.year(), epoch.month(), epoch.day()), (2023, 11, 14));
+        assert_eq!((epoch.hour(), epoch.minute(), epoch.second()), (22, 13, 20));
+        // Before 1980
+        assert!(parse_epoch("0").is_none());
+        assert!(parse_epoch("-1").is_none());
+        assert!(parse_epoch("yesterday").is_none());
+    }
+
+    #[test]
+    fn normalize() {
+        let reproducible =
+            Reproducible::new().epoch(DateTime::from_date_and_time(2020, 1, 1, 0, 0, 0).unwrap());
+        let cases = [
+            (0o100600, 0o100644),
+            (0o100744, 0o100755),
+            (0o040700, 0o040755),
+            (0o120755, 0o120777),
+            (0o600, 0o644),
+        ];
+        for (mode, normalized) in cases {
+            let options = FileOptions {
+                permissions: Some(mode),
+                ..FileOptions::default()
+            };
+            assert_eq!(
+                reproducible.normalize(options).permissions,
+                Some(normalized)
+            );
+        }
+
+        let later = FileOptions::default()
+            .last_modified_time(DateTime::from_date_and_time(2021, 5, 1, 12, 0, 0).unwrap());
+        let earlier = FileOptions::default()
+            .last_modified_time(DateTime::from_date_and_time(2019, 5, 1, 12, 0, 0).unwrap());
+        assert_eq!(
+            reproducible.normalize(later).last_modified_time.year(),
+            2020
+        );
+        assert_eq!(
+            reproducible.normalize(earlier).last_modified_time.year(),
+            2019
+        );
+    }
+}
@@ -6,13 +6,22 @@
 use std::num::Wrapping;
 
 /// A container to hold the current key state
-#[derive(Clone, Copy)]
 pub(crate) struct ZipCryptoKeys {
     key_0: Wrapping<u32>,
     key_1: Wrapping<u32>,
     key_2: Wrapping<u32>,
 }
 
+#[cfg(feature = "zeroize")]
+impl Drop for ZipCryptoKeys {
+    fn drop(&mut self) {
+        use zeroize::Zeroize;
+        self.key_0.0.zeroize();
+        self.key_1.0.zeroize();
+        self.key_2.0.zeroize();
+    }
+}
+
 impl ZipCryptoKeys {
     fn new() -> ZipCryptoKeys {
         ZipCryptoKeys {
@@ -40,7 +49,6 @@ impl ZipCryptoKeys {
         plain_byte
     }
 
-    #[allow(dead_code)]
     fn encrypt_byte(&mut self, plain_byte: u8) -> u8 {
         let cipher_byte: u8 = self.stream_byte() ^ plain_byte;
         self.update(plain_byte);
@@ -123,29 +131,55 @@ impl<R: std::io::Read> ZipCryptoReader<R> {
         Ok(Some(ZipCryptoReaderValid { reader: self }))
     }
 }
+/// A ZipCrypto writer which encrypts data as it is written
+///
+/// Since the CRC of the plaintext is not known upfront, the check byte of the
+/// encryption header is the high byte of the modification time, as done by
+/// Info-ZIP. Entries written this way must set the data descriptor flag.
 pub(crate) struct ZipCryptoWriter<W> {
-    pub(crate) writer: W,
-    pub(crate) buffer: Vec<u8>,
-    pub(crate) keys: ZipCryptoKeys,
+    writer: W,
+    buffer: Vec<u8>,
+    keys: ZipCryptoKeys,
 }
 impl<W: std::io::Write> ZipCryptoWriter<W> {
-    pub(crate) fn finish(mut self, crc32: u32) -> std::io::Result<W> {
-        self.buffer[11] = (crc32 >> 24) as u8;
-        for byte in self.buffer.iter_mut() {
-            *byte = self.keys.encrypt_byte(*byte);
+    /// Write the encryption header for an entry with the given msdos modification time.
+    pub(crate) fn new(
+        mut writer: W,
+        mut keys: ZipCryptoKeys,
+        last_mod_time: u16,
+    ) -> std::io::Result<ZipCryptoWriter<W>> {
+        let mut header = [0u8; 12];
+        getrandom::fill(&mut header[..11])?;
+        header[11] = (last_mod_time >> 8) as u8;
+        for byte in header.iter_mut() {
+            *byte = keys.encrypt_byte(*byte);
         }
-        self.writer.write_all(&self.buffer)?;
+        writer.write_all(&header)?;
+        Ok(ZipCryptoWriter {
+            writer,
+            buffer: Vec::new(),
+            keys,
+        })
+    }
+
+    pub(crate) fn finish(mut self) -> std::io::Result<W> {
         self.writer.flush()?;
         Ok(self.writer)
     }
 }
 impl<W: std::io::Write> std::io::Write for ZipCryptoWriter<W> {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        self.buffer.extend_from_slice(buf);
+        // Limit the size of the scratch buffer; The key state is updated as
+        // soon as a byte is encrypted, so everything encrypted must be written.
+        let buf = &buf[..buf.len().min(64 * 1024)];
+        self.buffer.clear();
+        self.buffer
+            .extend(buf.iter().map(|&byte| self.keys.encrypt_byte(byte)));
+        self.writer.write_all(&self.buffer)?;
         Ok(buf.len())
     }
     fn flush(&mut self) -> std::io::Result<()> {
-        Ok(())
+        self.writer.flush()
     }
 }
 
@@ -0,0 +1,165 @@
+//! Zstandard dictionaries for archives of many small entries
+//!
+//! Every zstd entry is compressed on its own, so small entries compress poorly: each one starts
+//! without any knowledge of the data. A dictionary trained on samples of the entries gives the
+//! compressor that knowledge upfront.
+//!
+//! A dictionary is added to an archive with [`ZipWriter::add_zstd_dictionary`], which stores it
+//! once as an uncompressed entry named `.zstd-dictionaries/<id>`, where `<id>` is the decimal id
+//! of the dictionary. Files select it with [`ZstdParams::dictionary`]. Their data is a regular
+//! zstd frame, whose header contains the id of the dictionary.
+//!
+//! [`ZipArchive`] and [`ZipStreamReader`] load the dictionary entries of an archive and use them
+//! to decompress the entries referencing them. The archive only loads a dictionary once an entry
+//! referencing it is opened. The stream reader only knows the dictionaries that precede an entry,
+//! which is always the case for archives written by [`ZipWriter`]. Dictionary entries that are
+//! invalid, compressed, larger than 8 MiB or stored under another id are ignored, so only the
+//! entries referencing their id fail to decompress.
+//!
+//! ```
+//! # fn main() -> ziperu::result::ZipResult<()> {
+//! use std::io::{Cursor, Read, Write};
+//! use ziperu::write::{CompressionParams, FileOptions, ZstdParams};
+//! use ziperu::zstd_dictionary::ZstdDictionary;
+//! use ziperu::{CompressionMethod, ZipArchive, ZipWriter};
+//!
+//! let documents: Vec<String> = (0..1000)
+//!     .map(|i| format!(r#"{{"id": {i}, "name": "document {i}", "tags": ["a", "b"]}}"#))
+//!     .collect();
+//! let dictionary = ZstdDictionary::train(&documents, 4096)?;
+//! let options = FileOptions::default()
+//!     .compression_method(CompressionMethod::Zstd)
+//!     .compression_params(CompressionParams::Zstd(
+//!         ZstdParams::default().dictionary(dictionary.id()),
+//!     ));
+//!
+//! let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+//! zip.add_zstd_dictionary(dictionary)?;
+//! for (i, document) in documents.iter().enumerate() {
+//!     zip.start_file(format!("{i}.json"), options)?;
+//!     zip.write_all(document.as_bytes())?;
+//! }
+//!
+//! let mut archive = ZipArchive::new(zip.finish()?)?;
+//! let mut document = String::new();
+//! archive.by_name("7.json")?.read_to_string(&mut document)?;
+//! assert_eq!(document, documents[7]);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`ZipWriter::add_zstd_dictionary`]: crate::ZipWriter::add_zstd_dictionary
+//! [`ZipWriter`]: crate::ZipWriter
+//! [`ZstdParams::dictionary`]: crate::write::ZstdParams::dictionary
+//! [`ZipArchive`]: crate::ZipArchive
+//! [`ZipStreamReader`]: crate::unstable::stream::ZipStreamReader
+
+use crate::compression::CompressionMethod;
+use crate::result::{ZipError, ZipResult};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// The directory holding the dictionary entries
+const DICTIONARY_DIRECTORY: &str = ".zstd-dictionaries/";
+
+/// The size of the largest dictionary entry to load
+const MAX_ENTRY_SIZE: u64 = 8 << 20;
+
+/// The size of the largest zstd frame header, which contains the dictionary id
+pub(crate) const MAX_FRAME_HEADER_SIZE: u64 = 18;
+
+/// The dictionaries of an archive, indexed by id
+pub(crate) type ZstdDictionaries = HashMap<u32, ZstdDictionary>;
+
+/// A zstd dictionary with an id
+///
+/// The dictionary is cheap to clone, the data is shared.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ZstdDictionary {
+    id: u32,
+    data: Arc<[u8]>,
+}
+
+impl ZstdDictionary {
+    /// Use a dictionary in the zstd format, as created by `zstd --train`
+    ///
+    /// Fails if the data is not a dictionary with an id. Raw content dictionaries are not
+    /// supported, since entries compressed with them don't reference the dictionary.
+    pub fn new(data: Vec<u8>) -> ZipResult<ZstdDictionary> {
+        let id = zstd::zstd_safe::get_dict_id_from_dict(&data)
+            .ok_or(ZipError::InvalidArchive("Invalid zstd dictionary"))?;
+        Ok(ZstdDictionary {
+            id: id.get(),
+            data: data.into(),
+        })
+    }
+
+    /// Train a dictionary of at most `max_size` bytes on samples of the data to compress
+    ///
+    /// Training needs a reasonable number of samples, a few hundred or more. The zstd
+    /// documentation suggests a dictionary size of about 100 KiB, and a total size of the samples
+    /// of about 100 times the dictionary size.
+    pub fn train<S: AsRef<[u8]>>(samples: &[S], max_size: usize) -> ZipResult<ZstdDictionary> {
+        ZstdDictionary::new(zstd::dict::from_samples(samples, max_size)?)
+    }
+
+    /// The id of the dictionary, which is stored in the frames compressed with it
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// The dictionary in the zstd format
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// The name of the entry storing the dictionary
+    pub(crate) fn entry_name(&self) -> String {
+        format!("{DICTIONARY_DIRECTORY}{}", self.id)
+    }
+
+    /// The id of the dictionary stored in an entry with this name
+    fn id_from_entry_name(name: &str) -> Option<u32> {
+        let id = name.strip_prefix(DICTIONARY_DIRECTORY)?;
+        // Only accept the canonical form written by `entry_name`
+        id.parse()
+            .ok()
+            .filter(|parsed: &u32| parsed.to_string() == id)
+    }
+
+    /// The id of the dictionary stored in an entry of `size` bytes, if the entry stores a
+    /// dictionary that should be loaded
+    ///
+    /// Dictionary entries are uncompressed, since decompressing them could need the dictionaries.
+    pub(crate) fn id_from_entry(
+        name: &str,
+        compression: CompressionMethod,
+        size: u64,
+    ) -> Option<u32> {
+        match compression {
+            CompressionMethod::Stored if size <= MAX_ENTRY_SIZE => {
+                ZstdDictionary::id_from_entry_name(name)
+            }
+            _ => None,
+        }
+    }
+
+    /// Use the content of the entry storing the dictionary `id`, or `None` if it is invalid
+    ///
+    /// An invalid dictionary entry is ignored, so it only breaks the entries referencing its id.
+    pub(crate) fn from_entry_content(id: u32, content: &[u8]) -> Option<ZstdDictionary> {
+        ZstdDictionary::new(content.to_vec())
+            .ok()
+            .filter(|dictionary| dictionary.id == id)
+    }
+}
+
+impl fmt::Debug for ZstdDictionary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ZstdDictionary")
+            .field("id", &self.id)
+            .field("len", &self.data.len())
+            .finish()
+    }
+}
@@ -0,0 +1,78 @@
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::Path;
+use ziperu::write::FileOptions;
+use ziperu::{ZipArchive, ZipWriter};
+
+fn create_tree(root: &Path) {
+    let _ = fs::remove_dir_all(root);
+    fs::create_dir_all(root.join("b/nested")).unwrap();
+    fs::create_dir_all(root.join("a")).unwrap();
+    fs::create_dir_all(root.join("target")).unwrap();
+    fs::write(root.join("z.txt"), b"z").unwrap();
+    fs::write(root.join("a/file.txt"), b"a").unwrap();
+    fs::write(root.join("b/nested/file.txt"), b"nested").unwrap();
+    fs::write(root.join("b/skip.tmp"), b"tmp").unwrap();
+    fs::write(root.join("target/output"), b"output").unwrap();
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(root.join("z.txt"), fs::Permissions::from_mode(0o750)).unwrap();
+        std::os::unix::fs::symlink("../z.txt", root.join("a/link")).unwrap();
+    }
+}
+
+fn archive(root: &Path) -> Vec<u8> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.add_dir_all_filtered(root, "prefix", FileOptions::default(), |path| {
+        !path.starts_with("target") && path.extension().is_none_or(|ext| ext != "tmp")
+    })
+    .unwrap();
+    zip.finish().unwrap().into_inner()
+}
+
+#[test]
+fn add_dir_all() {
+    let root = std::env::temp_dir().join(format!("ziperu-add-dir-all-{}", std::process::id()));
+    create_tree(&root);
+
+    let data = archive(&root);
+    assert_eq!(data, archive(&root), "archives should be reproducible");
+
+    let mut zip = ZipArchive::new(Cursor::new(data)).unwrap();
+    let expected = [
+        "prefix/a/",
+        "prefix/b/",
+        "prefix/z.txt",
+        "prefix/a/file.txt",
+        #[cfg(unix)]
+        "prefix/a/link",
+        "prefix/b/nested/",
+        "prefix/b/nested/file.txt",
+    ];
+    assert_eq!(zip.file_names().collect::<Vec<_>>(), expected);
+
+    let mut content = String::new();
+    zip.by_name("prefix/b/nested/file.txt")
+        .unwrap()
+        .read_to_string(&mut content)
+        .unwrap();
+    assert_eq!(content, "nested");
+
+    #[cfg(unix)]
+    {
+        assert_eq!(
+            zip.by_name("prefix/z.txt").unwrap().unix_mode(),
+            Some(0o100750)
+        );
+
+        let mut link = zip.by_name("prefix/a/link").unwrap();
+        assert_eq!(link.unix_mode().unwrap() & 0o170000, 0o120000);
+        let mut target = String::new();
+        link.read_to_string(&mut target).unwrap();
+        assert_eq!(target, "../z.txt");
+    }
+
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -78,3 +78,214 @@ fn aes128_encrypted_file() {
         .expect("couldn't read encrypted file");
     assert_eq!(SECRET_CONTENT, content);
 }
+
+#[test]
+fn aes_authenticate_first() {
+    let mut v = Vec::new();
+    v.extend_from_slice(include_bytes!("data/aes_archive.zip"));
+    let mut archive = ZipArchive::new(io::Cursor::new(v.clone())).unwrap();
+    archive.set_authenticate_aes_first(true);
+
+    let mut content = String::new();
+    archive
+        .by_name_decrypt("secret_data_256_uncompressed", PASSWORD)
+        .unwrap()
+        .unwrap()
+        .read_to_string(&mut content)
+        .unwrap();
+    assert_eq!(SECRET_CONTENT, content);
+
+    // Flip a bit in the encrypted data, after the salt and password verification value
+    let index = archive
+        .file_names()
+        .position(|name| name == "secret_data_256_uncompressed")
+        .unwrap();
+    let data_start = archive.by_index_raw(index).unwrap().data_start();
+    v[data_start as usize + 16 + 2 + 5] ^= 1;
+
+    let mut archive = ZipArchive::new(io::Cursor::new(v)).unwrap();
+    let mut file = archive
+        .by_name_decrypt("secret_data_256_uncompressed", PASSWORD)
+        .unwrap()
+        .unwrap();
+    // By default, tampered data is released before the authentication code is checked
+    assert_eq!(file.read(&mut [0; 4]).unwrap(), 4);
+    let err = file.read_to_end(&mut Vec::new()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    drop(file);
+
+    archive.set_authenticate_aes_first(true);
+    match archive.by_name_decrypt("secret_data_256_uncompressed", PASSWORD) {
+        Err(ziperu::result::ZipError::Io(err)) => {
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData)
+        }
+        _ => panic!("tampered entry should not be opened"),
+    }
+}
+
+/// A reader counting the bytes read from the archive
+struct CountingReader(io::Cursor<Vec<u8>>, std::rc::Rc<std::cell::Cell<u64>>);
+
+impl Read for CountingReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.0.read(buf)?;
+        self.1.set(self.1.get() + read as u64);
+        Ok(read)
+    }
+}
+
+impl io::Seek for CountingReader {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        self.0.seek(pos)
+    }
+}
+
+#[test]
+fn aes_password_provider_authenticates_once() {
+    let read = std::rc::Rc::new(std::cell::Cell::new(0));
+    let reader = CountingReader(
+        io::Cursor::new(include_bytes!("data/aes_archive.zip").to_vec()),
+        read.clone(),
+    );
+    let mut archive = ZipArchive::new(reader).unwrap();
+    archive.set_authenticate_aes_first(true);
+
+    read.set(0);
+    drop(
+        archive
+            .by_name_decrypt("secret_data_256", PASSWORD)
+            .unwrap(),
+    );
+    let decrypt_read = read.get();
+
+    archive.set_password_provider(|_| Some(PASSWORD.to_vec()));
+    read.set(0);
+    let mut content = String::new();
+    let mut file = archive.by_name("secret_data_256").unwrap();
+    // The entry was authenticated while checking the password, and not again when opening it
+    assert_eq!(read.get(), decrypt_read);
+    file.read_to_string(&mut content).unwrap();
+    assert_eq!(SECRET_CONTENT, content);
+}
+
+#[test]
+fn aes_decryption_session() {
+    let mut v = Vec::new();
+    v.extend_from_slice(include_bytes!("data/aes_archive.zip"));
+    let mut archive = ZipArchive::new(io::Cursor::new(v)).unwrap();
+    let mut session = archive.decryption_session();
+    session.derive_keys(PASSWORD).unwrap();
+
+    for _ in 0..2 {
+        let mut content = String::new();
+        session
+            .by_name_decrypt("secret_data_128", PASSWORD)
+            .unwrap()
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(SECRET_CONTENT, content);
+    }
+    assert!(
+        session
+            .by_name_decrypt("secret_data_128", b"wrong")
+            .unwrap()
+            .is_err()
+    );
+
+    let dir = std::env::temp_dir().join(format!("ziperu-aes-session-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    session
+        .extract_with_options(&dir, Default::default(), PASSWORD)
+        .unwrap();
+    for name in [
+        "secret_data_128",
+        "secret_data_192",
+        "secret_data_256",
+        "secret_data_256_uncompressed",
+    ] {
+        assert_eq!(
+            std::fs::read_to_string(dir.join(name)).unwrap(),
+            SECRET_CONTENT
+        );
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let err = session
+        .extract_with_options(&dir, Default::default(), b"wrong")
+        .unwrap_err();
+    let details = err.details().unwrap();
+    assert_eq!(details.kind(), &ziperu::result::ErrorKind::WrongPassword);
+    assert!(details.name().is_some());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn aes_encryption_info_and_password_provider() {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use ziperu::{AesMode, AesVendorVersion, EncryptionMethod};
+
+    let mut v = Vec::new();
+    v.extend_from_slice(include_bytes!("data/aes_archive.zip"));
+    let mut archive = ZipArchive::new(io::Cursor::new(v)).unwrap();
+
+    let index = archive
+        .file_names()
+        .position(|name| name == "secret_data_192")
+        .unwrap();
+    let expected = Some(EncryptionMethod::Aes {
+        mode: AesMode::Aes192,
+        vendor_version: AesVendorVersion::Ae2,
+    });
+    assert_eq!(archive.encryption(index).unwrap(), expected);
+    assert_eq!(archive.by_index_raw(index).unwrap().encryption(), expected);
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let provider_calls = calls.clone();
+    archive.set_password_provider(move |request| {
+        provider_calls.fetch_add(1, Ordering::Relaxed);
+        assert!(request.name.starts_with("secret_data"));
+        assert!(matches!(request.encryption, EncryptionMethod::Aes { .. }));
+        let candidates: [&[u8]; 2] = [b"wrong password", PASSWORD];
+        candidates.get(request.attempt).map(|p| p.to_vec())
+    });
+
+    let mut content = String::new();
+    let mut file = archive.by_name("secret_data_192").unwrap();
+    assert!(file.is_encrypted());
+    file.read_to_string(&mut content).unwrap();
+    assert_eq!(SECRET_CONTENT, content);
+    drop(file);
+    assert_eq!(calls.load(Ordering::Relaxed), 2);
+
+    let dir = std::env::temp_dir().join(format!("ziperu-aes-provider-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    archive.extract(&dir).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(dir.join("secret_data_128")).unwrap(),
+        SECRET_CONTENT
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    archive.set_password_provider(|_| None);
+    assert!(matches!(
+        archive.by_name("secret_data_192"),
+        Err(ziperu::result::ZipError::UnsupportedArchive(
+            ziperu::result::ZipError::PASSWORD_REQUIRED
+        ))
+    ));
+    // A provider that never finds the password doesn't retry forever
+    let calls = Arc::new(AtomicUsize::new(0));
+    let provider_calls = calls.clone();
+    archive.set_password_provider(move |_| {
+        provider_calls.fetch_add(1, Ordering::Relaxed);
+        Some(b"wrong password".to_vec())
+    });
+    archive.set_max_password_attempts(2);
+    let err = archive.by_name("secret_data_192").err().unwrap();
+    let details = err.details().unwrap();
+    assert_eq!(details.kind(), &ziperu::result::ErrorKind::WrongPassword);
+    assert_eq!(details.name(), Some("secret_data_192"));
+    assert_eq!(calls.load(Ordering::Relaxed), 2);
+}
@@ -0,0 +1,213 @@
+#![cfg(any(
+    feature = "deflate",
+    feature = "deflate-miniz",
+    feature = "deflate-zlib"
+))]
+
+use std::io::{Cursor, Read, Write};
+use ziperu::unstable::write::FileOptionsExt;
+use ziperu::write::{AutoCompression, FileOptions};
+use ziperu::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// Data that doesn't compress
+fn random(len: usize) -> Vec<u8> {
+    let mut state = 0x2545f4914f6cdd1du64;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect()
+}
+
+fn text(len: usize) -> Vec<u8> {
+    b"The quick brown fox jumps over the lazy dog. "
+        .iter()
+        .copied()
+        .cycle()
+        .take(len)
+        .collect()
+}
+
+fn options() -> FileOptions<'static> {
+    FileOptions::default().compression_method(CompressionMethod::Deflated)
+}
+
+fn write(policy: AutoCompression, files: &[(&str, Vec<u8>)]) -> Vec<u8> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.set_auto_compression(Some(policy));
+    for (name, content) in files {
+        zip.start_file(*name, options()).unwrap();
+        // Write in small chunks, so that the sample is completed by several writes
+        for chunk in content.chunks(1000) {
+            zip.write_all(chunk).unwrap();
+        }
+    }
+    zip.finish().unwrap().into_inner()
+}
+
+/// Check the content of all files and return their compression methods
+fn read(data: Vec<u8>, files: &[(&str, Vec<u8>)]) -> Vec<CompressionMethod> {
+    let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    files
+        .iter()
+        .map(|(name, content)| {
+            let mut file = archive.by_name(name).unwrap();
+            let mut read = Vec::new();
+            file.read_to_end(&mut read).unwrap();
+            assert_eq!(&read, content, "{name}");
+            file.compression()
+        })
+        .collect()
+}
+
+#[test]
+fn stores_incompressible_files() {
+    let files = [
+        ("random.bin", random(100_000)),
+        ("small.bin", random(100)),
+        ("text.txt", text(100_000)),
+        ("small.txt", text(500)),
+        ("empty.txt", Vec::new()),
+    ];
+    let data = write(AutoCompression::new(), &files);
+    assert_eq!(
+        read(data.clone(), &files),
+        [
+            CompressionMethod::Stored,
+            CompressionMethod::Stored,
+            CompressionMethod::Deflated,
+            CompressionMethod::Deflated,
+            CompressionMethod::Stored,
+        ]
+    );
+
+    // The local header has the chosen method as well
+    assert_eq!(u16::from_le_bytes([data[8], data[9]]), 0);
+}
+
+#[test]
+fn rules() {
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    png.extend(text(10_000));
+    let files = [
+        ("photo.JPG", text(10_000)),
+        ("image", png),
+        ("notes.txt", text(10_000)),
+        ("data.csv", random(10_000)),
+    ];
+    let policy = AutoCompression::new()
+        .extension("txt", CompressionMethod::Stored)
+        .extension("csv", CompressionMethod::Deflated);
+    assert_eq!(
+        read(write(policy, &files), &files),
+        [
+            CompressionMethod::Stored,
+            CompressionMethod::Stored,
+            CompressionMethod::Stored,
+            CompressionMethod::Deflated,
+        ]
+    );
+
+    let policy = AutoCompression::new().clear_rules();
+    assert_eq!(
+        read(write(policy, &files[..2]), &files[..2]),
+        [CompressionMethod::Deflated, CompressionMethod::Deflated]
+    );
+}
+
+#[test]
+fn sample_of_large_file() {
+    // Only the compressible start of the file is sampled
+    let mut content = text(2000);
+    content.extend(random(100_000));
+    let files = [("mixed.bin", content)];
+    let policy = AutoCompression::new().sample_size(1000);
+    assert_eq!(
+        read(write(policy, &files), &files),
+        [CompressionMethod::Deflated]
+    );
+
+    let policy = AutoCompression::new().sample_size(1000).max_ratio(0.0);
+    assert_eq!(
+        read(write(policy, &files), &files),
+        [CompressionMethod::Stored]
+    );
+}
+
+#[cfg(all(feature = "bzip2", feature = "zstd"))]
+#[test]
+fn best_candidate() {
+    let files = [("text.txt", text(100_000))];
+    let policy = AutoCompression::new()
+        .candidate(CompressionMethod::Bzip2)
+        .candidate(CompressionMethod::Zstd);
+    let method = read(write(policy, &files), &files)[0];
+    assert_ne!(method, CompressionMethod::Stored);
+    assert_ne!(method, CompressionMethod::Deflated);
+}
+
+#[test]
+fn stored_files_are_not_sampled() {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.set_auto_compression(Some(AutoCompression::new()));
+    let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+    zip.start_file("text.txt", options).unwrap();
+    zip.write_all(&text(10_000)).unwrap();
+    let files = [("text.txt", text(10_000))];
+    assert_eq!(
+        read(zip.finish().unwrap().into_inner(), &files),
+        [CompressionMethod::Stored]
+    );
+}
+
+#[test]
+fn policy_changed_while_sampling() {
+    let files = [
+        ("cleared.txt", text(10_000)),
+        ("smaller.txt", text(10_000)),
+        ("after.txt", text(10_000)),
+    ];
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for (i, (name, content)) in files.iter().enumerate() {
+        zip.set_auto_compression(Some(AutoCompression::new().sample_size(8000)));
+        zip.start_file(*name, options()).unwrap();
+        zip.write_all(&content[..5000]).unwrap();
+        // The pending sample is still completed with the policy the file was started with
+        let replacement = match i {
+            0 => None,
+            _ => Some(AutoCompression::new().sample_size(1000)),
+        };
+        zip.set_auto_compression(replacement);
+        zip.write_all(&content[5000..]).unwrap();
+    }
+    assert_eq!(
+        read(zip.finish().unwrap().into_inner(), &files),
+        [CompressionMethod::Deflated; 3]
+    );
+}
+
+#[test]
+fn encrypted() {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.set_auto_compression(Some(AutoCompression::new()));
+    let options = options().with_deprecated_encryption(b"password");
+    zip.start_file("random.bin", options).unwrap();
+    zip.write_all(&random(10_000)).unwrap();
+    zip.start_file("text.txt", options).unwrap();
+    zip.write_all(&text(10_000)).unwrap();
+
+    let mut archive = ZipArchive::new(zip.finish().unwrap()).unwrap();
+    for (name, content, method) in [
+        ("random.bin", random(10_000), CompressionMethod::Stored),
+        ("text.txt", text(10_000), CompressionMethod::Deflated),
+    ] {
+        let mut file = archive.by_name_decrypt(name, b"password").unwrap().unwrap();
+        assert_eq!(file.compression(), method);
+        let mut read = Vec::new();
+        file.read_to_end(&mut read).unwrap();
+        assert_eq!(read, content);
+    }
+}
@@ -0,0 +1,248 @@
+mod common;
+
+use std::io::{self, Cursor, Read, Write};
+use std::sync::Arc;
+use std::thread;
+use ziperu::codec::{Codec, CodecRegistry, Encoder};
+use ziperu::read::ZipFile;
+use ziperu::result::{ErrorKind, ZipResult};
+use ziperu::unstable::stream::{ZipStreamFileMetadata, ZipStreamReader, ZipStreamVisitor};
+use ziperu::write::FileOptions;
+use ziperu::{CompressionMethod, ZipArchive, ZipWriter};
+
+const METHOD: u16 = 0x4c34;
+const CONTENT: &[u8] = b"Lorem ipsum dolor sit amet, consectetur adipiscing elit";
+
+/// Flips all bits, so that the stored data differs from the content
+struct Invert;
+
+impl Codec for Invert {
+    fn method(&self) -> u16 {
+        METHOD
+    }
+
+    fn version_needed(&self) -> u16 {
+        63
+    }
+
+    fn decoder<'a>(
+        &self,
+        reader: Box<dyn Read + 'a>,
+        _uncompressed_size: u64,
+    ) -> io::Result<Box<dyn Read + 'a>> {
+        Ok(Box::new(invert(reader)?))
+    }
+
+    fn send_decoder<'a>(
+        &self,
+        reader: Box<dyn Read + Send + 'a>,
+        _uncompressed_size: u64,
+    ) -> io::Result<Box<dyn Read + Send + 'a>> {
+        Ok(Box::new(invert(reader)?))
+    }
+
+    fn encoder(&self, level: Option<i32>) -> io::Result<Box<dyn Encoder>> {
+        if level.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Unsupported compression level",
+            ));
+        }
+        Ok(Box::new(Invert))
+    }
+}
+
+fn invert(mut reader: impl Read) -> io::Result<Cursor<Vec<u8>>> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    let data = data.strip_suffix(b"END").ok_or(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "Missing trailer",
+    ))?;
+    Ok(Cursor::new(data.iter().map(|b| !b).collect()))
+}
+
+/// Decodes like [`Invert`], but lazily, with a decoder that never releases its input
+struct LazyInvert;
+
+impl Codec for LazyInvert {
+    fn method(&self) -> u16 {
+        METHOD
+    }
+
+    fn version_needed(&self) -> u16 {
+        63
+    }
+
+    fn decoder<'a>(
+        &self,
+        reader: Box<dyn Read + 'a>,
+        uncompressed_size: u64,
+    ) -> io::Result<Box<dyn Read + 'a>> {
+        // The trailer is left unread, and the input is leaked like a decoder holding on to it in a
+        // cache would
+        Ok(Box::new(Inverted(
+            Box::leak(reader).take(uncompressed_size),
+        )))
+    }
+
+    fn encoder(&self, level: Option<i32>) -> io::Result<Box<dyn Encoder>> {
+        Invert.encoder(level)
+    }
+}
+
+struct Inverted<R>(R);
+
+impl<R: Read> Read for Inverted<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.0.read(buf)?;
+        buf[..count].iter_mut().for_each(|b| *b = !*b);
+        Ok(count)
+    }
+}
+
+impl Encoder for Invert {
+    fn write(&mut self, input: &[u8], output: &mut dyn Write) -> io::Result<()> {
+        output.write_all(&input.iter().map(|b| !b).collect::<Vec<_>>())
+    }
+
+    fn finish(self: Box<Self>, output: &mut dyn Write) -> io::Result<()> {
+        // Write a trailer, to make sure finish is called
+        output.write_all(b"END")
+    }
+}
+
+fn archive() -> Vec<u8> {
+    common::write_archive(|zip| {
+        zip.set_codecs(CodecRegistry::new().register(Invert));
+        let options =
+            FileOptions::default().compression_method(CompressionMethod::Unsupported(METHOD));
+        common::add_files(zip, &[("custom.txt", CONTENT)], options);
+        let options = options.compression_method(CompressionMethod::Stored);
+        common::add_files(zip, &[("stored.txt", CONTENT)], options);
+    })
+}
+
+#[test]
+fn custom_codec_roundtrip() {
+    let mut archive = ZipArchive::new(Cursor::new(archive())).unwrap();
+
+    let mut raw = Vec::new();
+    let mut file = archive.by_index_raw(0).unwrap();
+    assert_eq!(file.compression(), CompressionMethod::Unsupported(METHOD));
+    file.read_to_end(&mut raw).unwrap();
+    assert_eq!(raw.len(), CONTENT.len() + 3);
+    assert!(raw.ends_with(b"END"));
+    drop(file);
+
+    // The entry can't be opened without the codec
+    let Err(err) = archive.by_name("custom.txt") else {
+        panic!("Opened an entry with an unknown method");
+    };
+    assert!(matches!(
+        err.details().unwrap().kind(),
+        ErrorKind::UnsupportedMethod(METHOD)
+    ));
+
+    archive.set_codecs(CodecRegistry::new().register(Invert));
+    let mut content = Vec::new();
+    archive
+        .by_name("custom.txt")
+        .unwrap()
+        .read_to_end(&mut content)
+        .unwrap();
+    assert_eq!(content, CONTENT);
+}
+
+#[test]
+fn custom_codec_owned() {
+    let data: Arc<[u8]> = archive().into();
+    let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    archive.set_codecs(CodecRegistry::new().register(Invert));
+    let mut file = archive.by_name_owned("custom.txt").unwrap();
+    let content = thread::spawn(move || {
+        let mut content = Vec::new();
+        file.read_to_end(&mut content).unwrap();
+        content
+    })
+    .join()
+    .unwrap();
+    assert_eq!(content, CONTENT);
+}
+
+#[test]
+fn custom_codec_version_needed() {
+    let data = archive();
+    let version_needed = u16::from_le_bytes([data[4], data[5]]);
+    assert_eq!(version_needed, 63);
+}
+
+#[test]
+fn custom_codec_level() {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.set_codecs(CodecRegistry::new().register(Invert));
+    let options = FileOptions::default()
+        .compression_method(CompressionMethod::Unsupported(METHOD))
+        .compression_level(Some(1));
+    assert!(zip.start_file("custom.txt", options).is_err());
+}
+
+/// Collects the names and contents of the entries of a stream, except those in `skip`
+struct Visitor {
+    skip: &'static [&'static str],
+    files: Vec<(String, Vec<u8>)>,
+}
+
+impl ZipStreamVisitor for Visitor {
+    fn visit_file(&mut self, file: &mut ZipFile<'_>) -> ZipResult<()> {
+        if self.skip.contains(&file.name()) {
+            return Ok(());
+        }
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)?;
+        self.files.push((file.name().to_owned(), content));
+        Ok(())
+    }
+
+    fn visit_additional_metadata(&mut self, _: &ZipStreamFileMetadata) -> ZipResult<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn custom_codec_stream() {
+    let mut visitor = Visitor {
+        skip: &[],
+        files: Vec::new(),
+    };
+    ZipStreamReader::new(Cursor::new(archive()))
+        .with_codecs(CodecRegistry::new().register(Invert))
+        .visit(&mut visitor)
+        .unwrap();
+    assert_eq!(
+        visitor.files,
+        [
+            ("custom.txt".to_owned(), CONTENT.to_vec()),
+            ("stored.txt".to_owned(), CONTENT.to_vec()),
+        ]
+    );
+}
+
+#[test]
+fn custom_codec_stream_unread() {
+    // Dropping an entry drains the input the decoder holds on to, so the next entry is found
+    for skip in [&[][..], &["custom.txt"]] {
+        let mut visitor = Visitor {
+            skip,
+            files: Vec::new(),
+        };
+        ZipStreamReader::new(Cursor::new(archive()))
+            .with_codecs(CodecRegistry::new().register(LazyInvert))
+            .visit(&mut visitor)
+            .unwrap();
+        assert_eq!(
+            visitor.files.last().unwrap(),
+            &("stored.txt".to_owned(), CONTENT.to_vec())
+        );
+    }
+}
@@ -0,0 +1,61 @@
+//! Helpers shared by the integration tests
+
+#![allow(dead_code)]
+
+use std::fs;
+use std::io::{Cursor, Read, Seek, Write};
+use std::path::PathBuf;
+use ziperu::write::FileOptions;
+use ziperu::{ZipArchive, ZipWriter};
+
+/// Write an archive in memory with `build`
+pub fn write_archive(build: impl FnOnce(&mut ZipWriter<Cursor<Vec<u8>>>)) -> Vec<u8> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    build(&mut zip);
+    zip.finish().unwrap().into_inner()
+}
+
+/// Write an archive in memory with `build` and open it
+pub fn archive(build: impl FnOnce(&mut ZipWriter<Cursor<Vec<u8>>>)) -> ZipArchive<Cursor<Vec<u8>>> {
+    ZipArchive::new(Cursor::new(write_archive(build))).unwrap()
+}
+
+/// Write an archive in memory with these files, see [`add_files`]
+pub fn write_files<C: AsRef<[u8]>>(files: &[(&str, C)], options: FileOptions<'_>) -> Vec<u8> {
+    write_archive(|zip| add_files(zip, files, options))
+}
+
+/// Add files with their names and contents, a name ending with `/` adds a directory
+pub fn add_files<C: AsRef<[u8]>>(
+    zip: &mut ZipWriter<Cursor<Vec<u8>>>,
+    files: &[(&str, C)],
+    options: FileOptions<'_>,
+) {
+    for (name, content) in files {
+        if name.ends_with('/') {
+            zip.add_directory(*name, options).unwrap();
+        } else {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(content.as_ref()).unwrap();
+        }
+    }
+}
+
+/// Read the content of the file with this name
+pub fn read_by_name<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> String {
+    let mut content = String::new();
+    archive
+        .by_name(name)
+        .unwrap()
+        .read_to_string(&mut content)
+        .unwrap();
+    content
+}
+
+/// An empty temporary directory for a test
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ziperu-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
@@ -0,0 +1,221 @@
+#![cfg(any(
+    feature = "deflate",
+    feature = "deflate-miniz",
+    feature = "deflate-zlib",
+    feature = "bzip2",
+    feature = "zstd",
+    feature = "xz"
+))]
+
+use std::io::{Cursor, Read, Write};
+use ziperu::result::ZipResult;
+use ziperu::write::{CompressionParams, FileOptions};
+use ziperu::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// Repetitive content with some variation, so that all methods have something to compress
+fn content() -> Vec<u8> {
+    (0..16 * 1024u32)
+        .flat_map(|i| format!("line {} of {}\n", i % 1000, i / 1000).into_bytes())
+        .collect()
+}
+
+fn write(options: FileOptions<'_>, content: &[u8]) -> ZipResult<Vec<u8>> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.start_file("file.txt", options)?;
+    zip.write_all(content)?;
+    Ok(zip.finish()?.into_inner())
+}
+
+fn read(data: Vec<u8>) -> Vec<u8> {
+    let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    let mut content = Vec::new();
+    archive
+        .by_name("file.txt")
+        .unwrap()
+        .read_to_end(&mut content)
+        .unwrap();
+    content
+}
+
+#[cfg(any(feature = "deflate", feature = "deflate-miniz", feature = "bzip2"))]
+fn compressed_size(data: Vec<u8>) -> u64 {
+    ZipArchive::new(Cursor::new(data))
+        .unwrap()
+        .by_index_raw(0)
+        .unwrap()
+        .compressed_size()
+}
+
+#[cfg(any(feature = "deflate", feature = "deflate-miniz"))]
+#[test]
+fn deflate_strategies() {
+    use ziperu::write::{DeflateParams, DeflateStrategy};
+
+    let content = content();
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    let default_size = compressed_size(write(options, &content).unwrap());
+    for strategy in [
+        DeflateStrategy::Default,
+        DeflateStrategy::Filtered,
+        DeflateStrategy::HuffmanOnly,
+        DeflateStrategy::Rle,
+        DeflateStrategy::Fixed,
+    ] {
+        let params = CompressionParams::Deflate(DeflateParams::default().strategy(strategy));
+        let data = write(options.compression_params(params), &content).unwrap();
+        if strategy == DeflateStrategy::HuffmanOnly {
+            // Without matches, the repetitive content compresses much worse
+            assert!(compressed_size(data.clone()) > 2 * default_size);
+        }
+        assert_eq!(read(data), content, "{strategy:?}");
+    }
+}
+
+#[cfg(feature = "deflate-zlib")]
+#[test]
+fn deflate_window_bits() {
+    use ziperu::write::DeflateParams;
+
+    let content = content();
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    let params = CompressionParams::Deflate(DeflateParams::default().window_bits(9));
+    let data = write(options.compression_params(params), &content).unwrap();
+    assert_eq!(read(data), content);
+
+    let params = CompressionParams::Deflate(DeflateParams::default().window_bits(16));
+    assert!(write(options.compression_params(params), &content).is_err());
+}
+
+#[cfg(feature = "bzip2")]
+#[test]
+fn bzip2_work_factor() {
+    use ziperu::write::Bzip2Params;
+
+    let content = content();
+    let options = FileOptions::default().compression_method(CompressionMethod::Bzip2);
+    let params = CompressionParams::Bzip2(Bzip2Params::default().work_factor(250));
+    let data = write(options.compression_params(params), &content).unwrap();
+    // The work factor doesn't change the compressed data
+    assert_eq!(
+        compressed_size(data.clone()),
+        compressed_size(write(options, &content).unwrap())
+    );
+    assert_eq!(read(data), content);
+
+    let params = CompressionParams::Bzip2(Bzip2Params::default().work_factor(0));
+    assert!(write(options.compression_params(params), &content).is_err());
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn zstd_params() {
+    use ziperu::write::ZstdParams;
+
+    let content = content();
+    let options = FileOptions::default().compression_method(CompressionMethod::Zstd);
+    let params = CompressionParams::Zstd(
+        ZstdParams::default()
+            .window_log(24)
+            .long_distance_matching(true)
+            .checksum(true),
+    );
+    let data = write(options.compression_params(params), &content).unwrap();
+    assert_eq!(read(data), content);
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn zstd_window_limit() {
+    use ziperu::read::{DecoderLimits, ZipFile};
+    use ziperu::unstable::stream::{ZipStreamFileMetadata, ZipStreamReader, ZipStreamVisitor};
+    use ziperu::write::ZstdParams;
+
+    struct Visitor;
+
+    impl ZipStreamVisitor for Visitor {
+        fn visit_file(&mut self, file: &mut ZipFile<'_>) -> ZipResult<()> {
+            file.read_to_end(&mut Vec::new())?;
+            Ok(())
+        }
+
+        fn visit_additional_metadata(&mut self, _: &ZipStreamFileMetadata) -> ZipResult<()> {
+            Ok(())
+        }
+    }
+
+    let content = content();
+    let options = FileOptions::default()
+        .compression_method(CompressionMethod::Zstd)
+        .compression_params(CompressionParams::Zstd(
+            ZstdParams::default().window_log(24),
+        ));
+    let data = write(options, &content).unwrap();
+    let limits = DecoderLimits::default().zstd_window_log_max(20);
+
+    let mut archive = ZipArchive::new(Cursor::new(data.clone())).unwrap();
+    archive.set_decoder_limits(limits);
+    let mut file = archive.by_name("file.txt").unwrap();
+    assert!(file.read_to_end(&mut Vec::new()).is_err());
+    drop(file);
+
+    archive.set_decoder_limits(DecoderLimits::default().zstd_window_log_max(24));
+    let mut decompressed = Vec::new();
+    archive
+        .by_name("file.txt")
+        .unwrap()
+        .read_to_end(&mut decompressed)
+        .unwrap();
+    assert_eq!(decompressed, content);
+
+    assert!(
+        ZipStreamReader::new(Cursor::new(data))
+            .with_decoder_limits(limits)
+            .visit(&mut Visitor)
+            .is_err()
+    );
+}
+
+#[cfg(feature = "xz")]
+#[test]
+fn xz_params() {
+    use ziperu::write::{XzFilter, XzParams};
+
+    let content = content();
+    let options = FileOptions::default().compression_method(CompressionMethod::Xz);
+    let params = XzParams::default()
+        .dict_size(1 << 16)
+        .filter(XzFilter::Delta { distance: 4 })
+        .unwrap();
+    let data = write(
+        options.compression_params(CompressionParams::Xz(params)),
+        &content,
+    )
+    .unwrap();
+    assert_eq!(read(data), content);
+    for distance in [0, 257, 512] {
+        assert!(params.filter(XzFilter::Delta { distance }).is_err());
+    }
+
+    let params = params
+        .filter(XzFilter::X86)
+        .and_then(|params| params.filter(XzFilter::Arm))
+        .unwrap();
+    assert!(params.filter(XzFilter::Sparc).is_err());
+    let data = write(
+        options.compression_params(CompressionParams::Xz(params)),
+        &content,
+    )
+    .unwrap();
+    assert_eq!(read(data), content);
+}
+
+#[cfg(all(feature = "zstd", feature = "bzip2"))]
+#[test]
+fn mismatched_params() {
+    use ziperu::write::ZstdParams;
+
+    let options = FileOptions::default()
+        .compression_method(CompressionMethod::Bzip2)
+        .compression_params(CompressionParams::Zstd(ZstdParams::default()));
+    assert!(write(options, b"content").is_err());
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use std::io::Cursor;
+use ziperu::ZipArchive;
+use ziperu::result::ZipError;
+use ziperu::write::FileOptions;
+
+const FILES: &[(&str, &str)] = &[
+    ("a/b/c.txt", "a/b/c.txt"),
+    ("./e.txt", "./e.txt"),
+    ("../evil.txt", "../evil.txt"),
+    ("/etc/passwd", "/etc/passwd"),
+    ("f/../g.txt", "f/../g.txt"),
+    ("a/d/", ""),
+];
+
+fn names<'a>(entries: impl Iterator<Item = ziperu::read::ZipDirEntry<'a>>) -> Vec<&'a str> {
+    entries.map(|entry| entry.name()).collect()
+}
+
+#[test]
+fn implicit_directories() {
+    let data = common::write_files(FILES, FileOptions::default());
+    let archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    let tree = archive.directory_tree();
+
+    assert_eq!(names(tree.read_dir("").unwrap()), ["a", "e.txt", "g.txt"]);
+    assert_eq!(names(tree.read_dir("/a/").unwrap()), ["b", "d"]);
+    assert_eq!(names(tree.read_dir("a/b").unwrap()), ["c.txt"]);
+    assert_eq!(tree.read_dir("a/d").unwrap().count(), 0);
+
+    let a = tree.metadata("a").unwrap();
+    assert!(a.is_dir());
+    assert!(a.is_implicit());
+    assert!(a.metadata().is_none());
+
+    let d = tree.metadata("a/b/../d").unwrap();
+    assert_eq!(d.path(), "a/d");
+    assert!(d.is_dir());
+    assert!(!d.is_implicit());
+    assert_eq!(d.metadata().unwrap().name(), "a/d/");
+
+    let root = tree.metadata("").unwrap();
+    assert!(root.is_dir());
+    assert_eq!(root.path(), "");
+}
+
+#[test]
+fn files() {
+    let data = common::write_files(FILES, FileOptions::default());
+    let archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    let tree = archive.directory_tree();
+
+    let file = tree.metadata("a/b/c.txt").unwrap();
+    assert!(file.is_file());
+    assert_eq!(file.name(), "c.txt");
+    let metadata = file.metadata().unwrap();
+    assert_eq!(metadata.index(), 0);
+    assert_eq!(metadata.size(), 9);
+
+    assert_eq!(
+        tree.metadata("e.txt").unwrap().metadata().unwrap().name(),
+        "./e.txt"
+    );
+    assert!(tree.exists("./g.txt"));
+    assert!(tree.exists("/a/b/c.txt"));
+    assert!(!tree.exists("f"));
+    assert!(!tree.exists("a/b/c"));
+    assert!(matches!(
+        tree.read_dir("a/b/c.txt"),
+        Err(ZipError::FileNotFound)
+    ));
+    assert!(matches!(tree.read_dir("x"), Err(ZipError::FileNotFound)));
+}
+
+#[test]
+fn unsafe_names_are_excluded() {
+    let data = common::write_files(FILES, FileOptions::default());
+    let archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    let tree = archive.directory_tree();
+    assert!(!tree.exists("evil.txt"));
+    assert!(!tree.exists("../evil.txt"));
+    assert!(!tree.exists("etc"));
+    assert!(!tree.exists("etc/passwd"));
+    // The files can still be opened by name
+    assert!(archive.file_exists("/etc/passwd"));
+}
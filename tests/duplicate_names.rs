@@ -0,0 +1,64 @@
+mod common;
+
+use std::io::Cursor;
+use ziperu::ZipArchive;
+use ziperu::read::DuplicatePolicy;
+use ziperu::result::ZipError;
+use ziperu::write::FileOptions;
+
+const FILES: &[(&str, &str)] = &[
+    ("a.txt", "first"),
+    ("b.txt", "b"),
+    ("a.txt", "second"),
+    ("a.txt", "third"),
+];
+
+#[test]
+fn all_records_are_kept() {
+    let data = common::write_files(FILES, FileOptions::default());
+    let archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    assert_eq!(archive.len(), 4);
+    assert_eq!(
+        archive.file_names().collect::<Vec<_>>(),
+        ["a.txt", "b.txt", "a.txt", "a.txt"]
+    );
+    assert_eq!(archive.duplicate_names().collect::<Vec<_>>(), ["a.txt"]);
+
+    let indices: Vec<_> = archive
+        .entries_named("a.txt")
+        .map(|entry| entry.index())
+        .collect();
+    assert_eq!(indices, [0, 2, 3]);
+    assert_eq!(archive.entries_named("b.txt").len(), 1);
+    assert_eq!(archive.entries_named("c.txt").len(), 0);
+}
+
+#[test]
+fn policy() {
+    let data = common::write_files(FILES, FileOptions::default());
+    let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    assert_eq!(archive.index_for_name("a.txt"), Some(3));
+    assert_eq!(common::read_by_name(&mut archive, "a.txt"), "third");
+
+    archive.set_duplicate_policy(DuplicatePolicy::First);
+    assert_eq!(archive.index_for_name("a.txt"), Some(0));
+    assert_eq!(common::read_by_name(&mut archive, "a.txt"), "first");
+    assert_eq!(common::read_by_name(&mut archive, "b.txt"), "b");
+    assert!(matches!(
+        archive.by_name("c.txt"),
+        Err(ZipError::FileNotFound)
+    ));
+}
+
+#[test]
+fn strict() {
+    let data = common::write_files(FILES, FileOptions::default());
+    assert!(matches!(
+        ZipArchive::new_strict(Cursor::new(data)),
+        Err(ZipError::InvalidArchive("Duplicate file name"))
+    ));
+
+    let data = common::write_files(&[("a.txt", "")], FileOptions::default());
+    let archive = ZipArchive::new_strict(Cursor::new(data)).unwrap();
+    assert_eq!(archive.duplicate_names().len(), 0);
+}
@@ -0,0 +1,73 @@
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use ziperu::result::ZipError;
+use ziperu::write::FileOptions;
+use ziperu::{CompressionMethod, DateTime, ZipArchive, ZipWriter};
+
+/// A reader counting the calls to `read` and `seek`
+struct CountingReader {
+    inner: Cursor<Vec<u8>>,
+    calls: Arc<AtomicUsize>,
+}
+
+impl Read for CountingReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        self.inner.read(buf)
+    }
+}
+
+impl Seek for CountingReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        self.inner.seek(pos)
+    }
+}
+
+#[test]
+fn metadata_without_io() {
+    let time = DateTime::from_date_and_time(2020, 2, 3, 4, 5, 6).unwrap();
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.add_directory("docs", FileOptions::default().last_modified_time(time))
+        .unwrap();
+    let options = FileOptions::default()
+        .compression_method(CompressionMethod::Stored)
+        .last_modified_time(time)
+        .unix_permissions(0o600);
+    zip.start_file("docs/readme.txt", options).unwrap();
+    zip.write_all(b"Read me").unwrap();
+    let data = zip.finish().unwrap().into_inner();
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let reader = CountingReader {
+        inner: Cursor::new(data),
+        calls: calls.clone(),
+    };
+    let archive = ZipArchive::new(reader).unwrap();
+    let calls_after_open = calls.load(Ordering::Relaxed);
+
+    let entries: Vec<_> = archive.entries().collect();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].name(), "docs/");
+    assert!(entries[0].is_dir());
+    assert_eq!(entries[0].unix_mode(), Some(0o40755));
+
+    let file = archive.metadata(1).unwrap();
+    assert_eq!(file.index(), 1);
+    assert_eq!(file.name(), "docs/readme.txt");
+    assert!(file.is_file());
+    assert_eq!(file.size(), 7);
+    assert_eq!(file.compressed_size(), 7);
+    assert_eq!(file.compression(), CompressionMethod::Stored);
+    assert_eq!(file.crc32(), crc32fast::hash(b"Read me"));
+    assert_eq!(file.unix_mode(), Some(0o100600));
+    assert_eq!(file.last_modified().year(), 2020);
+    assert_eq!(file.encryption(), None);
+    assert_eq!(file.comment(), "");
+    assert!(file.header_start() > entries[0].header_start());
+    assert!(file.central_header_start() > file.header_start());
+    assert!(matches!(archive.metadata(2), Err(ZipError::FileNotFound)));
+
+    assert_eq!(calls.load(Ordering::Relaxed), calls_after_open);
+}
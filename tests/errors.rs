@@ -0,0 +1,116 @@
+mod common;
+
+use std::io::{self, Cursor, Read};
+use ziperu::result::{ErrorKind, ZipError};
+use ziperu::write::FileOptions;
+use ziperu::{CompressionMethod, ZipArchive};
+
+const FILES: &[(&str, &str)] = &[("first.txt", "first"), ("second.txt", "second")];
+
+#[test]
+fn crc_mismatch() {
+    let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+    let mut data = common::write_files(FILES, options);
+    let (header_start, data_start, crc32) = {
+        let mut zip = ZipArchive::new(Cursor::new(&data)).unwrap();
+        let file = zip.by_name("second.txt").unwrap();
+        (file.header_start(), file.data_start(), file.crc32())
+    };
+    data[data_start as usize] ^= 0xff;
+
+    let mut zip = ZipArchive::new(Cursor::new(data)).unwrap();
+    let err = zip
+        .by_name("second.txt")
+        .unwrap()
+        .read_to_end(&mut Vec::new())
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    // The I/O error wraps the structured error
+    let inner = err.get_ref().unwrap().downcast_ref::<ZipError>().unwrap();
+    let details = inner.details().unwrap();
+    assert!(matches!(
+        details.kind(),
+        ErrorKind::CrcMismatch { expected, actual } if *expected == crc32 && *actual != crc32
+    ));
+    assert_eq!(details.name(), Some("second.txt"));
+    assert_eq!(details.index(), Some(1));
+    assert_eq!(details.offset(), Some(header_start));
+
+    // Converting back and forth is lossless
+    let err = ZipError::from(err);
+    assert!(err.details().is_some());
+    let err = io::Error::from(err);
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(ZipError::from(err).details().is_some());
+}
+
+#[test]
+fn bad_signature() {
+    let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+    let mut data = common::write_files(FILES, options);
+    let header_start = ZipArchive::new(Cursor::new(&data))
+        .unwrap()
+        .by_name("second.txt")
+        .unwrap()
+        .header_start();
+    data[header_start as usize] = 0;
+
+    let mut zip = ZipArchive::new(Cursor::new(data)).unwrap();
+    let err = match zip.by_index(1) {
+        Err(err) => err,
+        Ok(_) => panic!("the local header should be rejected"),
+    };
+    let details = err.details().unwrap();
+    assert_eq!(
+        details.kind(),
+        &ErrorKind::BadSignature {
+            expected: 0x04034b50,
+            found: 0x04034b00,
+        }
+    );
+    assert_eq!(details.name(), Some("second.txt"));
+    assert_eq!(details.index(), Some(1));
+    assert_eq!(details.offset(), Some(header_start));
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "invalid Zip archive: Invalid signature (expected 0x04034b50, found 0x04034b00) \
+             in entry \"second.txt\" (index 1) at offset {header_start}"
+        )
+    );
+}
+
+#[test]
+fn unsupported_method() {
+    let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+    let mut data = common::write_files(FILES, options);
+    let eocd = data.len() - 22;
+    let central_start = u32::from_le_bytes(data[eocd + 16..eocd + 20].try_into().unwrap());
+    // Set the compression method of the second central directory header to PPMd
+    let method = central_start as usize + 46 + "first.txt".len() + 10;
+    data[method..method + 2].copy_from_slice(&98u16.to_le_bytes());
+
+    let mut zip = ZipArchive::new(Cursor::new(data)).unwrap();
+    let err = match zip.by_name("second.txt") {
+        Err(err) => err,
+        Ok(_) => panic!("the compression method should be rejected"),
+    };
+    assert_eq!(
+        err.details().unwrap().kind(),
+        &ErrorKind::UnsupportedMethod(98)
+    );
+    assert_eq!(io::Error::from(err).kind(), io::ErrorKind::Unsupported);
+}
+
+#[test]
+fn io_errors_are_unwrapped() {
+    let err = io::Error::new(io::ErrorKind::TimedOut, "timeout");
+    let err = io::Error::from(ZipError::from(err));
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    assert_eq!(err.to_string(), "timeout");
+
+    let err = io::Error::from(ZipError::FileNotFound);
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    assert!(matches!(ZipError::from(err), ZipError::FileNotFound));
+}
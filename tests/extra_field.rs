@@ -0,0 +1,78 @@
+use byteorder::{LittleEndian, WriteBytesExt};
+use std::io::{Cursor, Write};
+use ziperu::extra_field::ExtendedTimestamp;
+use ziperu::result::ZipResult;
+use ziperu::write::FileOptions;
+use ziperu::{CompressionMethod, ExtraField, ZipArchive, ZipWriter};
+
+#[test]
+fn typed_extra_fields() {
+    let local_fields = [
+        ExtraField::ExtendedTimestamp(ExtendedTimestamp {
+            mod_time: Some(1_600_000_000),
+            ac_time: Some(1_600_000_001),
+            cr_time: None,
+        }),
+        ExtraField::Unix {
+            uid: 1000,
+            gid: 1000,
+        },
+    ];
+    let central_fields = [ExtraField::ExtendedTimestamp(ExtendedTimestamp {
+        mod_time: Some(1_600_000_000),
+        ac_time: None,
+        cr_time: None,
+    })];
+
+    let mut archive = Cursor::new(Vec::new());
+    {
+        let mut zip = ZipWriter::new(&mut archive);
+        let options = FileOptions::default()
+            .compression_method(CompressionMethod::Stored)
+            .extra_fields(&local_fields);
+
+        zip.start_file("both.txt", options).unwrap();
+        zip.write_all(b"file data").unwrap();
+
+        zip.start_file("central.txt", options.central_extra_fields(&central_fields))
+            .unwrap();
+        zip.write_all(b"file data").unwrap();
+
+        zip.start_file_with_extra_data("raw.txt", options).unwrap();
+        zip.write_u16::<LittleEndian>(0xbeef).unwrap();
+        zip.write_u16::<LittleEndian>(4).unwrap();
+        zip.write_all(b"data").unwrap();
+        zip.end_extra_data().unwrap();
+        zip.write_all(b"file data").unwrap();
+
+        zip.finish().unwrap();
+    }
+
+    let mut zip = ZipArchive::new(archive).unwrap();
+    let fields = |zip: &mut ZipArchive<_>, name| -> Vec<ExtraField> {
+        let mut file = zip.by_name(name).unwrap();
+        let mut content = String::new();
+        std::io::Read::read_to_string(&mut file, &mut content).unwrap();
+        assert_eq!(content, "file data");
+        file.extra_fields().collect::<ZipResult<_>>().unwrap()
+    };
+
+    assert_eq!(fields(&mut zip, "both.txt"), local_fields);
+    assert_eq!(fields(&mut zip, "central.txt"), central_fields);
+    let mut expected = local_fields.to_vec();
+    expected.push(ExtraField::Unknown {
+        header_id: 0xbeef,
+        data: b"data".to_vec(),
+    });
+    assert_eq!(fields(&mut zip, "raw.txt"), expected);
+}
+
+#[test]
+fn managed_extra_fields_are_rejected() {
+    let fields = [ExtraField::Zip64(Default::default())];
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    assert!(
+        zip.start_file("file.txt", FileOptions::default().extra_fields(&fields))
+            .is_err()
+    );
+}
@@ -0,0 +1,188 @@
+mod common;
+
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use ziperu::extra_field::ExtendedTimestamp;
+use ziperu::read::{ExtractOptions, OverwritePolicy};
+use ziperu::result::ZipError;
+use ziperu::write::FileOptions;
+use ziperu::{DateTime, ExtraField, ZipArchive, ZipWriter};
+
+fn archive() -> ZipArchive<Cursor<Vec<u8>>> {
+    let timestamp = [ExtraField::ExtendedTimestamp(ExtendedTimestamp {
+        mod_time: Some(1_000_000_000),
+        ..Default::default()
+    })];
+    let modified = DateTime::from_date_and_time(2018, 11, 17, 10, 38, 30).unwrap();
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default()
+        .last_modified_time(modified)
+        .unix_permissions(0o600);
+    zip.add_directory("dir/", options.unix_permissions(0o700))
+        .unwrap();
+    zip.start_file("dir/a.txt", options).unwrap();
+    zip.write_all(b"a").unwrap();
+    zip.start_file("b.txt", options.extra_fields(&timestamp))
+        .unwrap();
+    zip.write_all(b"bb").unwrap();
+    ZipArchive::new(zip.finish().unwrap()).unwrap()
+}
+
+#[test]
+fn overwrite_policy() {
+    let dir = common::temp_dir("extract-overwrite");
+    fs::write(dir.join("b.txt"), b"existing").unwrap();
+
+    archive()
+        .extract_with_options(
+            &dir,
+            ExtractOptions::default().overwrite(OverwritePolicy::Skip),
+        )
+        .unwrap();
+    assert_eq!(fs::read(dir.join("b.txt")).unwrap(), b"existing");
+    assert_eq!(fs::read(dir.join("dir/a.txt")).unwrap(), b"a");
+
+    let err = archive()
+        .extract_with_options(
+            &dir,
+            ExtractOptions::default().overwrite(OverwritePolicy::Error),
+        )
+        .unwrap_err();
+    assert!(matches!(err, ZipError::Io(e) if e.kind() == std::io::ErrorKind::AlreadyExists));
+
+    archive().extract(&dir).unwrap();
+    assert_eq!(fs::read(dir.join("b.txt")).unwrap(), b"bb");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn filter_and_rename() {
+    let dir = common::temp_dir("extract-filter");
+    let options = ExtractOptions::default().filter(|path| {
+        path.starts_with("dir")
+            .then(|| Path::new("renamed").join(path.strip_prefix("dir").unwrap()))
+    });
+    archive().extract_with_options(&dir, options).unwrap();
+    assert_eq!(fs::read(dir.join("renamed/a.txt")).unwrap(), b"a");
+    assert!(!dir.join("b.txt").exists());
+    assert!(!dir.join("dir").exists());
+
+    for path in ["../escape", "/absolute"] {
+        let options = ExtractOptions::default().filter(|_| Some(PathBuf::from(path)));
+        archive().extract_with_options(&dir, options).unwrap_err();
+    }
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn restore_modified_time() {
+    let dir = common::temp_dir("extract-mtime");
+    archive()
+        .extract_with_options(&dir, ExtractOptions::default().restore_modified_time(true))
+        .unwrap();
+
+    let modified = |path: &str| fs::metadata(dir.join(path)).unwrap().modified().unwrap();
+    // 2018-11-17 10:38:30 UTC
+    let dos_time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_542_451_110);
+    assert_eq!(modified("dir/a.txt"), dos_time);
+    assert_eq!(modified("dir"), dos_time);
+    assert_eq!(
+        modified("b.txt"),
+        SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000)
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn unix_mode_toggle() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode =
+        |dir: &Path, path: &str| fs::metadata(dir.join(path)).unwrap().permissions().mode() & 0o777;
+
+    let dir = common::temp_dir("extract-mode");
+    archive().extract(&dir).unwrap();
+    assert_eq!(mode(&dir, "b.txt"), 0o600);
+    assert_eq!(mode(&dir, "dir"), 0o700);
+    fs::remove_dir_all(&dir).unwrap();
+
+    fs::create_dir_all(&dir).unwrap();
+    archive()
+        .extract_with_options(&dir, ExtractOptions::default().apply_unix_mode(false))
+        .unwrap();
+    assert_ne!(mode(&dir, "b.txt"), 0o600);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn progress() {
+    let dir = common::temp_dir("extract-progress");
+    let mut reports = Vec::new();
+    let options = ExtractOptions::default().progress(|progress| {
+        reports.push((
+            progress.path.to_path_buf(),
+            progress.entries_extracted,
+            progress.total_entries,
+            progress.bytes_written,
+        ))
+    });
+    archive().extract_with_options(&dir, options).unwrap();
+    assert_eq!(
+        reports,
+        [
+            (PathBuf::from("dir"), 1, Some(3), 0),
+            (PathBuf::from("dir/a.txt"), 2, Some(3), 1),
+            (PathBuf::from("b.txt"), 3, Some(3), 3),
+        ]
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn atomic() {
+    let parent = common::temp_dir("extract-atomic");
+    let dir = parent.join("out");
+    archive()
+        .extract_with_options(&dir, ExtractOptions::default().atomic(true))
+        .unwrap();
+    assert_eq!(fs::read(dir.join("dir/a.txt")).unwrap(), b"a");
+
+    // The destination must be empty
+    archive()
+        .extract_with_options(&dir, ExtractOptions::default().atomic(true))
+        .unwrap_err();
+    assert_eq!(fs::read_dir(&parent).unwrap().count(), 1);
+    fs::remove_dir_all(&dir).unwrap();
+    fs::create_dir(&dir).unwrap();
+    archive()
+        .extract_with_options(&dir, ExtractOptions::default().atomic(true))
+        .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    // Corrupt the contents of the last entry, which is only noticed when
+    // its checksum is verified after the other entries were extracted
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(ziperu::CompressionMethod::Stored);
+    zip.start_file("first.txt", options).unwrap();
+    zip.write_all(b"first").unwrap();
+    zip.start_file("second.txt", options).unwrap();
+    zip.write_all(b"second").unwrap();
+    let mut data = zip.finish().unwrap().into_inner();
+    let offset = ZipArchive::new(Cursor::new(&data))
+        .unwrap()
+        .by_name("second.txt")
+        .unwrap()
+        .data_start() as usize;
+    data[offset] ^= 0xff;
+
+    let mut zip = ZipArchive::new(Cursor::new(data)).unwrap();
+    zip.extract_with_options(&dir, ExtractOptions::default().atomic(true))
+        .unwrap_err();
+    assert!(!dir.exists());
+    assert_eq!(fs::read_dir(&parent).unwrap().count(), 0);
+    fs::remove_dir_all(parent).unwrap();
+}
@@ -0,0 +1,114 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use ziperu::read::{ExtractOptions, OverwritePolicy};
+use ziperu::write::FileOptions;
+
+#[test]
+fn symlinks_are_recreated() {
+    let dir = common::temp_dir("extract-symlinks");
+    let mut zip = common::archive(|zip| {
+        zip.start_file("data/file.txt", FileOptions::default())
+            .unwrap();
+        zip.write_all(b"content").unwrap();
+        zip.add_symlink("data/link", "file.txt", FileOptions::default())
+            .unwrap();
+        zip.add_symlink("dirlink", "data", FileOptions::default())
+            .unwrap();
+    });
+    assert!(zip.by_name("data/link").unwrap().is_symlink());
+    assert!(!zip.by_name("data/file.txt").unwrap().is_symlink());
+
+    zip.extract(&dir).unwrap();
+    assert_eq!(
+        fs::read_link(dir.join("data/link")).unwrap(),
+        Path::new("file.txt")
+    );
+    assert_eq!(fs::read(dir.join("dirlink/link")).unwrap(), b"content");
+
+    // Extracting again replaces the links instead of following them
+    zip.extract(&dir).unwrap();
+    assert!(
+        fs::symlink_metadata(dir.join("dirlink"))
+            .unwrap()
+            .file_type()
+            .is_symlink()
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn symlinks_replace_existing_files() {
+    let dir = common::temp_dir("extract-symlink-over-file");
+    fs::write(dir.join("link"), b"existing").unwrap();
+    let zip = || {
+        common::archive(|zip| {
+            zip.start_file("file.txt", FileOptions::default()).unwrap();
+            zip.add_symlink("link", "file.txt", FileOptions::default())
+                .unwrap();
+        })
+    };
+
+    zip()
+        .extract_with_options(
+            &dir,
+            ExtractOptions::default().overwrite(OverwritePolicy::Skip),
+        )
+        .unwrap();
+    assert_eq!(fs::read(dir.join("link")).unwrap(), b"existing");
+
+    zip().extract(&dir).unwrap();
+    assert_eq!(
+        fs::read_link(dir.join("link")).unwrap(),
+        Path::new("file.txt")
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn escaping_symlinks_are_rejected() {
+    for target in ["/etc", "..", "a/../../..", "../outside"] {
+        let dir = common::temp_dir("extract-escaping-symlink");
+        let mut zip = common::archive(|zip| {
+            zip.add_symlink("link", target, FileOptions::default())
+                .unwrap();
+        });
+        zip.extract(&dir).unwrap_err();
+        assert!(fs::symlink_metadata(dir.join("link")).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
+
+#[test]
+fn writing_through_symlinks_is_rejected() {
+    let dir = common::temp_dir("extract-through-symlink");
+    let outside = common::temp_dir("extract-through-symlink-outside");
+    let mut zip = common::archive(|zip| {
+        zip.add_directory("inner/", FileOptions::default()).unwrap();
+        zip.add_symlink("link", "inner", FileOptions::default())
+            .unwrap();
+        zip.start_file("link/file.txt", FileOptions::default())
+            .unwrap();
+        zip.write_all(b"content").unwrap();
+    });
+    zip.extract(&dir).unwrap_err();
+    assert!(!dir.join("inner/file.txt").exists());
+
+    // A symlink already present in the destination is not followed either
+    fs::remove_file(dir.join("link")).unwrap();
+    std::os::unix::fs::symlink(&outside, dir.join("link")).unwrap();
+    let mut zip = common::archive(|zip| {
+        zip.start_file("link/file.txt", FileOptions::default())
+            .unwrap();
+        zip.write_all(b"content").unwrap();
+    });
+    zip.extract(&dir).unwrap_err();
+    assert!(!outside.join("file.txt").exists());
+
+    fs::remove_dir_all(dir).unwrap();
+    fs::remove_dir_all(outside).unwrap();
+}
@@ -0,0 +1,140 @@
+mod common;
+
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use ziperu::ZipArchive;
+use ziperu::read::Glob;
+use ziperu::result::ZipError;
+use ziperu::unstable::stream::ZipStreamReader;
+use ziperu::unstable::write::FileOptionsExt;
+use ziperu::write::FileOptions;
+
+const FILES: &[(&str, &str)] = &[
+    ("assets/icons/", ""),
+    ("assets/logo.png", "assets/logo.png"),
+    ("assets/icons/small.png", "assets/icons/small.png"),
+    ("assets/icons/small.svg", "assets/icons/small.svg"),
+    ("./docs/readme.png", "./docs/readme.png"),
+    ("main.rs", "main.rs"),
+];
+
+fn files(root: &Path) -> Vec<String> {
+    let mut files = Vec::new();
+    let mut pending = vec![root.to_owned()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            match path.is_dir() {
+                true => pending.push(path),
+                false => {
+                    let relative = path.strip_prefix(root).unwrap();
+                    files.push(relative.to_str().unwrap().replace('\\', "/"));
+                }
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+#[test]
+fn glob_entries() {
+    let data = common::write_archive(|zip| {
+        common::add_files(zip, FILES, FileOptions::default());
+        let options = FileOptions::default().with_deprecated_encryption(b"password");
+        common::add_files(zip, &[("assets/secret.txt", "secret")], options);
+    });
+    let archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    let names: Vec<_> = archive.glob("**/*.png").map(|entry| entry.name()).collect();
+    assert_eq!(
+        names,
+        [
+            "assets/logo.png",
+            "assets/icons/small.png",
+            "./docs/readme.png"
+        ]
+    );
+    let names: Vec<_> = archive.glob("assets/*").map(|entry| entry.name()).collect();
+    assert_eq!(
+        names,
+        ["assets/icons/", "assets/logo.png", "assets/secret.txt"]
+    );
+    assert_eq!(archive.glob("*.txt").count(), 0);
+}
+
+#[test]
+fn extract_matching_glob() {
+    let data = common::write_archive(|zip| {
+        common::add_files(zip, FILES, FileOptions::default());
+        let options = FileOptions::default().with_deprecated_encryption(b"password");
+        common::add_files(zip, &[("assets/secret.txt", "secret")], options);
+    });
+    let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    let dir = common::temp_dir("extract-matching-glob");
+    // The encrypted entry is not selected, so no password is needed
+    archive
+        .extract_matching(&dir, Glob::new("assets/**/*.png"))
+        .unwrap();
+    assert_eq!(files(&dir), ["assets/icons/small.png", "assets/logo.png"]);
+    assert_eq!(
+        fs::read(dir.join("assets/icons/small.png")).unwrap(),
+        b"assets/icons/small.png"
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn extract_matching_predicate() {
+    let data = common::write_archive(|zip| {
+        common::add_files(zip, FILES, FileOptions::default());
+        let options = FileOptions::default().with_deprecated_encryption(b"password");
+        common::add_files(zip, &[("assets/secret.txt", "secret")], options);
+    });
+    let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    let dir = common::temp_dir("extract-matching-predicate");
+    let mut seen = Vec::new();
+    archive
+        .extract_matching(&dir, |path: &Path| {
+            seen.push(path.to_owned());
+            path.starts_with("docs")
+        })
+        .unwrap();
+    assert_eq!(files(&dir), ["docs/readme.png"]);
+    assert!(seen.contains(&PathBuf::from("docs/readme.png")));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn extract_matching_stream() {
+    let dir = common::temp_dir("extract-matching-stream");
+    // The stream reader doesn't support encrypted entries
+    let data = common::write_files(FILES, FileOptions::default());
+    ZipStreamReader::new(Cursor::new(data))
+        .extract_matching(&dir, Glob::new("**/*.png"))
+        .unwrap();
+    assert_eq!(
+        files(&dir),
+        [
+            "assets/icons/small.png",
+            "assets/logo.png",
+            "docs/readme.png"
+        ]
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn extract_matching_invalid_path() {
+    let data = common::write_files(
+        &[("a.txt", ""), ("../evil.txt", "")],
+        FileOptions::default(),
+    );
+    let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    let dir = common::temp_dir("extract-matching-invalid");
+    assert!(matches!(
+        archive.extract_matching(&dir, Glob::new("*.txt")),
+        Err(ZipError::InvalidArchive(_))
+    ));
+    fs::remove_dir_all(&dir).unwrap();
+}
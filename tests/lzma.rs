@@ -1,7 +1,10 @@
 #![cfg(feature = "lzma")]
 
-use std::io::{Cursor, Read};
-use ziperu::ZipArchive;
+use std::io::{Cursor, Read, Write};
+use ziperu::read::DecoderLimits;
+use ziperu::result::{ErrorKind, ZipError};
+use ziperu::write::FileOptions;
+use ziperu::{CompressionMethod, ZipArchive, ZipWriter};
 
 #[test]
 fn decompress_lzma() {
@@ -18,3 +21,32 @@ fn decompress_lzma() {
         .expect("couldn't read encrypted and compressed file");
     assert_eq!(b"This is a test", &content[..]);
 }
+
+#[test]
+fn lzma_dict_size_limit() {
+    // An LZMA header announcing a 64 MiB dictionary, the limit is checked before decoding
+    let mut header = vec![9, 20, 5, 0, 0x5d];
+    header.extend_from_slice(&(64u32 << 20).to_le_bytes());
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+    zip.start_file("test.txt", options).unwrap();
+    zip.write_all(&header).unwrap();
+    let mut data = zip.finish().unwrap().into_inner();
+    // Change the method of the local and central headers to LZMA
+    for (signature, method_offset) in [(b"PK\x03\x04", 8), (b"PK\x01\x02", 10)] {
+        let start = data
+            .windows(signature.len())
+            .position(|window| window == signature)
+            .unwrap();
+        data[start + method_offset] = 14;
+    }
+
+    let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    archive.set_decoder_limits(DecoderLimits::default().lzma_dict_size_max(1 << 20));
+    let mut file = archive.by_name("test.txt").unwrap();
+    let err = ZipError::from(file.read_to_end(&mut Vec::new()).unwrap_err());
+    assert!(matches!(
+        err.details().unwrap().kind(),
+        ErrorKind::LimitExceeded { limit: 0x100000 }
+    ));
+}
@@ -0,0 +1,118 @@
+use std::io::{Cursor, Read, Write};
+use ziperu::result::ZipError;
+use ziperu::unstable::write::FileOptionsExt;
+use ziperu::write::FileOptions;
+use ziperu::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// Returns the offset of the central directory header of the file `name`.
+#[cfg(feature = "aes-crypto")]
+fn central_header(data: &[u8], name: &[u8]) -> usize {
+    (0..data.len() - 46)
+        .find(|&i| {
+            data[i..].starts_with(b"PK\x01\x02")
+                && data[i + 46..].starts_with(name)
+                && u16::from_le_bytes([data[i + 28], data[i + 29]]) as usize == name.len()
+        })
+        .unwrap()
+}
+
+#[cfg(feature = "aes-crypto")]
+#[test]
+fn aes_entry_shorter_than_its_header() {
+    let mut data = include_bytes!("data/aes_archive.zip").to_vec();
+    let offset = central_header(&data, b"secret_data_256_uncompressed");
+    data[offset + 20..offset + 24].copy_from_slice(&5u32.to_le_bytes());
+
+    let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    assert!(matches!(
+        archive.by_name_decrypt("secret_data_256_uncompressed", b"helloworld"),
+        Err(ZipError::InvalidArchive(_))
+    ));
+}
+
+#[cfg(feature = "aes-crypto")]
+#[test]
+fn aes_field_on_unencrypted_entry() {
+    let mut data = include_bytes!("data/aes_archive.zip").to_vec();
+    let offset = central_header(&data, b"secret_data_256_uncompressed");
+    data[offset + 8] &= !1;
+
+    let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    assert!(matches!(
+        archive.by_name("secret_data_256_uncompressed"),
+        Err(ZipError::InvalidArchive(_))
+    ));
+}
+
+#[test]
+fn append_with_invalid_directory_offset() {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+    zip.start_file("file.txt", options).unwrap();
+    zip.write_all(b"content").unwrap();
+    let mut data = zip.finish().unwrap().into_inner();
+
+    // Point the central directory past the end of central directory record
+    let eocd = data.len() - 22;
+    data[eocd + 16..eocd + 20].copy_from_slice(&(eocd as u32 + 1).to_le_bytes());
+
+    assert!(ZipWriter::new_append(Cursor::new(data)).is_err());
+}
+
+#[test]
+fn encrypted_file_with_extra_data() {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().with_deprecated_encryption(b"password");
+    assert!(matches!(
+        zip.start_file_with_extra_data("file.txt", options),
+        Err(ZipError::UnsupportedArchive(_))
+    ));
+
+    // The writer is still usable afterwards
+    zip.start_file("file.txt", options).unwrap();
+    zip.write_all(b"content").unwrap();
+    let mut archive = ZipArchive::new(zip.finish().unwrap()).unwrap();
+    let mut content = String::new();
+    archive
+        .by_name_decrypt("file.txt", b"password")
+        .unwrap()
+        .unwrap()
+        .read_to_string(&mut content)
+        .unwrap();
+    assert_eq!(content, "content");
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn read_failing_entry_twice() {
+    use ziperu::write::{CompressionParams, ZstdParams};
+    use ziperu::zstd_dictionary::ZstdDictionary;
+
+    let samples: Vec<String> = (0..500)
+        .map(|i| format!(r#"{{"id": {i}, "name": "document {}"}}"#, i * 7))
+        .collect();
+    let dictionary = ZstdDictionary::train(&samples, 2048).unwrap();
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.add_zstd_dictionary(dictionary.clone()).unwrap();
+    let options = FileOptions::default()
+        .compression_method(CompressionMethod::Zstd)
+        .compression_params(CompressionParams::Zstd(
+            ZstdParams::default().dictionary(dictionary.id()),
+        ));
+    zip.start_file("file.json", options).unwrap();
+    zip.write_all(samples[0].as_bytes()).unwrap();
+    let mut data = zip.finish().unwrap().into_inner();
+    // Rename the dictionary entry in the local and central headers
+    let name = b".zstd-dictionaries/";
+    for i in 0..data.len() - name.len() {
+        if data[i..].starts_with(name) {
+            data[i + 1] = b'Z';
+        }
+    }
+
+    let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    let mut file = archive.by_name("file.json").unwrap();
+    let first = file.read(&mut [0; 16]).unwrap_err();
+    let second = file.read(&mut [0; 16]).unwrap_err();
+    assert_eq!(first.to_string(), second.to_string());
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use std::io::Cursor;
+use ziperu::ZipArchive;
+use ziperu::read::NameMatching;
+use ziperu::result::{ErrorKind, ZipError};
+use ziperu::write::FileOptions;
+
+#[test]
+fn case_insensitive() {
+    let data = common::write_files(
+        &[
+            ("Docs/README.md", "Docs/README.md"),
+            ("src/Main.rs", "src/Main.rs"),
+        ],
+        FileOptions::default(),
+    );
+    let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    assert!(!archive.file_exists("docs/readme.md"));
+    assert!(matches!(
+        archive.by_name("docs/readme.md"),
+        Err(ZipError::FileNotFound)
+    ));
+
+    archive.set_name_matching(NameMatching::exact().case_insensitive(true));
+    assert!(archive.file_exists("docs/readme.md"));
+    assert_eq!(archive.find_name("docs/readme.md").unwrap(), 0);
+    assert_eq!(archive.index_for_name("docs/readme.md"), None);
+    assert_eq!(
+        common::read_by_name(&mut archive, "docs/readme.md"),
+        "Docs/README.md"
+    );
+    assert_eq!(
+        common::read_by_name(&mut archive, "SRC/MAIN.RS"),
+        "src/Main.rs"
+    );
+    assert_eq!(archive.ambiguous_names().count(), 0);
+
+    archive.set_name_matching(NameMatching::exact());
+    assert!(!archive.file_exists("docs/readme.md"));
+}
+
+#[test]
+fn ambiguous() {
+    let data = common::write_files(
+        &[
+            ("readme", "readme"),
+            ("README", "README"),
+            ("other", "other"),
+        ],
+        FileOptions::default(),
+    );
+    let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    archive.set_name_matching(NameMatching::exact().case_insensitive(true));
+    assert_eq!(
+        archive.ambiguous_names().collect::<Vec<_>>(),
+        [["readme", "README"]]
+    );
+    // Exact matches are preferred
+    assert_eq!(common::read_by_name(&mut archive, "README"), "README");
+    assert!(archive.file_exists("ReadMe"));
+    match archive.by_name("ReadMe") {
+        Err(ZipError::Detailed(details)) => assert_eq!(
+            details.kind(),
+            &ErrorKind::AmbiguousName {
+                names: vec!["readme".to_owned(), "README".to_owned()]
+            }
+        ),
+        other => panic!("unexpected result {:?}", other.map(|file| file.size())),
+    }
+}
+
+#[cfg(feature = "unicode-normalization")]
+#[test]
+fn unicode_normalized() {
+    // Written in NFD, as on macOS
+    let data = common::write_files(
+        &[("Cafe\u{301}/Me\u{301}nu.txt", "Cafe\u{301}/Me\u{301}nu.txt")],
+        FileOptions::default(),
+    );
+    let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    assert!(!archive.file_exists("Caf\u{e9}/M\u{e9}nu.txt"));
+
+    archive.set_name_matching(NameMatching::exact().unicode_normalized(true));
+    assert_eq!(
+        common::read_by_name(&mut archive, "Caf\u{e9}/M\u{e9}nu.txt"),
+        "Cafe\u{301}/Me\u{301}nu.txt"
+    );
+    assert!(!archive.file_exists("caf\u{e9}/m\u{e9}nu.txt"));
+
+    archive.set_name_matching(
+        NameMatching::exact()
+            .unicode_normalized(true)
+            .case_insensitive(true),
+    );
+    assert!(archive.file_exists("CAF\u{c9}/M\u{c9}NU.TXT"));
+}
@@ -0,0 +1,114 @@
+mod common;
+
+use std::io::{Cursor, Read};
+use std::sync::Arc;
+use std::thread;
+use ziperu::ZipArchive;
+use ziperu::read::ZipFile;
+use ziperu::result::ZipError;
+use ziperu::unstable::write::FileOptionsExt;
+use ziperu::write::FileOptions;
+
+fn archive() -> Arc<[u8]> {
+    let (first, second) = ("first ".repeat(10_000), "second ".repeat(10_000));
+    let files = [("first.txt", &*first), ("second.txt", &*second)];
+    common::write_archive(|zip| {
+        common::add_files(zip, &files, FileOptions::default());
+        let options = FileOptions::default().with_deprecated_encryption(b"password");
+        common::add_files(zip, &[("secret.txt", "secret")], options);
+    })
+    .into()
+}
+
+fn read(mut file: impl Read) -> String {
+    let mut content = String::new();
+    file.read_to_string(&mut content).unwrap();
+    content
+}
+
+struct Entries {
+    files: Vec<ZipFile<'static, dyn Read + Send>>,
+}
+
+#[test]
+fn outlives_archive() {
+    let archive = ZipArchive::new(Cursor::new(archive())).unwrap();
+    let entries = Entries {
+        files: vec![
+            archive.by_index_owned(1).unwrap(),
+            archive.by_name_owned("first.txt").unwrap(),
+        ],
+    };
+    drop(archive);
+
+    let mut files = entries.files.into_iter();
+    assert_eq!(read(files.next().unwrap()), "second ".repeat(10_000));
+    assert_eq!(read(files.next().unwrap()), "first ".repeat(10_000));
+}
+
+#[test]
+fn read_on_other_threads() {
+    let archive = ZipArchive::new(Cursor::new(archive())).unwrap();
+    let threads: Vec<_> = ["first.txt", "second.txt"]
+        .into_iter()
+        .map(|name| {
+            let file = archive.by_name_owned(name).unwrap();
+            thread::spawn(move || read(file))
+        })
+        .collect();
+    let contents: Vec<_> = threads
+        .into_iter()
+        .map(|thread| thread.join().unwrap())
+        .collect();
+    assert_eq!(
+        contents,
+        ["first ".repeat(10_000), "second ".repeat(10_000)]
+    );
+}
+
+#[test]
+fn interleaved_reads() {
+    let archive = ZipArchive::new(Cursor::new(archive())).unwrap();
+    let mut first = archive.by_name_owned("first.txt").unwrap();
+    let mut second = archive.by_name_owned("second.txt").unwrap();
+    assert_eq!(first.name(), "first.txt");
+    assert_eq!(second.size(), 70_000);
+
+    let (mut first_content, mut second_content) = (Vec::new(), Vec::new());
+    let mut buffer = [0; 1000];
+    loop {
+        let read_first = first.read(&mut buffer).unwrap();
+        first_content.extend_from_slice(&buffer[..read_first]);
+        let read_second = second.read(&mut buffer).unwrap();
+        second_content.extend_from_slice(&buffer[..read_second]);
+        if read_first == 0 && read_second == 0 {
+            break;
+        }
+    }
+    assert_eq!(first_content, "first ".repeat(10_000).as_bytes());
+    assert_eq!(second_content, "second ".repeat(10_000).as_bytes());
+
+    // The archive can still be used
+    let mut archive = archive;
+    assert_eq!(read(archive.by_index(0).unwrap()), "first ".repeat(10_000));
+}
+
+#[test]
+fn errors_and_passwords() {
+    let mut archive = ZipArchive::new(Cursor::new(archive())).unwrap();
+    assert!(matches!(
+        archive.by_name_owned("missing.txt"),
+        Err(ZipError::FileNotFound)
+    ));
+    assert!(matches!(
+        archive.by_index_owned(3),
+        Err(ZipError::FileNotFound)
+    ));
+    assert!(matches!(
+        archive.by_name_owned("secret.txt"),
+        Err(ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED))
+    ));
+
+    archive.set_password_provider(|_| Some(b"password".to_vec()));
+    assert_eq!(read(archive.by_name_owned("secret.txt").unwrap()), "secret");
+}
@@ -0,0 +1,102 @@
+use std::io::{Cursor, Write};
+use ziperu::write::{FileOptions, Reproducible};
+use ziperu::{DateTime, ZipArchive, ZipWriter};
+
+fn write_archive(
+    reproducible: Reproducible,
+    time: DateTime,
+    file_mode: u32,
+    dir_mode: u32,
+) -> Vec<u8> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.set_reproducible(Some(reproducible));
+    let options = FileOptions::default().last_modified_time(time);
+    zip.add_directory("src", options.unix_permissions(dir_mode))
+        .unwrap();
+    zip.start_file("src/main.rs", options.unix_permissions(file_mode))
+        .unwrap();
+    zip.write_all(b"fn main() {}\n").unwrap();
+    zip.start_file("build.sh", options.unix_permissions(file_mode | 0o100))
+        .unwrap();
+    zip.write_all(b"#!/bin/sh\ncargo build\n").unwrap();
+    zip.add_symlink("run", "build.sh", options).unwrap();
+    // Files without explicit options use the current time
+    zip.start_file("README", FileOptions::default()).unwrap();
+    zip.write_all(b"Hello\n").unwrap();
+    zip.finish().unwrap().into_inner()
+}
+
+#[test]
+fn identical_inputs_give_identical_bytes() {
+    // Independent of SOURCE_DATE_EPOCH
+    let reproducible = Reproducible::new().epoch(DateTime::default());
+    let first = write_archive(
+        reproducible,
+        DateTime::from_date_and_time(2021, 3, 4, 5, 6, 8).unwrap(),
+        0o600,
+        0o700,
+    );
+    let second = write_archive(
+        reproducible,
+        DateTime::from_date_and_time(2024, 7, 8, 9, 10, 12).unwrap(),
+        0o664,
+        0o775,
+    );
+    assert_eq!(first, second);
+
+    let mut archive = ZipArchive::new(Cursor::new(first)).unwrap();
+    let expected = [
+        ("src/", 0o40755),
+        ("src/main.rs", 0o100644),
+        ("build.sh", 0o100755),
+        ("run", 0o120777),
+        ("README", 0o100644),
+    ];
+    for (index, (name, mode)) in expected.into_iter().enumerate() {
+        let file = archive.by_index(index).unwrap();
+        assert_eq!(file.name(), name);
+        assert_eq!(file.unix_mode(), Some(mode), "{name}");
+        let modified = file.last_modified();
+        assert_eq!(
+            (modified.datepart(), modified.timepart()),
+            (
+                DateTime::default().datepart(),
+                DateTime::default().timepart()
+            )
+        );
+    }
+}
+
+#[test]
+fn times_are_clamped_to_epoch() {
+    let epoch = DateTime::from_date_and_time(2022, 1, 1, 0, 0, 0).unwrap();
+    let before = DateTime::from_date_and_time(2020, 6, 1, 12, 0, 0).unwrap();
+    let archive = write_archive(Reproducible::new().epoch(epoch), before, 0o644, 0o755);
+    let mut archive = ZipArchive::new(Cursor::new(archive)).unwrap();
+    assert_eq!(
+        archive
+            .by_name("src/main.rs")
+            .unwrap()
+            .last_modified()
+            .year(),
+        2020
+    );
+    // Without the time feature, the default time is 1980, before the epoch
+    #[cfg(feature = "time")]
+    assert_eq!(
+        archive.by_name("README").unwrap().last_modified().year(),
+        2022
+    );
+}
+
+#[test]
+fn sorted_entries() {
+    let time = DateTime::default();
+    let sorted = Reproducible::new().sort_entries(true);
+    let archive = write_archive(sorted, time, 0o644, 0o755);
+    let mut archive = ZipArchive::new(Cursor::new(archive)).unwrap();
+    let names: Vec<_> = (0..archive.len())
+        .map(|index| archive.by_index(index).unwrap().name().to_owned())
+        .collect();
+    assert_eq!(names, ["README", "build.sh", "run", "src/", "src/main.rs"]);
+}
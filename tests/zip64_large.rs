@@ -53,7 +53,7 @@
 // 22c400260  00 00 50 4b 05 06 00 00  00 00 03 00 03 00 27 01  |..PK..........'.|
 // 22c400270  00 00 ff ff ff ff 00 00                           |........|
 // 22c400278
-use std::io::{self, Read, Seek, SeekFrom};
+use std::io::{self, Read, Seek, SeekFrom, Write};
 
 const BLOCK1_LENGTH: u64 = 0x60;
 const BLOCK1: [u8; BLOCK1_LENGTH as usize] = [
@@ -144,14 +144,14 @@ impl Seek for Zip64File {
             }
             SeekFrom::End(offset) => {
                 if offset > 0 || offset < -(TOTAL_LENGTH as i64) {
-                    return Err(io::Error::new(io::ErrorKind::Other, "Invalid seek offset"));
+                    return Err(io::Error::other("Invalid seek offset"));
                 }
                 self.pointer = (TOTAL_LENGTH as i64 + offset) as u64;
             }
             SeekFrom::Current(offset) => {
                 let seekpos = self.pointer as i64 + offset;
                 if seekpos < 0 || seekpos as u64 > TOTAL_LENGTH {
-                    return Err(io::Error::new(io::ErrorKind::Other, "Invalid seek offset"));
+                    return Err(io::Error::other("Invalid seek offset"));
                 }
                 self.pointer = seekpos as u64;
             }
@@ -209,3 +209,128 @@ fn zip64_large() {
         };
     }
 }
+
+const ZEROS: [u8; 4096] = [0; 4096];
+
+/// An in-memory file which only stores written chunks that aren't all zeros.
+#[derive(Default)]
+struct SparseFile {
+    chunks: Vec<(u64, Vec<u8>)>,
+    pointer: u64,
+    len: u64,
+}
+
+impl Write for SparseFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Compare chunks with memcmp, checking each byte is slow without optimizations
+        if buf
+            .chunks(ZEROS.len())
+            .any(|chunk| chunk != &ZEROS[..chunk.len()])
+        {
+            self.chunks.push((self.pointer, buf.to_vec()));
+        } else {
+            // Zeros overwrite anything written before
+            self.chunks.retain(|(start, chunk)| {
+                *start + chunk.len() as u64 <= self.pointer
+                    || *start >= self.pointer + buf.len() as u64
+            });
+        }
+        self.pointer += buf.len() as u64;
+        self.len = self.len.max(self.pointer);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for SparseFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.pointer = match pos {
+            SeekFrom::Start(offset) => offset,
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset).unwrap(),
+            SeekFrom::Current(offset) => self.pointer.checked_add_signed(offset).unwrap(),
+        };
+        Ok(self.pointer)
+    }
+}
+
+impl Read for SparseFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf
+            .len()
+            .min(self.len.saturating_sub(self.pointer) as usize);
+        let buf = &mut buf[..len];
+        buf.fill(0);
+        for (start, chunk) in &self.chunks {
+            for (i, byte) in buf.iter_mut().enumerate() {
+                let position = self.pointer + i as u64;
+                if position >= *start && position < *start + chunk.len() as u64 {
+                    *byte = chunk[(position - start) as usize];
+                }
+            }
+        }
+        self.pointer += len as u64;
+        Ok(len)
+    }
+}
+
+#[test]
+fn zip64_promotion() {
+    const SIZE: u64 = (u32::MAX as u64) + 1;
+
+    let mut zip = ziperu::ZipWriter::new(SparseFile::default());
+    let options =
+        ziperu::write::FileOptions::default().compression_method(ziperu::CompressionMethod::Stored);
+    zip.start_file("large", options).unwrap();
+    let zeros = vec![0; 1 << 20];
+    let mut written = 0;
+    while written < SIZE {
+        let len = zeros.len().min((SIZE - written) as usize);
+        zip.write_all(&zeros[..len]).unwrap();
+        written += len as u64;
+    }
+    zip.start_file("small", options).unwrap();
+    zip.write_all(b"small").unwrap();
+    let mut file = zip.finish().unwrap();
+
+    // The local header was switched to a ZIP64 data descriptor
+    let mut header = [0; 30 + 5 + 20];
+    file.seek(SeekFrom::Start(0)).unwrap();
+    file.read_exact(&mut header).unwrap();
+    assert_eq!(u16::from_le_bytes([header[4], header[5]]), 45);
+    assert_eq!(
+        u16::from_le_bytes([header[6], header[7]]) & (1 << 3),
+        1 << 3
+    );
+    assert_eq!(header[14..18], [0; 4]);
+    assert_eq!(header[18..26], [0xff; 8]);
+    // The reserved space became a ZIP64 extra field, so readers expect 8 byte sizes
+    assert_eq!(u16::from_le_bytes([header[28], header[29]]), 20);
+    assert_eq!(&header[30..35], b"large");
+    assert_eq!(header[35..39], [0x01, 0x00, 0x10, 0x00]);
+
+    let mut archive = ziperu::ZipArchive::new(file).unwrap();
+    let large = archive.by_name("large").unwrap();
+    assert_eq!(large.size(), SIZE);
+    assert_eq!(large.compressed_size(), SIZE);
+    let descriptor_start = large.data_start() + SIZE;
+    drop(large);
+
+    let mut content = String::new();
+    archive
+        .by_name("small")
+        .unwrap()
+        .read_to_string(&mut content)
+        .unwrap();
+    assert_eq!(content, "small");
+
+    let mut descriptor = [0; 24];
+    let mut file = archive.into_inner();
+    file.seek(SeekFrom::Start(descriptor_start)).unwrap();
+    file.read_exact(&mut descriptor).unwrap();
+    assert_eq!(descriptor[..4], [0x50, 0x4b, 0x07, 0x08]);
+    assert_eq!(descriptor[8..16], SIZE.to_le_bytes());
+    assert_eq!(descriptor[16..], SIZE.to_le_bytes());
+}
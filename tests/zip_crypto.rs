@@ -121,3 +121,105 @@ fn issue_zip_old_280() {
     let mut data = Vec::new();
     file.read_to_end(&mut data).unwrap();
 }
+
+#[test]
+fn encrypting_file_streams_with_random_header() {
+    use std::io::Write;
+    use ziperu::unstable::write::FileOptionsExt;
+    use ziperu::write::FileOptions;
+
+    let content: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+    let write = || {
+        let mut archive = ziperu::write::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = FileOptions::default()
+            .compression_method(ziperu::CompressionMethod::Stored)
+            .with_deprecated_encryption(b"password");
+        archive.start_file("large", options).unwrap();
+        for chunk in content.chunks(1000) {
+            archive.write_all(chunk).unwrap();
+        }
+        archive.start_file("plain", FileOptions::default()).unwrap();
+        archive.write_all(b"plain").unwrap();
+        archive.finish().unwrap().into_inner()
+    };
+    let data = write();
+    assert_ne!(data, write(), "the encryption header should be random");
+
+    // Local header has the encryption and data descriptor flags
+    assert_eq!(u16::from_le_bytes([data[6], data[7]]) & 0b1001, 0b1001);
+
+    let mut archive = ziperu::ZipArchive::new(Cursor::new(&data)).unwrap();
+    let (data_end, crc32) = {
+        let file = archive.by_index_raw(0).unwrap();
+        (file.data_start() + file.compressed_size(), file.crc32())
+    };
+    let descriptor = &data[data_end as usize..][..16];
+    assert_eq!(descriptor[..4], [0x50, 0x4b, 0x07, 0x08]);
+    assert_eq!(descriptor[4..8], crc32.to_le_bytes());
+    assert_eq!(descriptor[8..12], (content.len() as u32 + 12).to_le_bytes());
+    assert_eq!(descriptor[12..], (content.len() as u32).to_le_bytes());
+
+    let mut decrypted = Vec::new();
+    archive
+        .by_index_decrypt(0, b"password")
+        .unwrap()
+        .unwrap()
+        .read_to_end(&mut decrypted)
+        .unwrap();
+    assert!(decrypted == content);
+
+    let mut plain = String::new();
+    archive
+        .by_name("plain")
+        .unwrap()
+        .read_to_string(&mut plain)
+        .unwrap();
+    assert_eq!(plain, "plain");
+}
+
+#[test]
+fn zip_crypto_encryption_info() {
+    let mut archive = ziperu::ZipArchive::new(Cursor::new(ZIP_CRYPTO_FILE)).unwrap();
+    assert_eq!(
+        archive.encryption(0).unwrap(),
+        Some(ziperu::EncryptionMethod::ZipCrypto)
+    );
+    archive.set_password_provider(|request| (request.attempt == 0).then(|| b"test".to_vec()));
+    let mut content = String::new();
+    archive
+        .by_index(0)
+        .unwrap()
+        .read_to_string(&mut content)
+        .unwrap();
+    assert_eq!(content, "abcdefghijklmnopqrstuvwxyz123456789");
+}
+
+#[test]
+fn encrypting_options_borrow_password() {
+    use std::io::Write;
+    use ziperu::unstable::write::FileOptionsExt;
+    use ziperu::write::FileOptions;
+
+    let password = String::from("password").into_bytes();
+    let options = FileOptions::default().with_deprecated_encryption(&password);
+
+    let mut archive = ziperu::write::ZipWriter::new(Cursor::new(Vec::new()));
+    // The options stay `Copy` and can be reused for several entries
+    for name in ["first", "second"] {
+        archive.start_file(name, options).unwrap();
+        archive.write_all(name.as_bytes()).unwrap();
+    }
+    let data = archive.finish().unwrap().into_inner();
+
+    let mut archive = ziperu::ZipArchive::new(Cursor::new(data)).unwrap();
+    for name in ["first", "second"] {
+        let mut content = String::new();
+        archive
+            .by_name_decrypt(name, &password)
+            .unwrap()
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, name);
+    }
+}
@@ -0,0 +1,301 @@
+#![cfg(feature = "zstd")]
+
+use std::cell::Cell;
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use ziperu::read::ZipFile;
+use ziperu::result::ZipResult;
+use ziperu::unstable::stream::{ZipStreamFileMetadata, ZipStreamReader, ZipStreamVisitor};
+use ziperu::write::{CompressionParams, FileOptions, ZstdParams};
+use ziperu::zstd_dictionary::ZstdDictionary;
+use ziperu::{CompressionMethod, ZipArchive, ZipWriter};
+
+fn documents() -> Vec<String> {
+    (0..500)
+        .map(|i| {
+            format!(
+                r#"{{"id": {i}, "name": "document {}", "created": "2024-01-{:02}", "tags": ["report", "draft"]}}"#,
+                i * 7,
+                i % 28 + 1
+            )
+        })
+        .collect()
+}
+
+fn options(dictionary: Option<u32>) -> FileOptions<'static> {
+    let options = FileOptions::default().compression_method(CompressionMethod::Zstd);
+    match dictionary {
+        Some(id) => options.compression_params(CompressionParams::Zstd(
+            ZstdParams::default().dictionary(id),
+        )),
+        None => options,
+    }
+}
+
+fn write(dictionary: Option<&ZstdDictionary>) -> Vec<u8> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    if let Some(dictionary) = dictionary {
+        zip.add_zstd_dictionary(dictionary.clone()).unwrap();
+    }
+    for (i, document) in documents().iter().enumerate() {
+        zip.start_file(format!("{i}.json"), options(dictionary.map(|d| d.id())))
+            .unwrap();
+        zip.write_all(document.as_bytes()).unwrap();
+    }
+    zip.finish().unwrap().into_inner()
+}
+
+fn total_compressed_size(archive: &mut ZipArchive<Cursor<Vec<u8>>>) -> u64 {
+    let mut size = 0;
+    for i in 0..archive.len() {
+        let file = archive.by_index_raw(i).unwrap();
+        if file.compression() == CompressionMethod::Zstd {
+            size += file.compressed_size();
+        }
+    }
+    size
+}
+
+/// Collects the names and contents of the entries of a stream
+struct Visitor(Vec<(String, Vec<u8>)>);
+
+impl ZipStreamVisitor for Visitor {
+    fn visit_file(&mut self, file: &mut ZipFile<'_>) -> ZipResult<()> {
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)?;
+        self.0.push((file.name().to_owned(), content));
+        Ok(())
+    }
+
+    fn visit_additional_metadata(&mut self, _: &ZipStreamFileMetadata) -> ZipResult<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn dictionary_roundtrip() {
+    let dictionary = ZstdDictionary::train(&documents(), 2048).unwrap();
+    let mut archive = ZipArchive::new(Cursor::new(write(Some(&dictionary)))).unwrap();
+
+    let name = format!(".zstd-dictionaries/{}", dictionary.id());
+    let mut stored = Vec::new();
+    archive
+        .by_name(&name)
+        .unwrap()
+        .read_to_end(&mut stored)
+        .unwrap();
+    assert_eq!(stored, dictionary.as_bytes());
+
+    for (i, document) in documents().iter().enumerate() {
+        let mut content = String::new();
+        archive
+            .by_name(&format!("{i}.json"))
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(&content, document);
+    }
+
+    let mut without = ZipArchive::new(Cursor::new(write(None))).unwrap();
+    assert!(total_compressed_size(&mut archive) < total_compressed_size(&mut without) / 2);
+}
+
+#[test]
+fn dictionary_stream() {
+    let dictionary = ZstdDictionary::train(&documents(), 2048).unwrap();
+    let mut visitor = Visitor(Vec::new());
+    ZipStreamReader::new(Cursor::new(write(Some(&dictionary))))
+        .visit(&mut visitor)
+        .unwrap();
+
+    // The visitor still sees the dictionary entry
+    assert_eq!(
+        visitor.0[0],
+        (
+            format!(".zstd-dictionaries/{}", dictionary.id()),
+            dictionary.as_bytes().to_vec()
+        )
+    );
+    for ((name, content), (i, document)) in
+        visitor.0[1..].iter().zip(documents().iter().enumerate())
+    {
+        assert_eq!(name, &format!("{i}.json"));
+        assert_eq!(content, document.as_bytes());
+    }
+}
+
+#[test]
+fn missing_dictionary() {
+    let dictionary = ZstdDictionary::train(&documents(), 2048).unwrap();
+    let mut data = write(Some(&dictionary));
+    // Rename the dictionary entry in the local and central headers
+    let name = b".zstd-dictionaries/";
+    for i in 0..data.len() - name.len() {
+        if data[i..].starts_with(name) {
+            data[i + 1] = b'Z';
+        }
+    }
+
+    let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    let mut file = archive.by_name("0.json").unwrap();
+    assert!(file.read_to_end(&mut Vec::new()).is_err());
+}
+
+#[test]
+fn invalid_dictionary_entries() {
+    let dictionary = ZstdDictionary::train(&documents(), 2048).unwrap();
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    // Not a dictionary, compressed, and stored under another id
+    for (id, method, content) in [
+        (1, CompressionMethod::Stored, &b"not a dictionary"[..]),
+        (2, CompressionMethod::Zstd, dictionary.as_bytes()),
+        (3, CompressionMethod::Stored, dictionary.as_bytes()),
+    ] {
+        zip.start_file(
+            format!(".zstd-dictionaries/{id}"),
+            FileOptions::default().compression_method(method),
+        )
+        .unwrap();
+        zip.write_all(content).unwrap();
+    }
+    zip.add_zstd_dictionary(dictionary.clone()).unwrap();
+    zip.start_file("plain.json", options(None)).unwrap();
+    zip.write_all(documents()[0].as_bytes()).unwrap();
+    zip.start_file("dictionary.json", options(Some(dictionary.id())))
+        .unwrap();
+    zip.write_all(documents()[1].as_bytes()).unwrap();
+    let data = zip.finish().unwrap().into_inner();
+
+    let mut archive = ZipArchive::new(Cursor::new(data.clone())).unwrap();
+    for (name, document) in [("plain.json", 0), ("dictionary.json", 1)] {
+        let mut content = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, documents()[document]);
+    }
+
+    let mut visitor = Visitor(Vec::new());
+    ZipStreamReader::new(Cursor::new(data))
+        .visit(&mut visitor)
+        .unwrap();
+    assert_eq!(visitor.0.len(), 6);
+    assert_eq!(visitor.0[0].1, b"not a dictionary");
+    assert_eq!(visitor.0[1].1, dictionary.as_bytes());
+    assert_eq!(visitor.0[5].1, documents()[1].as_bytes());
+}
+
+#[test]
+fn oversized_dictionary_entry() {
+    let dictionary = ZstdDictionary::train(&documents(), 2048).unwrap();
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.start_file(
+        format!(".zstd-dictionaries/{}", dictionary.id()),
+        FileOptions::default().compression_method(CompressionMethod::Stored),
+    )
+    .unwrap();
+    zip.write_all(dictionary.as_bytes()).unwrap();
+    zip.write_all(&vec![0; 8 << 20]).unwrap();
+    // The dictionary entry already exists, so it is only registered
+    zip.add_zstd_dictionary(dictionary.clone()).unwrap();
+    zip.start_file("file.json", options(Some(dictionary.id())))
+        .unwrap();
+    zip.write_all(documents()[0].as_bytes()).unwrap();
+    let data = zip.finish().unwrap().into_inner();
+
+    // The entry is ignored, rather than used as a dictionary
+    let missing = "The zstd dictionary of the entry is missing";
+    let mut archive = ZipArchive::new(Cursor::new(data.clone())).unwrap();
+    let mut file = archive.by_name("file.json").unwrap();
+    let err = file.read_to_end(&mut Vec::new()).unwrap_err();
+    assert!(err.to_string().contains(missing));
+
+    let err = ZipStreamReader::new(Cursor::new(data))
+        .visit(&mut Visitor(Vec::new()))
+        .unwrap_err();
+    assert!(err.to_string().contains(missing));
+}
+
+/// Counts the bytes read from a cursor
+struct CountingReader<'a>(Cursor<Vec<u8>>, &'a Cell<u64>);
+
+impl Read for CountingReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.0.read(buf)?;
+        self.1.set(self.1.get() + read as u64);
+        Ok(read)
+    }
+}
+
+impl Seek for CountingReader<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.0.seek(pos)
+    }
+}
+
+#[test]
+fn dictionaries_loaded_when_used() {
+    let dictionary = ZstdDictionary::train(&documents(), 2048).unwrap();
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.add_zstd_dictionary(dictionary.clone()).unwrap();
+    zip.start_file("plain.json", options(None)).unwrap();
+    zip.write_all(documents()[0].as_bytes()).unwrap();
+    zip.start_file("dictionary.json", options(Some(dictionary.id())))
+        .unwrap();
+    zip.write_all(documents()[1].as_bytes()).unwrap();
+    let data = zip.finish().unwrap().into_inner();
+
+    let read = Cell::new(0);
+    let mut archive = ZipArchive::new(CountingReader(Cursor::new(data), &read)).unwrap();
+    for (name, document, loaded) in [("plain.json", 0, false), ("dictionary.json", 1, true)] {
+        read.set(0);
+        let mut content = String::new();
+        archive
+            .by_name(name)
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, documents()[document]);
+        // Loading the dictionary reads its entry
+        let size = dictionary.as_bytes().len() as u64;
+        assert_eq!(read.get() > size, loaded);
+    }
+}
+
+#[test]
+fn unknown_dictionary() {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    assert!(zip.start_file("file.json", options(Some(1))).is_err());
+}
+
+#[test]
+fn invalid_dictionary() {
+    assert!(ZstdDictionary::new(b"not a dictionary".to_vec()).is_err());
+}
+
+#[test]
+fn add_dictionary_again() {
+    let dictionary = ZstdDictionary::train(&documents(), 2048).unwrap();
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.add_zstd_dictionary(dictionary.clone()).unwrap();
+    zip.add_zstd_dictionary(dictionary.clone()).unwrap();
+    let data = zip.finish().unwrap();
+
+    // Appending doesn't store the dictionary a second time
+    let mut zip = ZipWriter::new_append(data).unwrap();
+    zip.add_zstd_dictionary(dictionary.clone()).unwrap();
+    zip.start_file("file.json", options(Some(dictionary.id())))
+        .unwrap();
+    zip.write_all(documents()[0].as_bytes()).unwrap();
+    let mut archive = ZipArchive::new(zip.finish().unwrap()).unwrap();
+    assert_eq!(archive.len(), 2);
+
+    let mut content = String::new();
+    archive
+        .by_name("file.json")
+        .unwrap()
+        .read_to_string(&mut content)
+        .unwrap();
+    assert_eq!(content, documents()[0]);
+}